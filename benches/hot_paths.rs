@@ -0,0 +1,96 @@
+//! Benchmarks for what sweeps spend their time on. Every fixture is seeded, so runs on
+//! different commits compare the same work. Run with `cargo bench`.
+
+use avail_lc_sim::{ExperimentConfig, Grid, Grid2dErasure, SampleStrategy, Shape};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rand::{rngs::SmallRng, SeedableRng};
+
+/// A `2n x 2n` grid with `density` of its cells sampled at random
+fn sampled_grid(n: usize, density: f64) -> Grid {
+    let side = 2 * n;
+    let mut grid = Grid::new(side, side);
+    let amount = (density * (side * side) as f64) as usize;
+    grid.sample(
+        &mut SmallRng::seed_from_u64(0),
+        amount,
+        &SampleStrategy::RandomPoints,
+    );
+    grid
+}
+
+fn sample(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sample");
+    for n in [256, 1024] {
+        let side = 2 * n;
+        // A network's worth of samples: an eighth of the grid
+        let amount = side * side / 8;
+        let strategies = [
+            ("RandomPoints", SampleStrategy::RandomPoints),
+            (
+                "Box",
+                SampleStrategy::box_checked(side, side, 4, 4).unwrap(),
+            ),
+        ];
+        for (name, strategy) in strategies {
+            let mut rng = SmallRng::seed_from_u64(0);
+            let mut grid = Grid::new(side, side);
+            group.bench_function(BenchmarkId::new(name, n), |b| {
+                b.iter(|| {
+                    grid.clear();
+                    grid.sample(&mut rng, amount, &strategy);
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn reconstruct_fixpoint(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reconstruct_fixpoint");
+    let n = 256;
+    // Around where random samples stop being enough to recover the whole grid
+    for density in [0.2, 0.25, 0.3, 0.4] {
+        let g = Grid2dErasure::from_grid(sampled_grid(n, density), Shape::square(n)).unwrap();
+        group.bench_function(BenchmarkId::new(n.to_string(), density), |b| {
+            b.iter_batched(
+                || g.clone(),
+                |mut g| g.reconstruct_fixpoint(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn col_row_counts(c: &mut Criterion) {
+    let grid = sampled_grid(256, 0.25);
+    c.bench_function("col_row_counts/256", |b| b.iter(|| grid.col_row_counts()));
+}
+
+fn transpose(c: &mut Criterion) {
+    let grid = Grid::random(512, 2048, 0.5, &mut SmallRng::seed_from_u64(0)).unwrap();
+    c.bench_function("transpose/512x2048", |b| b.iter(|| grid.transpose()));
+}
+
+fn trial(c: &mut Criterion) {
+    // A network of a thousand clients sampling a 64 x 64 grid, a fifth of them censored
+    let e = ExperimentConfig {
+        n: 64,
+        n_clients: 1000,
+        percent_censored: 0.2,
+        n_samples: 15,
+        ..Default::default()
+    };
+    // Includes drawing the mask, which every run of a config does once
+    c.bench_function("trial/64", |b| b.iter(|| e.run_trials_seeded(0, 0..1)));
+}
+
+criterion_group!(
+    benches,
+    sample,
+    reconstruct_fixpoint,
+    col_row_counts,
+    transpose,
+    trial
+);
+criterion_main!(benches);
@@ -1,6 +1,4 @@
-use avail_lc_sim::{ExperimentConfig, SampleStrategy};
-use indicatif::ParallelProgressIterator;
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use avail_lc_sim::{run_sweep_results, Dims, ExperimentConfig, SampleStrategy};
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -20,15 +18,28 @@ fn main() -> Result<(), Box<dyn Error>> {
                         continue;
                     }
                     let n_samples = target_n_samples / wh;
-                    for dims in [1, 2] {
+                    for dims in [Dims::One, Dims::Two] {
+                        let (grid_w, grid_h) = match dims {
+                            Dims::One => (n, 2 * n),
+                            _ => (2 * n, 2 * n),
+                        };
+                        // skip boxes that don't tile the grid
+                        let Ok(sample_strategy) =
+                            SampleStrategy::box_checked(grid_w, grid_h, width, height)
+                        else {
+                            continue;
+                        };
                         let e = ExperimentConfig {
                             n,
                             dims,
                             n_clients,
                             percent_censored,
                             n_samples,
-                            sample_strategy: SampleStrategy::Box { width, height },
-                        };
+                            ..Default::default()
+                        }
+                        .with_sample_strategy(sample_strategy);
+                        // Stop before running anything rather than partway through the sweep
+                        e.validate().map_err(avail_lc_sim::Error::InvalidConfig)?;
                         exps.push(e);
                     }
                 }
@@ -36,17 +47,13 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    let results = exps
-        .par_iter()
-        .progress_count(exps.len() as u64)
-        .map(|e| (e, e.run()))
-        .collect::<Vec<_>>();
+    let results = run_sweep_results(exps);
 
     println!("Writing");
     let mut writer = csv::Writer::from_path("block_sampling.csv")?;
     writer.write_record(ExperimentConfig::header())?;
-    for (e, prob) in results {
-        writer.write_record(e.to_row(prob))?;
+    for (e, result) in results {
+        writer.write_record(e.to_row(&result))?;
     }
     writer.flush()?;
     Ok(())
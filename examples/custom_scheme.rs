@@ -0,0 +1,123 @@
+use avail_lc_sim::{Dims, Error, ExperimentConfig, Grid, Reconstructable, SampleStrategy, Shape};
+use rand::{distributions::Uniform, prelude::Distribution, RngCore};
+
+/// A repetition code: the original data is stored `extension` times, one copy below the
+/// other, and a cell can only be recovered from one of its own copies
+struct Repetition {
+    shape: Shape,
+    grid: Grid,
+}
+
+impl Reconstructable for Repetition {
+    type Index = (usize, usize);
+
+    // Only used in error messages, there's no `Dims` for this scheme
+    fn dims() -> Dims {
+        Dims::One
+    }
+
+    fn random_index<R: RngCore>(rng: &mut R, shape: Shape) -> Self::Index {
+        let row = Uniform::from(0..shape.rows).sample(rng);
+        let col = Uniform::from(0..shape.cols).sample(rng);
+        (row, col)
+    }
+
+    fn new_mask_at(shape: Shape, (row, col): Self::Index) -> (Grid, Self::Index) {
+        assert!(
+            row < shape.rows && col < shape.cols,
+            "target must be in the original data"
+        );
+        // Censor every copy of the target
+        let mut mask = Grid::new(shape.cols, shape.extension * shape.rows);
+        for copy in 0..shape.extension {
+            mask.set(row + copy * shape.rows, col, true);
+        }
+        (mask.not(), (row, col))
+    }
+
+    fn new(shape: Shape) -> Self {
+        Repetition {
+            shape,
+            grid: Grid::new(shape.cols, shape.extension * shape.rows),
+        }
+    }
+
+    fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    fn can_reconstruct(&self, (row, col): Self::Index) -> bool {
+        let rows = self.shape.rows;
+        (0..self.shape.extension).any(|copy| self.grid.get(row % rows + copy * rows, col))
+    }
+
+    fn reconstruction_closure(&self) -> Grid {
+        let mut closure = self.grid.clone();
+        for row in 0..closure.h() {
+            for col in 0..closure.w() {
+                if self.can_reconstruct((row, col)) {
+                    closure.set(row, col, true);
+                }
+            }
+        }
+        closure
+    }
+
+    fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn sample<R: RngCore>(&mut self, rng: &mut R, amount: usize, strategy: &SampleStrategy) {
+        self.grid.sample(rng, amount, strategy)
+    }
+
+    fn sample_exclusion<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        amount: usize,
+        strategy: &SampleStrategy,
+        mask: &Grid,
+    ) -> usize {
+        self.sample(rng, amount, strategy);
+        let sampled = self.grid.count_ones();
+        self.grid.and_inplace(mask);
+        sampled - self.grid.count_ones()
+    }
+
+    fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64) {
+        self.grid.thin(rng, keep)
+    }
+
+    fn reset(&mut self) {
+        self.grid.clear()
+    }
+
+    fn merge_from(&mut self, other: &Self) {
+        assert_eq!(self.shape, other.shape);
+        self.grid.or_inplace(&other.grid);
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Compares the repetition code with the 1d erasure code it has the same overhead as,
+    // written out like any other sweep
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    writer.write_record(ExperimentConfig::header())?;
+    for n_clients in [10, 20, 40, 80] {
+        let e = ExperimentConfig {
+            n: 16,
+            dims: Dims::One,
+            n_clients,
+            percent_censored: 0.2,
+            n_samples: 10,
+            ..Default::default()
+        };
+        e.validate_for::<Repetition>()
+            .map_err(Error::InvalidConfig)?;
+        let result = e.run_generic_result_seeded::<Repetition>(0);
+        writer.write_record(e.to_row_for_scheme("repetition", &result))?;
+        writer.write_record(e.to_row(&e.run_result_seeded(0)))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use std::process;
+
+use avail_lc_sim::{find_critical_clients, find_critical_samples, ExperimentConfig};
+use serde_json::{Map, Value};
+
+const USAGE: &str = "usage: search clients <template> <target> <lo> <hi>
+       search samples <template> <target> <max_samples>
+
+<template> is an ExperimentConfig as JSON, where any field that's left out keeps its
+default, e.g. '{\"n\": 16, \"percent_censored\": 0.2}'";
+
+/// Reads a config from JSON, filling in every field that's left out from the default
+fn parse_template(json: &str) -> Result<ExperimentConfig, serde_json::Error> {
+    let fields: Map<String, Value> = serde_json::from_str(json)?;
+    let mut config = serde_json::to_value(ExperimentConfig::default())?;
+    config
+        .as_object_mut()
+        .expect("configs serialize to objects")
+        .extend(fields);
+    serde_json::from_value(config)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // e.g. `cargo run --release --example search -- clients '{"n": 16}' 0.99 1 1000`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["clients", template, target, lo, hi] => {
+            let template = parse_template(template)?;
+            let point =
+                find_critical_clients(&template, target.parse()?, lo.parse()?, hi.parse()?)?;
+            println!("{} clients: {}", point.below, point.below_prob);
+            println!("{} clients: {}", point.above, point.above_prob);
+        }
+        ["samples", template, target, max_samples] => {
+            let template = parse_template(template)?;
+            let samples = find_critical_samples(&template, target.parse()?, max_samples.parse()?)?;
+            let (below_cells, above_cells) = samples.cell_budget();
+            let point = samples.point;
+            println!(
+                "{} samples ({} cells): {}",
+                point.below, below_cells, point.below_prob
+            );
+            println!(
+                "{} samples ({} cells): {}",
+                point.above, above_cells, point.above_prob
+            );
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,4 @@
-use avail_lc_sim::{ExperimentConfig, SampleStrategy};
-use indicatif::ParallelProgressIterator;
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use avail_lc_sim::{run_sweep_results, Dims, ExperimentConfig, SampleStrategy};
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -10,15 +8,18 @@ fn main() -> Result<(), Box<dyn Error>> {
         for n_clients in (50..=1000).step_by(50) {
             for percent_censored in [0.00, 0.2, 0.4, 0.6, 0.8, 0.9] {
                 for n in [16, 32, 64, 128] {
-                    for dims in [1, 2] {
+                    for dims in [Dims::One, Dims::Two] {
                         let e = ExperimentConfig {
                             n,
                             dims,
                             n_clients,
                             percent_censored,
                             n_samples,
-                            sample_strategy: SampleStrategy::RandomPoints,
-                        };
+                            ..Default::default()
+                        }
+                        .with_sample_strategy(SampleStrategy::RandomPoints);
+                        // Stop before running anything rather than partway through the sweep
+                        e.validate().map_err(avail_lc_sim::Error::InvalidConfig)?;
                         exps.push(e);
                     }
                 }
@@ -26,17 +27,13 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    let results = exps
-        .par_iter()
-        .progress_count(exps.len() as u64)
-        .map(|e| (e, e.run()))
-        .collect::<Vec<_>>();
+    let results = run_sweep_results(exps);
 
     println!("Writing");
     let mut writer = csv::Writer::from_path("small_grids.csv")?;
     writer.write_record(ExperimentConfig::header())?;
-    for (e, prob) in results {
-        writer.write_record(e.to_row(prob))?;
+    for (e, result) in results {
+        writer.write_record(e.to_row(&result))?;
     }
     writer.flush()?;
     Ok(())
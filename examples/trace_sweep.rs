@@ -0,0 +1,24 @@
+use avail_lc_sim::{run_sweep, ExperimentConfig};
+use tracing_subscriber::EnvFilter;
+
+fn main() {
+    // e.g. `RUST_LOG=avail_lc_sim=debug` for every trial's reconstruction rounds and merged
+    // grid density, or `RUST_LOG=avail_lc_sim=info` for just when each config finishes
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    let configs = [20, 40, 80]
+        .into_iter()
+        .map(|n_clients| ExperimentConfig {
+            n: 16,
+            n_clients,
+            percent_censored: 0.2,
+            n_samples: 15,
+            ..Default::default()
+        })
+        .collect();
+    for (e, prob) in run_sweep(configs) {
+        println!("n_clients = {}: {}", e.n_clients, prob);
+    }
+}
@@ -0,0 +1,31 @@
+#!/bin/sh
+# Builds the Python bindings into a fresh virtualenv and runs one tiny config with them
+set -e
+cd "$(dirname "$0")/.."
+venv="target/python-smoke"
+python3 -m venv "$venv"
+"$venv/bin/pip" install --quiet maturin
+"$venv/bin/maturin" build --release --out "$venv/wheels"
+"$venv/bin/pip" install --quiet --force-reinstall "$venv"/wheels/*.whl
+"$venv/bin/python" - <<'PY'
+import avail_lc_sim
+
+e = avail_lc_sim.ExperimentConfig(
+    n=4, n_clients=10, n_samples=3, percent_censored=0.5, strategy="RandomPoints"
+)
+result = e.run()
+assert result["trials"] > 0, result
+assert 0.0 <= result["prob"] <= 1.0, result
+assert result["successes"] == round(result["prob"] * result["trials"]), result
+
+boxes = avail_lc_sim.ExperimentConfig(n=4, n_clients=10, strategy="Box:2x2")
+sweep = avail_lc_sim.run_sweep([e, boxes], 2)
+assert len(sweep) == 2 and sweep[0]["trials"] == result["trials"], sweep
+
+try:
+    avail_lc_sim.ExperimentConfig(strategy="Triangles")
+    raise AssertionError("bad strategies should be rejected")
+except ValueError:
+    pass
+print("python bindings ok:", result)
+PY
@@ -0,0 +1,6 @@
+#!/bin/sh
+# Builds the library for wasm32 without rayon and runs its wasm tests, including a 16x16
+# config end to end, under node
+set -e
+cd "$(dirname "$0")/.."
+wasm-pack test --node -- --no-default-features --features wasm
@@ -1,19 +1,198 @@
 use std::{
     fmt::{Debug, Display},
-    ops::{BitOr, Not},
+    hash::{Hash, Hasher},
+    ops::{BitAnd, BitOr, BitXor, Range},
+    str::FromStr,
+    sync::OnceLock,
 };
 
 use bitvec_simd::BitVec;
-use rand::{distributions::Uniform, prelude::Distribution, RngCore};
+use rand::{
+    distributions::{Bernoulli, Uniform},
+    prelude::Distribution,
+    seq::index,
+    RngCore,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Splits `Name:args` into the name and its arguments, if there are any
+fn split_args(s: &str) -> (&str, Option<&str>) {
+    match s.split_once(':') {
+        Some((name, args)) => (name, Some(args)),
+        None => (s, None),
+    }
+}
+
+/// The bits of `bv` as words, 64 bits each with the first in the lowest bit. Bits past
+/// `bv.len()` in the last word are padding and can be anything.
+fn bv_words(bv: &BitVec) -> &[u64] {
+    // SAFETY: `bv` keeps its bits in `storage_len` contiguous `u64x4`s, each of which is
+    // four `u64`s in order, and they can't change while `bv` is borrowed
+    unsafe { std::slice::from_raw_parts(bv.as_ptr().cast::<u64>(), bv.storage_len() * 4) }
+}
+
+/// Bits `start..start + len` of the bitvec stored in `words`, where `len` is at most 64,
+/// as the low bits of a word
+fn read_bits(words: &[u64], start: usize, len: usize) -> u64 {
+    let (k, offset) = (start / 64, start % 64);
+    let mut bits = words[k] >> offset;
+    if offset > 0 && offset + len > 64 {
+        bits |= words[k + 1] << (64 - offset);
+    }
+    if len < 64 {
+        bits &= (1 << len) - 1;
+    }
+    bits
+}
+
+/// Ors `bits` into the bitvec stored in `words`, starting at bit `start`
+fn or_bits(words: &mut [u64], start: usize, bits: u64) {
+    let (k, offset) = (start / 64, start % 64);
+    words[k] |= bits << offset;
+    if offset > 0 && bits >> (64 - offset) != 0 {
+        words[k + 1] |= bits >> (64 - offset);
+    }
+}
+
+/// How many binary digits of a density `random_word` keeps
+const DENSITY_BITS: u32 = 16;
+
+/// Densities closer than this to 0 or 1 are too far off once rounded to `DENSITY_BITS`,
+/// so `Grid::random` draws each of their cells on its own
+const EXTREME_DENSITY: f64 = 1.0 / 256.0;
 
-#[derive(PartialEq, Clone)]
+/// A word whose bits are each set independently with probability `density`, rounded to
+/// `DENSITY_BITS` binary digits. Starting from the lowest digit, each digit ors in a
+/// random word if it's 1 and ands one in if it's 0, which takes the probability from
+/// `p` to `(1 + p) / 2` or `p / 2`, so after the last digit it's the whole fraction.
+fn random_word<R: RngCore>(rng: &mut R, density: f64) -> u64 {
+    let digits = (density * (1 << DENSITY_BITS) as f64).round() as u64;
+    if digits >> DENSITY_BITS != 0 {
+        return u64::MAX;
+    }
+    // Digits below the lowest 1 would only and zeros with zeros
+    let mut word = 0;
+    for digit in digits.trailing_zeros().min(DENSITY_BITS)..DENSITY_BITS {
+        word = if digits >> digit & 1 == 1 {
+            word | rng.next_u64()
+        } else {
+            word & rng.next_u64()
+        };
+    }
+    word
+}
+
+/// Sets bits `range` of the bitvec stored in `words`, a whole word at a time
+fn set_word_range(words: &mut [u64], range: Range<usize>) {
+    let mut ind = range.start;
+    while ind < range.end {
+        let offset = ind % 64;
+        let n = (64 - offset).min(range.end - ind);
+        words[ind / 64] |= (u64::MAX >> (64 - n)) << offset;
+        ind += n;
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "GridBytes", into = "GridBytes")]
 pub struct Grid {
+    // the cells stored column wise, `coord_to_ind`
     bv: BitVec,
+    // the same cells stored row wise, `coord_to_row_ind`, so rows can be counted as cheaply
+    // as columns. Always kept in sync with `bv`.
+    row_bv: BitVec,
+    // How many cells are set in each column and row, only kept once `track_counts` has
+    // been called
+    counts: Option<LineCounts>,
+    // Counts computed on demand when they aren't tracked, thrown away whenever a cell changes
+    cached_counts: OnceLock<LineCounts>,
+    w: usize,
+    h: usize,
+}
+
+#[derive(Clone)]
+struct LineCounts {
+    cols: Vec<usize>,
+    rows: Vec<usize>,
+}
+
+/// How a `Grid` is serialized: its cells packed eight to a byte in `coord_to_ind` order,
+/// rather than a bool per cell
+#[derive(Serialize, Deserialize)]
+struct GridBytes {
     w: usize,
     h: usize,
+    bits: Vec<u8>,
+}
+
+impl From<Grid> for GridBytes {
+    fn from(grid: Grid) -> Self {
+        let nbits = grid.w * grid.h;
+        let mut bits = vec![0u8; nbits.div_ceil(8)];
+        for ind in (0..nbits).filter(|&ind| grid.bv.get_unchecked(ind)) {
+            bits[ind / 8] |= 1 << (ind % 8);
+        }
+        GridBytes {
+            w: grid.w,
+            h: grid.h,
+            bits,
+        }
+    }
+}
+
+impl TryFrom<GridBytes> for Grid {
+    type Error = String;
+
+    fn try_from(GridBytes { w, h, bits }: GridBytes) -> Result<Self, Self::Error> {
+        let nbits = w
+            .checked_mul(h)
+            .ok_or_else(|| format!("a {}x{} grid is too large", w, h))?;
+        if bits.len() != nbits.div_ceil(8) {
+            return Err(format!(
+                "a {}x{} grid needs {} bytes, got {}",
+                w,
+                h,
+                nbits.div_ceil(8),
+                bits.len()
+            ));
+        }
+        // Anything set past the last cell means the buffer wasn't written for this grid
+        if nbits % 8 != 0 && bits[nbits / 8] >> (nbits % 8) != 0 {
+            return Err(format!("bits set past the end of a {}x{} grid", w, h));
+        }
+        let mut grid = Grid::new(w, h);
+        for ind in (0..nbits).filter(|&ind| (bits[ind / 8] >> (ind % 8)) & 1 == 1) {
+            let (row, col) = grid.ind_to_coord(ind);
+            grid.set(row, col, true);
+        }
+        Ok(grid)
+    }
+}
+
+/// A cell that is outside the grid it was looked up in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridIndexError {
+    pub row: usize,
+    pub col: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+impl Display for GridIndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "({}, {}) is outside a {}x{} grid",
+            self.row, self.col, self.w, self.h
+        )
+    }
 }
 
-#[derive(Debug, Clone)]
+impl std::error::Error for GridIndexError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SampleStrategy {
     /// Split the grid into width x height chunks, then sample those
     Box {
@@ -22,16 +201,145 @@ pub enum SampleStrategy {
         /// same as `width` but for columns
         height: usize,
     },
+    /// Sample width x height boxes at uniformly random origins, so unlike `Box` the size
+    /// doesn't have to divide the grid. Boxes may overlap.
+    SlidingBox {
+        /// How wide each box is. Must be at most the grid width.
+        width: usize,
+        /// same as `width` but for columns
+        height: usize,
+    },
+    /// Sample `lines` distinct rows or columns at random and take every cell in them, as
+    /// line-based DAS does. Lines may repeat between samples.
+    FullLines {
+        /// How many lines each sample takes. Must be at most the number of lines.
+        lines: usize,
+        axis: Axis,
+    },
     /// Sample cells uniformly at random
     RandomPoints,
 }
 
+/// Which lines of a grid `SampleStrategy::FullLines` samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Axis {
+    Row,
+    Column,
+}
+
+impl SampleStrategy {
+    /// A `Box` strategy for a `grid_w x grid_h` grid. Errors unless the boxes tile the grid
+    /// exactly, otherwise the cells past the last whole box would never be sampled.
+    pub fn box_checked(
+        grid_w: usize,
+        grid_h: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<SampleStrategy, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidStrategy(format!(
+                "{}x{} boxes must be non-empty",
+                width, height
+            )));
+        }
+        if grid_w % width != 0 || grid_h % height != 0 {
+            return Err(Error::InvalidStrategy(format!(
+                "{}x{} boxes don't evenly divide a {}x{} grid",
+                width, height, grid_w, grid_h
+            )));
+        }
+        Ok(SampleStrategy::Box { width, height })
+    }
+
+    /// Errors unless this strategy can sample a `grid_w x grid_h` grid: `Box`es have to
+    /// tile it as in `box_checked`, `SlidingBox`es have to fit in it, and `FullLines` can't
+    /// take more lines than it has
+    pub fn check_fits(&self, grid_w: usize, grid_h: usize) -> Result<(), Error> {
+        match *self {
+            SampleStrategy::Box { width, height } => {
+                Self::box_checked(grid_w, grid_h, width, height).map(drop)
+            }
+            SampleStrategy::SlidingBox { width, height } => {
+                if width == 0 || height == 0 || width > grid_w || height > grid_h {
+                    return Err(Error::InvalidStrategy(format!(
+                        "{}x{} sliding boxes don't fit in a {}x{} grid",
+                        width, height, grid_w, grid_h
+                    )));
+                }
+                Ok(())
+            }
+            SampleStrategy::FullLines { lines, axis } => {
+                let n_lines = match axis {
+                    Axis::Row => grid_h,
+                    Axis::Column => grid_w,
+                };
+                if lines == 0 || lines > n_lines {
+                    return Err(Error::InvalidStrategy(format!(
+                        "can't sample {} of the {} {:?}s of a {}x{} grid",
+                        lines, n_lines, axis, grid_w, grid_h
+                    )));
+                }
+                Ok(())
+            }
+            SampleStrategy::RandomPoints => Ok(()),
+        }
+    }
+}
+
+/// Parses `RandomPoints`, or `Box:<width>x<height>` such as `Box:2x4`, or
+/// `SlidingBox:<width>x<height>`, or `FullLines:<lines>:<Row|Column>` such as
+/// `FullLines:2:Row`
+impl FromStr for SampleStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let box_size = |size: &str| {
+            size.split_once('x')
+                .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                .ok_or_else(|| format!("{:?} isn't a box size like 2x4", size))
+        };
+        match split_args(s) {
+            ("RandomPoints", None) => Ok(SampleStrategy::RandomPoints),
+            ("Box", Some(size)) => {
+                let (width, height) = box_size(size)?;
+                Ok(SampleStrategy::Box { width, height })
+            }
+            ("SlidingBox", Some(size)) => {
+                let (width, height) = box_size(size)?;
+                Ok(SampleStrategy::SlidingBox { width, height })
+            }
+            ("FullLines", Some(args)) => {
+                let bad = || format!("{:?} isn't a line count and axis like 2:Row", args);
+                let (lines, axis) = args.split_once(':').ok_or_else(bad)?;
+                let lines = lines.parse().map_err(|_| bad())?;
+                let axis = match axis {
+                    "Row" => Axis::Row,
+                    "Column" => Axis::Column,
+                    _ => return Err(bad()),
+                };
+                Ok(SampleStrategy::FullLines { lines, axis })
+            }
+            _ => Err(format!(
+                "unknown sample strategy {:?}, expected RandomPoints, Box:<width>x<height>, \
+                 SlidingBox:<width>x<height> or FullLines:<lines>:<Row|Column>",
+                s
+            )),
+        }
+    }
+}
+
 impl Display for SampleStrategy {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SampleStrategy::Box { .. } => {
                 write!(f, "Box")
             }
+            SampleStrategy::SlidingBox { .. } => {
+                write!(f, "SlidingBox")
+            }
+            SampleStrategy::FullLines { .. } => {
+                write!(f, "FullLines")
+            }
             SampleStrategy::RandomPoints => {
                 write!(f, "RandomPoints")
             }
@@ -39,12 +347,200 @@ impl Display for SampleStrategy {
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MaskStrategy {
+    /// Censor the target, enough of its row and column that neither can be recovered
+    /// directly, and the whole block of parity cells they would need
+    #[default]
+    Block,
+    /// Withhold `count` entire rows, one of which contains the target. Only supported in 2d,
+    /// where `count` must be larger than `n` for the rows to be unrecoverable.
+    WithholdRows { count: usize },
+}
+
+/// Parses `Block`, or `WithholdRows:<count>` such as `WithholdRows:5`
+impl FromStr for MaskStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match split_args(s) {
+            ("Block", None) => Ok(MaskStrategy::Block),
+            ("WithholdRows", Some(count)) => Ok(MaskStrategy::WithholdRows {
+                count: count
+                    .parse()
+                    .map_err(|_| format!("{:?} isn't a number of rows", count))?,
+            }),
+            _ => Err(format!(
+                "unknown mask strategy {:?}, expected Block or WithholdRows:<count>",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for MaskStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaskStrategy::Block => {
+                write!(f, "Block")
+            }
+            MaskStrategy::WithholdRows { .. } => {
+                write!(f, "WithholdRows")
+            }
+        }
+    }
+}
+
+/// Why a mask doesn't censor its target, see `Grid2dErasure::validate_mask`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaskError {
+    /// The mask is `w x h`, but the grid is `expected_w x expected_h`
+    WrongSize {
+        w: usize,
+        h: usize,
+        expected_w: usize,
+        expected_h: usize,
+    },
+    /// The target isn't in the original data
+    TargetOutside { row: usize, col: usize },
+    /// The mask leaves the target itself available
+    TargetAvailable,
+    /// The target can be reconstructed from the cells the mask leaves available
+    Recoverable,
+}
+
+impl MaskError {
+    /// The checks every grid shares: `mask` is `w x h` and leaves out `(row, col)`, which
+    /// must be in the `rows x cols` original data
+    pub(crate) fn check(
+        mask: &Grid,
+        (w, h): (usize, usize),
+        (rows, cols): (usize, usize),
+        (row, col): (usize, usize),
+    ) -> Result<(), MaskError> {
+        if (mask.w(), mask.h()) != (w, h) {
+            return Err(MaskError::WrongSize {
+                w: mask.w(),
+                h: mask.h(),
+                expected_w: w,
+                expected_h: h,
+            });
+        }
+        if row >= rows || col >= cols {
+            return Err(MaskError::TargetOutside { row, col });
+        }
+        if mask.get(row, col) {
+            return Err(MaskError::TargetAvailable);
+        }
+        Ok(())
+    }
+}
+
+impl Display for MaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaskError::WrongSize {
+                w,
+                h,
+                expected_w,
+                expected_h,
+            } => write!(
+                f,
+                "a {}x{} mask for a {}x{} grid",
+                w, h, expected_w, expected_h
+            ),
+            MaskError::TargetOutside { row, col } => {
+                write!(f, "target ({}, {}) isn't in the original data", row, col)
+            }
+            MaskError::TargetAvailable => write!(f, "the mask leaves the target available"),
+            MaskError::Recoverable => write!(f, "the target can be reconstructed"),
+        }
+    }
+}
+
+impl std::error::Error for MaskError {}
+
 impl Grid {
     pub fn new(w: usize, h: usize) -> Self {
         Self {
             w,
             h,
             bv: BitVec::zeros(w * h),
+            row_bv: BitVec::zeros(w * h),
+            counts: None,
+            cached_counts: OnceLock::new(),
+        }
+    }
+
+    /// A `w x h` grid where each cell is set independently with probability `density`.
+    /// Errors unless `density` is in `[0, 1]`.
+    pub fn random<R: RngCore>(
+        w: usize,
+        h: usize,
+        density: f64,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        let cell = Bernoulli::new(density).map_err(|_| Error::InvalidFraction(density))?;
+        let nbits = w * h;
+        let mut words = vec![0; nbits.div_ceil(64)];
+        if density < EXTREME_DENSITY || density > 1.0 - EXTREME_DENSITY {
+            // Rounding to `DENSITY_BITS` would be too far off, so each cell gets its own draw
+            for ind in 0..nbits {
+                if cell.sample(rng) {
+                    words[ind / 64] |= 1 << (ind % 64);
+                }
+            }
+        } else {
+            words
+                .iter_mut()
+                .for_each(|word| *word = random_word(rng, density));
+        }
+        Ok(Self::from_col_words(w, h, words))
+    }
+
+    /// A `w x h` grid with exactly `k` distinct cells set, picked uniformly at random.
+    /// Errors if the grid has fewer than `k` cells.
+    pub fn random_k<R: RngCore>(w: usize, h: usize, k: usize, rng: &mut R) -> Result<Self, Error> {
+        if k > w * h {
+            return Err(Error::TooManyCells { k, w, h });
+        }
+        let mut grid = Self::new(w, h);
+        for ind in index::sample(rng, w * h, k) {
+            let (row, col) = grid.ind_to_coord(ind);
+            grid.set(row, col, true);
+        }
+        Ok(grid)
+    }
+
+    /// Keep per column and per row counts up to date from now on, so `col_row_counts`
+    /// never has to count them again after a change. Makes every `set` a bit slower.
+    pub fn track_counts(&mut self) {
+        if self.counts.is_none() {
+            self.counts = Some(self.line_counts());
+        }
+    }
+
+    fn line_counts(&self) -> LineCounts {
+        LineCounts {
+            cols: (0..self.w).map(|col| self.count_columnar(col)).collect(),
+            rows: self.count_rows(),
+        }
+    }
+
+    // For changes too large to track cell by cell
+    fn recount(&mut self) {
+        self.cached_counts.take();
+        if self.counts.is_some() {
+            self.counts = Some(self.line_counts());
+        }
+    }
+
+    /// The tracked counts if there are any, otherwise the cached ones, counted now if the
+    /// grid has changed since they last were
+    fn current_counts(&self) -> &LineCounts {
+        match &self.counts {
+            Some(counts) => counts,
+            None => self.cached_counts.get_or_init(|| self.line_counts()),
         }
     }
 
@@ -56,14 +552,69 @@ impl Grid {
     pub fn h(&self) -> usize {
         self.h
     }
+    /// Where `(row, col)` is stored: the grid is column major, so each column is `h`
+    /// consecutive cells. The row major copy kept next to it (`row * w + col`) is this same
+    /// layout for the `h x w` transpose, which is why `transpose` only swaps the two.
     #[inline(always)]
     pub fn coord_to_ind(&self, row: usize, col: usize) -> usize {
+        self.debug_check_coord(row, col);
         row + (col * self.h)
     }
+    /// The inverse of `coord_to_ind`
+    #[inline(always)]
+    pub fn ind_to_coord(&self, ind: usize) -> (usize, usize) {
+        (ind % self.h, ind / self.h)
+    }
+    #[inline(always)]
+    fn coord_to_row_ind(&self, row: usize, col: usize) -> usize {
+        self.debug_check_coord(row, col);
+        (row * self.w) + col
+    }
+    // Out of range coordinates would otherwise silently wrap into the next column (row)
+    #[inline(always)]
+    fn debug_check_coord(&self, row: usize, col: usize) {
+        debug_assert!(
+            row < self.h && col < self.w,
+            "{}",
+            GridIndexError {
+                row,
+                col,
+                w: self.w,
+                h: self.h
+            }
+        );
+    }
+    #[inline(always)]
+    fn check_coord(&self, row: usize, col: usize) -> Result<(), GridIndexError> {
+        if row < self.h && col < self.w {
+            Ok(())
+        } else {
+            Err(GridIndexError {
+                row,
+                col,
+                w: self.w,
+                h: self.h,
+            })
+        }
+    }
 
     #[inline(always)]
     pub fn set(&mut self, row: usize, col: usize, value: bool) {
-        self.bv.set(self.coord_to_ind(row, col), value);
+        let ind = self.coord_to_ind(row, col);
+        self.cached_counts.take();
+        if let Some(counts) = self.counts.as_mut() {
+            if self.bv.get_unchecked(ind) != value {
+                if value {
+                    counts.cols[col] += 1;
+                    counts.rows[row] += 1;
+                } else {
+                    counts.cols[col] -= 1;
+                    counts.rows[row] -= 1;
+                }
+            }
+        }
+        self.bv.set(ind, value);
+        self.row_bv.set(self.coord_to_row_ind(row, col), value);
     }
 
     #[inline(always)]
@@ -71,16 +622,99 @@ impl Grid {
         self.bv.get_unchecked(self.coord_to_ind(row, col))
     }
 
+    /// Same as `set`, but errors instead of touching anything if the cell is outside
+    /// the grid
+    pub fn try_set(&mut self, row: usize, col: usize, value: bool) -> Result<(), GridIndexError> {
+        self.check_coord(row, col)?;
+        self.set(row, col, value);
+        Ok(())
+    }
+
+    /// Same as `get`, but errors if the cell is outside the grid
+    pub fn try_get(&self, row: usize, col: usize) -> Result<bool, GridIndexError> {
+        self.check_coord(row, col)?;
+        Ok(self.get(row, col))
+    }
+
     pub fn count_columnar(&self, col: usize) -> usize {
-        let start_ind = self.coord_to_ind(0, col);
-        let end_ind = self.coord_to_ind(0, col + 1);
+        let start_ind = col * self.h;
+        let end_ind = (col + 1) * self.h;
         self.bv.count_ones_before(end_ind) - self.bv.count_ones_before(start_ind)
     }
 
+    /// The row equivalent of `count_columnar`
+    pub fn count_row(&self, row: usize) -> usize {
+        let start_ind = row * self.w;
+        let end_ind = (row + 1) * self.w;
+        self.row_bv.count_ones_before(end_ind) - self.row_bv.count_ones_before(start_ind)
+    }
+
+    /// The number of cells set in each row
+    pub fn count_rows(&self) -> Vec<usize> {
+        (0..self.h).map(|row| self.count_row(row)).collect()
+    }
+
     pub fn count_ones(&self) -> usize {
         self.bv.count_ones()
     }
 
+    /// How many cells are set in one of the grids but not the other. Panics unless the
+    /// grids are the same size.
+    pub fn hamming_distance(&self, other: &Grid) -> usize {
+        assert_eq!(self.w, other.w);
+        assert_eq!(self.h, other.h);
+        let mut diff = self.bv.clone();
+        diff.xor_inplace(&other.bv);
+        diff.count_ones()
+    }
+
+    /// The `(row, col)` of every set cell, column by column. On a mask before it's been
+    /// `not`-ed, that's every censored cell.
+    pub fn masked_cells(&self) -> Vec<(usize, usize)> {
+        self.iter_ones().collect()
+    }
+
+    /// Lazily walks the `(row, col)` of every set cell, column by column
+    pub fn iter_ones(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.iter_where(true)
+    }
+
+    /// Lazily walks the `(row, col)` of every unset cell, column by column
+    pub fn iter_zeros(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.iter_where(false)
+    }
+
+    /// Walks `bv` a word at a time, skipping words without a matching cell and pulling the
+    /// rest out of each word by its trailing zeros
+    fn iter_where(&self, value: bool) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let nbits = self.w * self.h;
+        bv_words(&self.bv)
+            .iter()
+            .take(nbits.div_ceil(64))
+            .enumerate()
+            .map(move |(k, &word)| {
+                // Zeros are the ones of the flipped word, without the padding past the end
+                let mut bits = if value { word } else { !word };
+                let cells = nbits - k * 64;
+                if cells < 64 {
+                    bits &= (1 << cells) - 1;
+                }
+                (k, bits)
+            })
+            .filter(|&(_, bits)| bits != 0)
+            .flat_map(|(k, mut bits)| {
+                std::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let offset = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    Some(k * 64 + offset)
+                })
+            })
+            .map(|ind| self.ind_to_coord(ind))
+    }
+
     #[inline(always)]
     pub fn sample<R: RngCore>(&mut self, rng: &mut R, amount: usize, strategy: &SampleStrategy) {
         match strategy {
@@ -98,16 +732,25 @@ impl Grid {
                     let box_i = i_sampler.sample(rng);
                     let box_j = j_sampler.sample(rng);
 
-                    let start_i = box_i * height;
-                    let start_j = box_j * width;
-
-                    for i in start_i..start_i + height {
-                        for j in start_j..start_j + width {
-                            self.set(i, j, true);
-                        }
-                    }
+                    self.set_box(box_i, box_j, *width, *height);
                 }
             }
+            SampleStrategy::SlidingBox { width, height } => {
+                let i_sampler = Uniform::new_inclusive(0, self.h - height);
+                let j_sampler = Uniform::new_inclusive(0, self.w - width);
+                for _ in 0..amount {
+                    let start_i = i_sampler.sample(rng);
+                    let start_j = j_sampler.sample(rng);
+                    self.set_box_at(start_i, start_j, *width, *height);
+                }
+            }
+            SampleStrategy::FullLines { lines, axis } => {
+                let n_lines = self.n_lines(*axis);
+                let sampled: Vec<usize> = (0..amount)
+                    .flat_map(|_| index::sample(rng, n_lines, *lines))
+                    .collect();
+                self.fill_lines(*axis, sampled);
+            }
             SampleStrategy::RandomPoints => {
                 let rs = Uniform::new(0, self.h());
                 let cs = Uniform::new(0, self.w());
@@ -118,44 +761,551 @@ impl Grid {
         }
     }
 
+    /// Like `sample`, but never draws the same cell (or box, or line) twice, so `amount`
+    /// samples always cover `amount` distinct cells (boxes, sets of lines). Saturates at the
+    /// whole grid. Sliding boxes are only distinct in their origins, so they may still
+    /// overlap.
+    pub fn sample_distinct<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        amount: usize,
+        strategy: &SampleStrategy,
+    ) {
+        match strategy {
+            SampleStrategy::Box { width, height } => {
+                let row_splits = self.w / width;
+                let col_splits = self.h / height;
+                let n_boxes = row_splits * col_splits;
+                for b in index::sample(rng, n_boxes, amount.min(n_boxes)) {
+                    self.set_box(b / row_splits, b % row_splits, *width, *height);
+                }
+            }
+            SampleStrategy::SlidingBox { width, height } => {
+                let cols = self.w - width + 1;
+                let n_origins = cols * (self.h - height + 1);
+                for b in index::sample(rng, n_origins, amount.min(n_origins)) {
+                    self.set_box_at(b / cols, b % cols, *width, *height);
+                }
+            }
+            SampleStrategy::FullLines { lines, axis } => {
+                let n_lines = self.n_lines(*axis);
+                let sampled = index::sample(rng, n_lines, (amount * lines).min(n_lines));
+                self.fill_lines(*axis, sampled);
+            }
+            SampleStrategy::RandomPoints => {
+                let n_cells = self.w * self.h;
+                for ind in index::sample(rng, n_cells, amount.min(n_cells)) {
+                    self.set(ind % self.h, ind / self.h, true);
+                }
+            }
+        }
+    }
+
+    /// How many times each cell would be hit by `sample`ing `amount` times, indexed like
+    /// `coord_to_ind`. The grid itself is left untouched.
+    pub fn sample_counts<R: RngCore>(
+        &self,
+        rng: &mut R,
+        amount: usize,
+        strategy: &SampleStrategy,
+    ) -> Vec<u32> {
+        let mut counts = vec![0; self.w * self.h];
+        match strategy {
+            SampleStrategy::Box { width, height } => {
+                let i_sampler = Uniform::new(0, self.h / height);
+                let j_sampler = Uniform::new(0, self.w / width);
+                for _ in 0..amount {
+                    let start_i = i_sampler.sample(rng) * height;
+                    let start_j = j_sampler.sample(rng) * width;
+                    for j in start_j..start_j + width {
+                        for i in start_i..start_i + height {
+                            counts[self.coord_to_ind(i, j)] += 1;
+                        }
+                    }
+                }
+            }
+            SampleStrategy::SlidingBox { width, height } => {
+                let i_sampler = Uniform::new_inclusive(0, self.h - height);
+                let j_sampler = Uniform::new_inclusive(0, self.w - width);
+                for _ in 0..amount {
+                    let start_i = i_sampler.sample(rng);
+                    let start_j = j_sampler.sample(rng);
+                    for j in start_j..start_j + width {
+                        for i in start_i..start_i + height {
+                            counts[self.coord_to_ind(i, j)] += 1;
+                        }
+                    }
+                }
+            }
+            SampleStrategy::FullLines { lines, axis } => {
+                for _ in 0..amount {
+                    for line in index::sample(rng, self.n_lines(*axis), *lines) {
+                        for (i, j) in self.line_cells(*axis, line) {
+                            counts[self.coord_to_ind(i, j)] += 1;
+                        }
+                    }
+                }
+            }
+            SampleStrategy::RandomPoints => {
+                let cells = Uniform::new(0, self.w * self.h);
+                for _ in 0..amount {
+                    counts[cells.sample(rng)] += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// The coefficient of variation (standard deviation over mean) of the per-cell hit
+    /// counts from `sample_counts`, where lower means the samples cover the grid more evenly.
+    /// NaN if nothing was sampled.
+    pub fn sample_uniformity<R: RngCore>(
+        &self,
+        rng: &mut R,
+        amount: usize,
+        strategy: &SampleStrategy,
+    ) -> f64 {
+        let counts = self.sample_counts(rng, amount, strategy);
+        let n = counts.len() as f64;
+        let mean = counts.iter().map(|&c| c as f64).sum::<f64>() / n;
+        let var = counts
+            .iter()
+            .map(|&c| (c as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        var.sqrt() / mean
+    }
+
+    /// Set every cell in column `col`
+    pub fn fill_col(&mut self, col: usize) {
+        self.fill_cols([col]);
+    }
+
+    /// Set every cell in each of `cols`. Columns are contiguous in `bv`, so they're filled
+    /// a word at a time, while `row_bv` gets a bit per row in a mask of words; both are
+    /// then or'ed in at once rather than a cell at a time.
+    pub fn fill_cols(&mut self, cols: impl IntoIterator<Item = usize>) {
+        let (w, h) = (self.w, self.h);
+        let (mut col_words, mut row_words) = (self.zero_words(), self.zero_words());
+        for col in cols {
+            self.debug_check_coord(0, col);
+            set_word_range(&mut col_words, col * h..(col + 1) * h);
+            for row in 0..h {
+                let ind = row * w + col;
+                row_words[ind / 64] |= 1 << (ind % 64);
+            }
+        }
+        self.or_words(&col_words, &row_words);
+    }
+
+    /// How many rows or columns the grid has
+    fn n_lines(&self, axis: Axis) -> usize {
+        match axis {
+            Axis::Row => self.h,
+            Axis::Column => self.w,
+        }
+    }
+
+    /// The `(row, col)` of every cell in row or column `line`
+    fn line_cells(&self, axis: Axis, line: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (w, h) = (self.w, self.h);
+        let (rows, cols) = match axis {
+            Axis::Row => (line..line + 1, 0..w),
+            Axis::Column => (0..h, line..line + 1),
+        };
+        rows.flat_map(move |i| cols.clone().map(move |j| (i, j)))
+    }
+
+    /// Set every cell in row `row`
+    pub fn fill_row(&mut self, row: usize) {
+        self.fill_rows([row]);
+    }
+
+    /// Same as `fill_cols`, but for rows, which are contiguous in `row_bv` instead
+    pub fn fill_rows(&mut self, rows: impl IntoIterator<Item = usize>) {
+        let (w, h) = (self.w, self.h);
+        let (mut col_words, mut row_words) = (self.zero_words(), self.zero_words());
+        for row in rows {
+            self.debug_check_coord(row, 0);
+            set_word_range(&mut row_words, row * w..(row + 1) * w);
+            for col in 0..w {
+                let ind = col * h + row;
+                col_words[ind / 64] |= 1 << (ind % 64);
+            }
+        }
+        self.or_words(&col_words, &row_words);
+    }
+
+    /// Set every cell in each of `lines`, which are rows or columns depending on `axis`
+    fn fill_lines(&mut self, axis: Axis, lines: impl IntoIterator<Item = usize>) {
+        match axis {
+            Axis::Row => self.fill_rows(lines),
+            Axis::Column => self.fill_cols(lines),
+        }
+    }
+
+    /// Enough zeroed words to hold every cell
+    fn zero_words(&self) -> Vec<u64> {
+        vec![0; (self.w * self.h).div_ceil(64)]
+    }
+
+    /// A `w x h` grid with the cells set in `words`, laid out like `bv`. `row_bv` is built
+    /// from them in a single pass over the set cells rather than a `set` per cell.
+    fn from_col_words(w: usize, h: usize, words: Vec<u64>) -> Grid {
+        let nbits = w * h;
+        let mut row_words = vec![0; words.len()];
+        for (k, &word) in words.iter().enumerate() {
+            let mut bits = word;
+            while bits != 0 {
+                let ind = k * 64 + bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                if ind < nbits {
+                    let row_ind = (ind % h) * w + ind / h;
+                    row_words[row_ind / 64] |= 1 << (row_ind % 64);
+                }
+            }
+        }
+        Grid {
+            w,
+            h,
+            bv: BitVec::from_slice_copy(&words, nbits),
+            row_bv: BitVec::from_slice_copy(&row_words, nbits),
+            counts: None,
+            cached_counts: OnceLock::new(),
+        }
+    }
+
+    /// Sets every cell set in `col_words`, laid out like `bv`, and `row_words`, laid out
+    /// like `row_bv`, which have to hold the same cells
+    fn or_words(&mut self, col_words: &[u64], row_words: &[u64]) {
+        let nbits = self.w * self.h;
+        self.bv
+            .or_inplace(&BitVec::from_slice_copy(col_words, nbits));
+        self.row_bv
+            .or_inplace(&BitVec::from_slice_copy(row_words, nbits));
+        self.recount();
+    }
+
+    /// Set every cell in the `box_i`th box down and `box_j`th box across
+    fn set_box(&mut self, box_i: usize, box_j: usize, width: usize, height: usize) {
+        self.set_box_at(box_i * height, box_j * width, width, height);
+    }
+
+    /// Set every cell in the box whose top left cell is `(start_i, start_j)`
+    fn set_box_at(&mut self, start_i: usize, start_j: usize, width: usize, height: usize) {
+        for i in start_i..start_i + height {
+            for j in start_j..start_j + width {
+                self.set(i, j, true);
+            }
+        }
+    }
+
+    /// Errors unless `other` is the same size, as every operation combining two grids needs
+    pub fn check_same_size(&self, other: &Grid) -> Result<(), Error> {
+        if (self.w, self.h) != (other.w, other.h) {
+            return Err(Error::ShapeMismatch {
+                expected: (self.w, self.h),
+                got: (other.w, other.h),
+            });
+        }
+        Ok(())
+    }
+
+    /// Keeps only the cells also set in `mask`. Panics unless the grids are the same size,
+    /// see `try_and_inplace`.
     #[inline(always)]
     pub fn and_inplace(&mut self, mask: &Grid) {
         assert_eq!(self.w, mask.w);
         assert_eq!(self.h, mask.h);
         self.bv.and_inplace(&mask.bv);
+        self.row_bv.and_inplace(&mask.row_bv);
+        self.recount();
+    }
+
+    /// Sets every cell set in `other`. Panics unless the grids are the same size, see
+    /// `try_or_inplace`.
+    #[inline(always)]
+    pub fn or_inplace(&mut self, other: &Grid) {
+        assert_eq!(self.w, other.w);
+        assert_eq!(self.h, other.h);
+        self.bv.or_inplace(&other.bv);
+        self.row_bv.or_inplace(&other.row_bv);
+        self.recount();
+    }
+
+    /// Same as `and_inplace`, but errors rather than panicking if `mask` is a different size
+    pub fn try_and_inplace(&mut self, mask: &Grid) -> Result<(), Error> {
+        self.check_same_size(mask)?;
+        self.and_inplace(mask);
+        Ok(())
+    }
+
+    /// Same as `or_inplace`, but errors rather than panicking if `other` is a different size
+    pub fn try_or_inplace(&mut self, other: &Grid) -> Result<(), Error> {
+        self.check_same_size(other)?;
+        self.or_inplace(other);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn xor_inplace(&mut self, other: &Grid) {
+        assert_eq!(self.w, other.w);
+        assert_eq!(self.h, other.h);
+        self.bv.xor_inplace(&other.bv);
+        self.row_bv.xor_inplace(&other.row_bv);
+        self.recount();
+    }
+
+    /// Clears every cell that is set in `other`
+    #[inline(always)]
+    pub fn and_not_inplace(&mut self, other: &Grid) {
+        assert_eq!(self.w, other.w);
+        assert_eq!(self.h, other.h);
+        self.bv.difference_inplace(&other.bv);
+        self.row_bv.difference_inplace(&other.row_bv);
+        self.recount();
+    }
+
+    /// Unset every cell in place, keeping the grid's allocations
+    pub fn clear(&mut self) {
+        self.bv.set_all_false();
+        self.row_bv.set_all_false();
+        self.cached_counts.take();
+        if let Some(counts) = &mut self.counts {
+            counts.cols.iter_mut().for_each(|c| *c = 0);
+            counts.rows.iter_mut().for_each(|c| *c = 0);
+        }
+    }
+
+    /// Makes this grid a copy of `other`, reusing its allocations if they're the same size
+    pub fn copy_from(&mut self, other: &Grid) {
+        if (self.w, self.h) != (other.w, other.h) {
+            *self = other.clone();
+            return;
+        }
+        self.clear();
+        self.or_inplace(other);
+    }
+
+    /// Independently keep each set cell with probability `keep`, clearing the rest
+    pub fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64) {
+        if keep >= 1.0 {
+            return;
+        }
+        let keep = Bernoulli::new(keep.max(0.0)).unwrap();
+        for col in 0..self.w {
+            for row in 0..self.h {
+                if self.get(row, col) && !keep.sample(rng) {
+                    self.set(row, col, false);
+                }
+            }
+        }
+    }
+
+    /// Independently clear each set cell with probability `error_rate`, modelling
+    /// responses that turn out to be wrong and have to be thrown away
+    pub fn corrupt<R: RngCore>(&mut self, rng: &mut R, error_rate: f64) {
+        self.thin(rng, 1.0 - error_rate)
     }
 
+    // Consumes the grid like `std::ops::Not` would, but kept inherent so callers don't
+    // need the trait in scope
+    #[allow(clippy::should_implement_trait)]
     pub fn not(self) -> Grid {
+        let nbits = self.w * self.h;
+        // `BitVec`'s own negation leaves the padding after the last cell set when the length
+        // is a multiple of 64, which `count_ones` then picks up, so flip against ones instead
+        let ones = BitVec::from_slice_copy(&vec![u64::MAX; nbits.div_ceil(64)], nbits);
+        let (w, h) = (self.w, self.h);
+        let counts = self.counts.map(|counts| LineCounts {
+            cols: counts.cols.into_iter().map(|count| h - count).collect(),
+            rows: counts.rows.into_iter().map(|count| w - count).collect(),
+        });
         Self {
-            w: self.w,
-            h: self.h,
-            bv: self.bv.not(),
+            w,
+            h,
+            bv: self.bv.xor(ones.clone()),
+            row_bv: self.row_bv.xor(ones),
+            counts,
+            cached_counts: OnceLock::new(),
+        }
+    }
+
+    /// A copy of the cells in `rows` x `cols`, so `(rows.start, cols.start)` becomes `(0, 0)`.
+    /// Errors if the rectangle doesn't fit in the grid.
+    pub fn sub_grid(&self, rows: Range<usize>, cols: Range<usize>) -> Result<Grid, Error> {
+        if rows.start > rows.end || rows.end > self.h || cols.start > cols.end || cols.end > self.w
+        {
+            return Err(Error::RegionOutOfBounds {
+                rows,
+                cols,
+                w: self.w,
+                h: self.h,
+            });
+        }
+        // Each column's `rows` are contiguous in `bv`, and so is each column of the copy,
+        // so they're copied up to a word at a time
+        let (w, h) = (cols.len(), rows.len());
+        let src = bv_words(&self.bv);
+        let mut words = vec![0; (w * h).div_ceil(64)];
+        for (j, col) in cols.enumerate() {
+            for i in (0..h).step_by(64) {
+                let bits = read_bits(src, col * self.h + rows.start + i, (h - i).min(64));
+                or_bits(&mut words, j * h + i, bits);
+            }
+        }
+        Ok(Grid::from_col_words(w, h, words))
+    }
+
+    /// Same as `sub_grid`, but for the `rows x cols` rectangle whose top left cell is
+    /// `(row0, col0)`. Panics if it doesn't fit in the grid.
+    pub fn subgrid(&self, row0: usize, col0: usize, rows: usize, cols: usize) -> Grid {
+        self.sub_grid(row0..row0 + rows, col0..col0 + cols)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// A `w x h` grid with this grid in its top left corner, the inverse of `sub_grid`.
+    /// Errors if this grid doesn't fit.
+    pub fn embed(&self, w: usize, h: usize) -> Result<Grid, Error> {
+        if self.w > w || self.h > h {
+            return Err(Error::RegionOutOfBounds {
+                rows: 0..self.h,
+                cols: 0..self.w,
+                w,
+                h,
+            });
+        }
+        Grid::from_indices(w, h, self.iter_ones())
+    }
+
+    /// The `h x w` grid with this grid's rows as its columns, so cell `(row, col)` ends up at
+    /// `(col, row)`. The row major copy of the cells is exactly the column major layout of
+    /// the transpose (see `coord_to_ind`), so no bits need moving.
+    pub fn transpose(&self) -> Grid {
+        Grid {
+            w: self.h,
+            h: self.w,
+            bv: self.row_bv.clone(),
+            row_bv: self.bv.clone(),
+            counts: self
+                .counts
+                .clone()
+                .map(|LineCounts { cols, rows }| LineCounts {
+                    cols: rows,
+                    rows: cols,
+                }),
+            cached_counts: OnceLock::new(),
         }
     }
 
-    #[cfg(test)]
+    /// A `W x H` grid from its rows of cells
     pub fn from_bool_grid<const W: usize, const H: usize>(bools: [[bool; W]; H]) -> Self {
         let mut grid = Self::new(W, H);
-        for i in 0..H {
-            for j in 0..W {
-                grid.set(i, j, bools[i][j])
+        for (i, row) in bools.iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                grid.set(i, j, value)
             }
         }
         grid
     }
 
-    pub fn col_row_counts(&self) -> (Vec<usize>, Vec<usize>) {
-        let mut col_counts = vec![0usize; self.w];
-        let mut row_counts = vec![0usize; self.h];
-        for i in 0..self.h {
-            for j in 0..self.w {
-                if self.get(i, j) {
-                    row_counts[i] += 1;
-                    col_counts[j] += 1;
-                }
+    /// Same as `from_bool_grid` for rows only known at runtime. Errors unless every row is
+    /// the same length.
+    pub fn from_bool_rows(rows: &[Vec<bool>]) -> Result<Self, Error> {
+        let w = rows.first().map_or(0, |row| row.len());
+        let mut grid = Self::new(w, rows.len());
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != w {
+                return Err(Error::RaggedRows {
+                    row: i,
+                    len: row.len(),
+                    expected: w,
+                });
+            }
+            for (j, &value) in row.iter().enumerate() {
+                grid.set(i, j, value)
             }
         }
-        (col_counts, row_counts)
+        Ok(grid)
+    }
+
+    /// A `w x h` grid with only the given `(row, col)` cells set.
+    /// Errors if any of them are outside the grid.
+    pub fn from_indices(
+        w: usize,
+        h: usize,
+        cells: impl IntoIterator<Item = (usize, usize)>,
+    ) -> Result<Self, Error> {
+        let mut grid = Self::new(w, h);
+        for (row, col) in cells {
+            grid.try_set(row, col, true)?;
+        }
+        Ok(grid)
+    }
+
+    /// A `w x h` grid with every cell of the given rows set. Errors with the first cell
+    /// outside the grid if any of the rows are.
+    pub fn full_rows(w: usize, h: usize, rows: &[usize]) -> Result<Self, Error> {
+        Self::from_indices(
+            w,
+            h,
+            rows.iter()
+                .flat_map(|&row| (0..w).map(move |col| (row, col))),
+        )
+    }
+
+    /// A `w x h` grid with every cell of the given columns set. Errors with the first cell
+    /// outside the grid if any of the columns are.
+    pub fn full_cols(w: usize, h: usize, cols: &[usize]) -> Result<Self, Error> {
+        Self::from_indices(
+            w,
+            h,
+            cols.iter()
+                .flat_map(|&col| (0..h).map(move |row| (row, col))),
+        )
+    }
+
+    /// The number of cells set in each column, from the tracked counts if there are any.
+    /// Otherwise they're counted once and reused until the grid changes.
+    pub fn col_counts(&self) -> Vec<usize> {
+        self.current_counts().cols.clone()
+    }
+
+    /// The row equivalent of `col_counts`
+    pub fn row_counts(&self) -> Vec<usize> {
+        self.current_counts().rows.clone()
+    }
+
+    pub fn col_row_counts(&self) -> (Vec<usize>, Vec<usize>) {
+        let counts = self.current_counts();
+        (counts.cols.clone(), counts.rows.clone())
+    }
+}
+
+// Compare the dimensions first, `BitVec`'s own eq panics on different lengths.
+// `row_bv` holds the same cells as `bv` and `counts` is derived from them, so they're
+// left out here and in `Hash`.
+impl PartialEq for Grid {
+    fn eq(&self, other: &Self) -> bool {
+        self.w == other.w && self.h == other.h && self.bv == other.bv
+    }
+}
+
+impl Eq for Grid {}
+
+impl Hash for Grid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.w.hash(state);
+        self.h.hash(state);
+        // Pack the cells into words ourselves rather than hashing `BitVec`'s storage,
+        // so any padding bits past the end can't make equal grids hash differently
+        let nbits = self.bv.len();
+        for start in (0..nbits).step_by(64) {
+            let word = (start..nbits.min(start + 64))
+                .filter(|&i| self.bv.get_unchecked(i))
+                .fold(0u64, |acc, i| acc | 1 << (i - start));
+            word.hash(state);
+        }
     }
 }
 
@@ -165,22 +1315,47 @@ impl BitOr for Grid {
     fn bitor(self, rhs: Self) -> Self::Output {
         assert_eq!(self.w, rhs.w);
         assert_eq!(self.h, rhs.h);
-        Self {
+        let mut grid = Self {
             w: self.w,
             h: self.h,
             bv: self.bv | rhs.bv,
-        }
+            row_bv: self.row_bv | rhs.row_bv,
+            counts: self.counts,
+            cached_counts: OnceLock::new(),
+        };
+        grid.recount();
+        grid
+    }
+}
+
+impl BitAnd for &Grid {
+    type Output = Grid;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let mut res = self.clone();
+        res.and_inplace(rhs);
+        res
+    }
+}
+
+impl BitXor for &Grid {
+    type Output = Grid;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let mut res = self.clone();
+        res.xor_inplace(rhs);
+        res
     }
 }
 
 impl Debug for Grid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "\n")?;
+        writeln!(f)?;
         for i in 0..self.h() {
             for j in 0..self.w() {
                 write!(f, "{:^3}", self.get(i, j) as u8)?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -188,9 +1363,91 @@ impl Debug for Grid {
 
 #[cfg(test)]
 mod tests {
-    use rand::thread_rng;
+    use rand::{rngs::SmallRng, thread_rng, SeedableRng};
 
     use super::*;
+    #[test]
+    fn test_copy_from() {
+        let mut rng = thread_rng();
+        let mut g = Grid::new(8, 6);
+        g.sample(&mut rng, 20, &SampleStrategy::RandomPoints);
+        let mut scratch = Grid::new(8, 6);
+        scratch.sample(&mut rng, 20, &SampleStrategy::RandomPoints);
+        scratch.copy_from(&g);
+        assert_eq!(scratch, g);
+        assert_eq!(scratch.col_row_counts(), g.col_row_counts());
+        // A differently sized grid is replaced
+        let mut scratch = Grid::new(0, 0);
+        scratch.copy_from(&g);
+        assert_eq!(scratch, g);
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        let mut rng = thread_rng();
+        let mut a = Grid::new(12, 7);
+        a.sample(&mut rng, 30, &SampleStrategy::RandomPoints);
+        assert_eq!(a.hamming_distance(&a), 0);
+        let mut b = a.clone();
+        for (i, j) in [(0, 0), (3, 11), (6, 5)] {
+            b.set(i, j, !b.get(i, j));
+        }
+        assert_eq!(a.hamming_distance(&b), 3);
+        assert_eq!(b.hamming_distance(&a), 3);
+        assert_eq!(a.hamming_distance(&Grid::new(12, 7)), a.count_ones());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hamming_distance_sizes() {
+        Grid::new(4, 4).hamming_distance(&Grid::new(4, 5));
+    }
+
+    #[test]
+    fn test_parse_strategies() {
+        assert_eq!("RandomPoints".parse(), Ok(SampleStrategy::RandomPoints));
+        assert_eq!(
+            "Box:2x4".parse(),
+            Ok(SampleStrategy::Box {
+                width: 2,
+                height: 4
+            })
+        );
+        assert_eq!(
+            "SlidingBox:3x1".parse(),
+            Ok(SampleStrategy::SlidingBox {
+                width: 3,
+                height: 1
+            })
+        );
+        assert_eq!(
+            "FullLines:2:Column".parse(),
+            Ok(SampleStrategy::FullLines {
+                lines: 2,
+                axis: Axis::Column
+            })
+        );
+        for bad in [
+            "Box",
+            "Box:2",
+            "Box:2xy",
+            "RandomPoints:1",
+            "Points",
+            "SlidingBox",
+            "FullLines:2",
+            "FullLines:2:Diagonal",
+        ] {
+            assert!(bad.parse::<SampleStrategy>().is_err(), "{}", bad);
+        }
+        assert_eq!("Block".parse(), Ok(MaskStrategy::Block));
+        assert_eq!(
+            "WithholdRows:5".parse(),
+            Ok(MaskStrategy::WithholdRows { count: 5 })
+        );
+        assert!("WithholdRows".parse::<MaskStrategy>().is_err());
+        assert!("WithholdRows:-1".parse::<MaskStrategy>().is_err());
+    }
+
     #[test]
     fn test_box_sampling() {
         let mut g = Grid::new(32, 64);
@@ -205,4 +1462,681 @@ mod tests {
         println!("{:?}", g);
         assert_eq!(g.count_ones(), 16 * 8);
     }
+
+    #[test]
+    fn test_sliding_box_sampling() {
+        use std::collections::HashSet;
+
+        // 3 doesn't divide 8, but a sliding box can start anywhere it fits
+        let strategy = SampleStrategy::SlidingBox {
+            width: 3,
+            height: 1,
+        };
+        let mut rng = SmallRng::seed_from_u64(5);
+        let mut origins = HashSet::new();
+        for _ in 0..500 {
+            let mut g = Grid::new(8, 1);
+            g.sample(&mut rng, 1, &strategy);
+            assert_eq!(g.count_ones(), 3);
+            let start = (0..8).find(|&j| g.get(0, j)).unwrap();
+            assert!((start..start + 3).all(|j| g.get(0, j)));
+            origins.insert(start);
+        }
+        assert_eq!(origins, (0..=5).collect());
+
+        let mut g = Grid::new(8, 1);
+        g.sample_distinct(&mut rng, 6, &strategy);
+        assert_eq!(g.count_ones(), 8, "every origin covers the whole row");
+        let counts = Grid::new(8, 1).sample_counts(&mut rng, 10, &strategy);
+        assert_eq!(counts.iter().sum::<u32>(), 30);
+
+        assert_eq!(strategy.check_fits(8, 1), Ok(()));
+        assert!(matches!(
+            strategy.check_fits(2, 1),
+            Err(Error::InvalidStrategy(_))
+        ));
+        assert!(SampleStrategy::Box {
+            width: 3,
+            height: 1
+        }
+        .check_fits(8, 1)
+        .is_err());
+    }
+
+    #[test]
+    fn test_full_lines_sampling() {
+        let (w, h) = (6, 10);
+        let rows = SampleStrategy::FullLines {
+            lines: 2,
+            axis: Axis::Row,
+        };
+        let mut rng = SmallRng::seed_from_u64(2);
+        for _ in 0..100 {
+            let mut g = Grid::new(w, h);
+            g.sample(&mut rng, 1, &rows);
+            // The 2 rows are distinct, and every cell in them is set
+            assert_eq!(g.count_ones(), 2 * w);
+            let full = (0..h).filter(|&i| (0..w).all(|j| g.get(i, j))).count();
+            assert_eq!(full, 2);
+        }
+
+        let cols = SampleStrategy::FullLines {
+            lines: 3,
+            axis: Axis::Column,
+        };
+        let mut g = Grid::new(w, h);
+        g.sample(&mut rng, 1, &cols);
+        assert_eq!(g.count_ones(), 3 * h);
+        // Both layouts get the columns
+        assert_eq!(g.col_row_counts().1, vec![3; h]);
+        assert_eq!(g.transpose().count_ones(), 3 * h);
+        let mut g = Grid::new(w, h);
+        g.sample_distinct(&mut rng, 5, &cols);
+        assert_eq!(g.count_ones(), w * h, "saturates at every column");
+        let counts = Grid::new(w, h).sample_counts(&mut rng, 4, &cols);
+        assert_eq!(counts.iter().sum::<u32>() as usize, 4 * 3 * h);
+
+        assert_eq!(rows.check_fits(w, h), Ok(()));
+        assert!(cols.check_fits(2, h).is_err());
+        assert_eq!(rows.to_string(), "FullLines");
+    }
+
+    #[test]
+    fn test_not_count() {
+        // 64 cells is a single word, the shape which used to count padding bits
+        for (w, h) in [(8, 8), (4, 4), (16, 16), (3, 5)] {
+            let mut g = Grid::new(w, h);
+            g.set(0, 0, true);
+            assert_eq!(g.not().count_ones(), w * h - 1);
+        }
+    }
+
+    #[test]
+    fn test_sample_distinct() {
+        let mut g = Grid::new(8, 4);
+        g.sample_distinct(&mut thread_rng(), 20, &SampleStrategy::RandomPoints);
+        assert_eq!(g.count_ones(), 20);
+        // Asking for more than the grid holds just fills it
+        g.sample_distinct(&mut thread_rng(), 100, &SampleStrategy::RandomPoints);
+        assert_eq!(g.count_ones(), 32);
+
+        let mut g = Grid::new(8, 4);
+        let strategy = SampleStrategy::Box {
+            width: 2,
+            height: 2,
+        };
+        g.sample_distinct(&mut thread_rng(), 5, &strategy);
+        assert_eq!(g.count_ones(), 5 * 4);
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let mut g = Grid::new(8, 8);
+        g.sample(&mut thread_rng(), 40, &SampleStrategy::RandomPoints);
+        let sampled = g.clone();
+        g.corrupt(&mut thread_rng(), 0.0);
+        assert_eq!(g, sampled);
+        g.corrupt(&mut thread_rng(), 0.5);
+        // Only ever clears cells
+        assert_eq!(&g & &sampled, g);
+        g.corrupt(&mut thread_rng(), 1.0);
+        assert_eq!(g.count_ones(), 0);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut g = Grid::from_indices(5, 3, [(0, 0), (2, 4), (1, 2)]).unwrap();
+        g.track_counts();
+        g.clear();
+        assert_eq!(g, Grid::new(5, 3));
+        assert_eq!(g.count_rows(), [0, 0, 0]);
+        assert_eq!(g.col_row_counts(), (vec![0; 5], vec![0; 3]));
+        g.set(1, 3, true);
+        assert_eq!(g.col_row_counts(), (vec![0, 0, 0, 1, 0], vec![0, 1, 0]));
+    }
+
+    #[test]
+    fn test_sample_counts() {
+        let g = Grid::new(8, 4);
+        let counts = g.sample_counts(&mut thread_rng(), 10, &SampleStrategy::RandomPoints);
+        assert_eq!(counts.len(), 32);
+        assert_eq!(counts.iter().sum::<u32>(), 10);
+
+        let strategy = SampleStrategy::Box {
+            width: 2,
+            height: 2,
+        };
+        let counts = g.sample_counts(&mut thread_rng(), 10, &strategy);
+        assert_eq!(counts.iter().sum::<u32>(), 10 * 4);
+        // Every cell in a box is hit together
+        for (i, j) in [(0, 0), (0, 2), (2, 4), (2, 6)] {
+            let c = counts[g.coord_to_ind(i, j)];
+            assert_eq!(counts[g.coord_to_ind(i + 1, j)], c);
+            assert_eq!(counts[g.coord_to_ind(i, j + 1)], c);
+            assert_eq!(counts[g.coord_to_ind(i + 1, j + 1)], c);
+        }
+        assert_eq!(g, Grid::new(8, 4));
+    }
+
+    #[test]
+    fn test_sample_uniformity() {
+        // Equal-area sliding boxes cover the grid less evenly than points, since cells near
+        // the edges fit in fewer box positions and are hit less often than the middle
+        let mut rng = SmallRng::seed_from_u64(7);
+        let g = Grid::new(16, 16);
+        let points = g.sample_uniformity(&mut rng, 64 * 256, &SampleStrategy::RandomPoints);
+        let sliding = SampleStrategy::SlidingBox {
+            width: 4,
+            height: 4,
+        };
+        let boxes = g.sample_uniformity(&mut rng, 64 * 16, &sliding);
+        assert!(points < boxes, "{} >= {}", points, boxes);
+        assert!((points - 0.125).abs() < 0.025, "{}", points);
+        assert!(boxes > 0.3, "{}", boxes);
+
+        // Aligned boxes don't: every cell is hit exactly as often as the box it's in, and
+        // hitting each of m cells (or boxes) k times on average gives a CoV of
+        // sqrt((m - 1) / (k m)) either way. Their lumpiness is in which cells are hit
+        // together, which a per-cell CoV can't see
+        let g = Grid::new(64, 64);
+        let points = g.sample_uniformity(&mut rng, 4 * 4096, &SampleStrategy::RandomPoints);
+        let strategy = SampleStrategy::Box {
+            width: 2,
+            height: 2,
+        };
+        let boxes = g.sample_uniformity(&mut rng, 4 * 1024, &strategy);
+        assert!((points - 0.5).abs() < 0.05, "{}", points);
+        assert!((boxes - 0.5).abs() < 0.1, "{}", boxes);
+        // Fewer hits per cell are less even
+        let sparse = g.sample_uniformity(&mut rng, 4096, &SampleStrategy::RandomPoints);
+        assert!(sparse > points);
+        assert!(g
+            .sample_uniformity(&mut rng, 0, &SampleStrategy::RandomPoints)
+            .is_nan());
+    }
+
+    #[test]
+    fn test_box_checked() {
+        let strategy = SampleStrategy::box_checked(8, 4, 4, 2).unwrap();
+        assert!(matches!(
+            strategy,
+            SampleStrategy::Box {
+                width: 4,
+                height: 2
+            }
+        ));
+        for (width, height) in [(3, 2), (4, 8), (0, 2)] {
+            assert!(matches!(
+                SampleStrategy::box_checked(8, 4, width, height),
+                Err(Error::InvalidStrategy(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_try_or_inplace() {
+        let mut g = Grid::new(4, 2);
+        let mut other = Grid::new(4, 2);
+        other.set(1, 3, true);
+        assert_eq!(g.try_or_inplace(&other), Ok(()));
+        assert_eq!(g, other);
+        assert_eq!(g.try_and_inplace(&Grid::new(4, 2)), Ok(()));
+        assert_eq!(g.count_ones(), 0);
+
+        let wrong = Grid::new(2, 4);
+        let err = Error::ShapeMismatch {
+            expected: (4, 2),
+            got: (2, 4),
+        };
+        assert_eq!(g.try_or_inplace(&wrong), Err(err.clone()));
+        assert_eq!(g.try_and_inplace(&wrong), Err(err));
+        assert_eq!(g, Grid::new(4, 2));
+    }
+
+    fn naive_col_row_counts(g: &Grid) -> (Vec<usize>, Vec<usize>) {
+        let mut col_counts = vec![0usize; g.w()];
+        let mut row_counts = vec![0usize; g.h()];
+        for (i, row_count) in row_counts.iter_mut().enumerate() {
+            for (j, col_count) in col_counts.iter_mut().enumerate() {
+                if g.get(i, j) {
+                    *row_count += 1;
+                    *col_count += 1;
+                }
+            }
+        }
+        (col_counts, row_counts)
+    }
+
+    #[test]
+    fn test_count_rows_random() {
+        // Rows that span several words of the row major copy, and don't end on a word
+        let mut rng = SmallRng::seed_from_u64(3);
+        for (w, h) in [(130, 3), (65, 9), (1, 40), (200, 1)] {
+            for density in [0.1, 0.5, 0.9] {
+                let g = Grid::random(w, h, density, &mut rng).unwrap();
+                assert_eq!(g.count_rows(), naive_col_row_counts(&g).1);
+                assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_rows() {
+        let mut rng = thread_rng();
+        for (w, h) in [(16, 16), (3, 5), (10, 7), (64, 2)] {
+            let mut g = Grid::new(w, h);
+            g.sample(&mut rng, w * h / 3, &SampleStrategy::RandomPoints);
+            assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+            let mut other = Grid::new(w, h);
+            other.sample_distinct(&mut rng, w * h / 4, &SampleStrategy::RandomPoints);
+            // Every way of changing the grid has to keep the row counts in sync
+            let mut g = g | other.clone();
+            assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+            g.thin(&mut rng, 0.5);
+            assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+            g.and_inplace(&other.not());
+            assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+            let g = g.not();
+            assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+            assert_eq!(g.col_counts(), naive_col_row_counts(&g).0);
+            assert_eq!(g.row_counts(), naive_col_row_counts(&g).1);
+            assert_eq!(g.count_rows().iter().sum::<usize>(), g.count_ones());
+            assert_eq!(g.count_row(h - 1), naive_col_row_counts(&g).1[h - 1]);
+        }
+    }
+
+    #[test]
+    fn test_cached_counts() {
+        let mut rng = thread_rng();
+        let mut g = Grid::new(10, 7);
+        g.sample(&mut rng, 20, &SampleStrategy::RandomPoints);
+        // Counting twice without changes reuses the cache
+        assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+        assert!(g.cached_counts.get().is_some());
+        assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+
+        let mut other = Grid::new(10, 7);
+        other.sample(&mut rng, 20, &SampleStrategy::RandomPoints);
+        g.set(3, 4, !g.get(3, 4));
+        assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+        g.and_inplace(&other.clone().not());
+        assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+        let mut g = g | other.clone();
+        assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+        g.set(0, 0, false);
+        g.set(6, 9, true);
+        assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+        g.clear();
+        assert_eq!(g.col_row_counts(), (vec![0; 10], vec![0; 7]));
+        g.fill_row(2);
+        assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+        // A clone starts out with the same cache, but changing it doesn't touch the original
+        let mut copy = g.clone();
+        copy.fill_col(1);
+        assert_eq!(copy.col_row_counts(), naive_col_row_counts(&copy));
+        assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+    }
+
+    #[test]
+    fn test_or_inplace() {
+        let mut g = Grid::new(16, 8);
+        g.sample(&mut thread_rng(), 30, &SampleStrategy::RandomPoints);
+        let mut other = Grid::new(16, 8);
+        other.sample(&mut thread_rng(), 30, &SampleStrategy::RandomPoints);
+        let expected = g.clone() | other.clone();
+        g.or_inplace(&other);
+        assert_eq!(g, expected);
+        assert_eq!(g.col_row_counts(), expected.col_row_counts());
+    }
+
+    #[test]
+    fn test_iter_ones() {
+        // Dense and sparse, so some words are skipped, and full grids that end on a word
+        // boundary or in the middle of one, so the padding has to be left out of the zeros
+        for (w, h, amount) in [
+            (16, 16, 128),
+            (3, 5, 7),
+            (10, 7, 35),
+            (64, 2, 64),
+            (1, 9, 4),
+            (40, 30, 6),
+            (64, 4, 10_000),
+            (13, 7, 10_000),
+        ] {
+            let mut g = Grid::new(w, h);
+            g.sample(&mut thread_rng(), amount, &SampleStrategy::RandomPoints);
+            let brute = |value| {
+                let mut cells = vec![];
+                for j in 0..w {
+                    for i in 0..h {
+                        if g.get(i, j) == value {
+                            cells.push((i, j));
+                        }
+                    }
+                }
+                cells
+            };
+            assert_eq!(g.iter_ones().collect::<Vec<_>>(), brute(true));
+            assert_eq!(g.iter_zeros().collect::<Vec<_>>(), brute(false));
+            assert_eq!(g.iter_ones().count(), g.count_ones());
+        }
+        assert_eq!(Grid::new(4, 4).iter_ones().next(), None);
+    }
+
+    #[test]
+    fn test_constructors() {
+        let t = true;
+        let f = false;
+        let g = Grid::from_indices(3, 2, [(0, 0), (1, 2)]).unwrap();
+        assert_eq!(g, Grid::from_bool_grid([[t, f, f], [f, f, t]]));
+        assert_eq!(
+            Grid::from_bool_rows(&[vec![t, f, f], vec![f, f, t]]).unwrap(),
+            g
+        );
+        let outside = |row, col| {
+            Err(Error::IndexOutOfBounds(GridIndexError {
+                row,
+                col,
+                w: 3,
+                h: 2,
+            }))
+        };
+        assert_eq!(Grid::from_indices(3, 2, [(2, 0)]), outside(2, 0));
+        assert_eq!(Grid::from_indices(3, 2, [(0, 3)]), outside(0, 3));
+        assert_eq!(
+            Grid::from_bool_rows(&[vec![t, f], vec![f]]),
+            Err(Error::RaggedRows {
+                row: 1,
+                len: 1,
+                expected: 2
+            })
+        );
+
+        let rows = Grid::full_rows(3, 4, &[1, 3]).unwrap();
+        assert_eq!(rows.count_rows(), vec![0, 3, 0, 3]);
+        assert!(matches!(
+            Grid::full_rows(3, 4, &[4]),
+            Err(Error::IndexOutOfBounds(GridIndexError { row: 4, .. }))
+        ));
+        let cols = Grid::full_cols(3, 4, &[2]).unwrap();
+        assert_eq!(cols.col_row_counts().0, vec![0, 0, 4]);
+        assert!(matches!(
+            Grid::full_cols(3, 4, &[3]),
+            Err(Error::IndexOutOfBounds(GridIndexError { col: 3, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_serde() {
+        // 15 and 72 cells don't fill a whole number of words
+        for (w, h) in [(3, 5), (8, 9), (8, 8), (16, 16)] {
+            let mut g = Grid::new(w, h);
+            g.sample(&mut thread_rng(), w * h / 2, &SampleStrategy::RandomPoints);
+            let json = serde_json::to_string(&g).unwrap();
+            let back: Grid = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, g);
+            assert_eq!(back.col_row_counts(), g.col_row_counts());
+        }
+        let g = Grid::from_indices(3, 5, [(0, 0), (4, 2)]).unwrap();
+        assert_eq!(
+            serde_json::to_string(&g).unwrap(),
+            r#"{"w":3,"h":5,"bits":[1,64]}"#
+        );
+
+        // Too short, too long, and a bit set past the 15th cell
+        for bad in [
+            r#"{"w":3,"h":5,"bits":[1]}"#,
+            r#"{"w":3,"h":5,"bits":[1,0,0]}"#,
+            r#"{"w":3,"h":5,"bits":[1,128]}"#,
+        ] {
+            assert!(serde_json::from_str::<Grid>(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_tracked_counts() {
+        let mut rng = thread_rng();
+        for (w, h) in [(16, 16), (3, 5), (10, 7), (64, 2)] {
+            let mut g = Grid::new(w, h);
+            g.sample(&mut rng, w * h / 4, &SampleStrategy::RandomPoints);
+            g.track_counts();
+            assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+            for step in 0..50 {
+                let mut other = Grid::new(w, h);
+                other.sample(&mut rng, w * h / 3, &SampleStrategy::RandomPoints);
+                match step % 5 {
+                    // Setting a cell to what it already is mustn't count it twice
+                    0 => {
+                        for (row, col) in other.iter_ones() {
+                            g.set(row, col, !g.get(row, col) || step % 2 == 0);
+                        }
+                    }
+                    1 => g.and_inplace(&other.not()),
+                    2 => g.or_inplace(&other),
+                    3 => g = g | other,
+                    _ => g.thin(&mut rng, 0.7),
+                }
+                assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+            }
+            let g = g.not();
+            assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+        }
+    }
+
+    #[test]
+    fn test_try_get_set() {
+        let mut g = Grid::new(3, 2);
+        assert_eq!(g.try_set(1, 2, true), Ok(()));
+        assert_eq!(g.try_get(1, 2), Ok(true));
+        assert_eq!(g.try_get(0, 2), Ok(false));
+        // One past the last row would be the first cell of the next column
+        let err = GridIndexError {
+            row: 2,
+            col: 0,
+            w: 3,
+            h: 2,
+        };
+        assert_eq!(g.try_set(2, 0, true), Err(err.clone()));
+        assert_eq!(g.try_get(2, 0), Err(err));
+        let err = g.try_get(0, 3).unwrap_err();
+        assert_eq!((err.row, err.col), (0, 3));
+        assert_eq!(err.to_string(), "(0, 3) is outside a 3x2 grid");
+        assert_eq!(g.count_ones(), 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "(2, 0) is outside a 3x2 grid")]
+    fn test_set_out_of_range() {
+        let mut g = Grid::new(3, 2);
+        g.set(2, 0, true);
+    }
+
+    #[test]
+    fn test_transpose() {
+        for (w, h) in [(16, 16), (3, 5), (10, 7), (64, 2), (1, 9)] {
+            let mut g = Grid::new(w, h);
+            g.sample(&mut thread_rng(), w * h / 2, &SampleStrategy::RandomPoints);
+            let t = g.transpose();
+            assert_eq!((t.w(), t.h()), (h, w));
+            for (row, col) in g.iter_ones() {
+                assert!(t.get(col, row));
+            }
+            assert_eq!(t.count_ones(), g.count_ones());
+            assert_eq!(t.col_row_counts(), naive_col_row_counts(&t));
+            assert_eq!(t.transpose(), g);
+
+            g.track_counts();
+            let mut t = g.transpose();
+            t.set(0, 0, true);
+            assert_eq!(t.col_row_counts(), naive_col_row_counts(&t));
+        }
+        let t = true;
+        let f = false;
+        let g = Grid::from_bool_grid([[t, f, f], [t, t, f]]);
+        assert_eq!(
+            g.transpose(),
+            Grid::from_bool_grid([[t, t], [f, t], [f, f]])
+        );
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let t = true;
+        let f = false;
+        let a = Grid::from_bool_grid([[t, t, f, f], [t, t, f, f], [f, f, f, f], [t, f, t, f]]);
+        let b = Grid::from_bool_grid([[t, f, t, f], [f, t, f, f], [f, f, t, f], [t, t, t, t]]);
+        let and = Grid::from_bool_grid([[t, f, f, f], [f, t, f, f], [f, f, f, f], [t, f, t, f]]);
+        let xor = Grid::from_bool_grid([[f, t, t, f], [t, f, f, f], [f, f, t, f], [f, t, f, t]]);
+        let a_not_b =
+            Grid::from_bool_grid([[f, t, f, f], [t, f, f, f], [f, f, f, f], [f, f, f, f]]);
+        assert_eq!(&a & &b, and);
+        assert_eq!(&a ^ &b, xor);
+        let mut diff = a.clone();
+        diff.and_not_inplace(&b);
+        assert_eq!(diff, a_not_b);
+        let mut x = a.clone();
+        x.track_counts();
+        x.xor_inplace(&b);
+        assert_eq!(x, xor);
+        assert_eq!(x.col_row_counts(), naive_col_row_counts(&xor));
+    }
+
+    #[test]
+    fn test_sub_grid() {
+        let mut rng = thread_rng();
+        // Columns shorter than a word, whole words, and longer than a word at any offset
+        for (w, h) in [(16, 16), (10, 7), (64, 2), (5, 65), (4, 200)] {
+            let mut g = Grid::new(w, h);
+            g.sample(&mut rng, w * h / 2, &SampleStrategy::RandomPoints);
+            let sub = g.sub_grid(1..h, 2..w - 1).unwrap();
+            assert_eq!((sub.w(), sub.h()), (w - 3, h - 1));
+            for i in 0..h - 1 {
+                for j in 0..w - 3 {
+                    assert_eq!(sub.get(i, j), g.get(i + 1, j + 2));
+                }
+            }
+            // and the row major copy has the same cells
+            assert_eq!(sub.col_row_counts(), naive_col_row_counts(&sub));
+            assert_eq!(g.subgrid(1, 2, h - 1, w - 3), sub);
+            assert_eq!(g.sub_grid(0..h, 0..w).unwrap(), g);
+            assert_eq!(g.sub_grid(0..0, 0..w).unwrap().count_ones(), 0);
+            assert_eq!(
+                g.sub_grid(0..h + 1, 0..w),
+                Err(Error::RegionOutOfBounds {
+                    rows: 0..h + 1,
+                    cols: 0..w,
+                    w,
+                    h
+                })
+            );
+            assert!(matches!(
+                g.sub_grid(0..h, w - 1..w + 1),
+                Err(Error::RegionOutOfBounds { .. })
+            ));
+            #[allow(clippy::reversed_empty_ranges)]
+            let backwards = 2..1;
+            assert!(matches!(
+                g.sub_grid(backwards, 0..w),
+                Err(Error::RegionOutOfBounds { .. })
+            ));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "aren't inside a 4x4 grid")]
+    fn test_subgrid_out_of_bounds() {
+        Grid::new(4, 4).subgrid(2, 0, 3, 4);
+    }
+
+    #[test]
+    fn test_embed() {
+        let g = Grid::from_indices(3, 2, [(0, 1), (1, 2)]).unwrap();
+        let big = g.embed(5, 4).unwrap();
+        assert_eq!(big, Grid::from_indices(5, 4, [(0, 1), (1, 2)]).unwrap());
+        assert_eq!(big.sub_grid(0..2, 0..3).unwrap(), g);
+        assert_eq!(
+            g.embed(2, 4),
+            Err(Error::RegionOutOfBounds {
+                rows: 0..2,
+                cols: 0..3,
+                w: 2,
+                h: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_random() {
+        let mut rng = SmallRng::seed_from_u64(316);
+        // Whole random words at 0.5 and in between, and a draw per cell near 0 and 1
+        for density in [0.001, 0.3, 0.5, 0.7, 0.999] {
+            let (w, h) = (256, 255);
+            let g = Grid::random(w, h, density, &mut rng).unwrap();
+            let n = (w * h) as f64;
+            let sigma = (n * density * (1.0 - density)).sqrt();
+            assert!(
+                (g.count_ones() as f64 - n * density).abs() < 5.0 * sigma,
+                "{}",
+                density
+            );
+            assert_eq!(g.col_row_counts(), naive_col_row_counts(&g));
+        }
+
+        assert_eq!(Grid::random(7, 3, 0.0, &mut rng).unwrap().count_ones(), 0);
+        assert_eq!(Grid::random(7, 3, 1.0, &mut rng).unwrap().count_ones(), 21);
+        assert_eq!(
+            Grid::random(7, 3, 1.5, &mut rng),
+            Err(Error::InvalidFraction(1.5))
+        );
+        assert_eq!(
+            Grid::random(7, 3, -0.1, &mut rng),
+            Err(Error::InvalidFraction(-0.1))
+        );
+    }
+
+    #[test]
+    fn test_random_k() {
+        let mut rng = thread_rng();
+        for k in [0, 1, 20, 35] {
+            assert_eq!(Grid::random_k(7, 5, k, &mut rng).unwrap().count_ones(), k);
+        }
+        assert_eq!(
+            Grid::random_k(7, 5, 36, &mut rng),
+            Err(Error::TooManyCells { k: 36, w: 7, h: 5 })
+        );
+    }
+
+    #[test]
+    fn test_thin() {
+        let mut g = Grid::new(16, 16);
+        g.sample(&mut thread_rng(), 64, &SampleStrategy::RandomPoints);
+        let before = g.clone();
+        g.thin(&mut thread_rng(), 1.0);
+        assert_eq!(g, before);
+        g.thin(&mut thread_rng(), 0.0);
+        assert_eq!(g.count_ones(), 0);
+    }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::HashSet;
+
+        let mut g = Grid::new(16, 16);
+        g.sample(&mut thread_rng(), 64, &SampleStrategy::RandomPoints);
+        let same = g.clone();
+        let mut flipped = g.clone();
+        flipped.set(3, 5, !g.get(3, 5));
+        // As many cells as `empty`, all unset too, but a different shape
+        let empty = Grid::new(16, 16);
+        let reshaped = Grid::new(8, 32);
+
+        let set: HashSet<Grid> = [g, same, flipped, empty, reshaped].into_iter().collect();
+        assert_eq!(set.len(), 4);
+        // Built in one go by `not` or cell by cell, the same grid hashes the same
+        let inverted = Grid::new(64, 1).not();
+        let mut filled = Grid::new(64, 1);
+        (0..64).for_each(|j| filled.set(0, j, true));
+        assert!(HashSet::from([inverted]).contains(&filled));
+    }
 }
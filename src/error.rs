@@ -0,0 +1,74 @@
+use std::ops::Range;
+
+use thiserror::Error;
+
+use crate::GridIndexError;
+
+/// What can go wrong building grids, combining them, or validating an `ExperimentConfig`
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum Error {
+    /// Two grids, or a grid and the shape it's meant to have, differ in size. Sizes are
+    /// `(w, h)`, as in `Grid::new`.
+    #[error("expected a {}x{} grid, got {}x{}", .expected.0, .expected.1, .got.0, .got.1)]
+    ShapeMismatch {
+        expected: (usize, usize),
+        got: (usize, usize),
+    },
+    /// A cell outside the grid it was set in
+    #[error(transparent)]
+    IndexOutOfBounds(#[from] GridIndexError),
+    /// A rectangle of cells that isn't inside a `w x h` grid
+    #[error("rows {rows:?} and columns {cols:?} aren't inside a {w}x{h} grid")]
+    RegionOutOfBounds {
+        rows: Range<usize>,
+        cols: Range<usize>,
+        w: usize,
+        h: usize,
+    },
+    /// More distinct cells asked for than a `w x h` grid has
+    #[error("can't set {k} cells in a {w}x{h} grid")]
+    TooManyCells { k: usize, w: usize, h: usize },
+    /// Rows of cells that aren't all the same length
+    #[error("row {row} has {len} cells, expected {expected}")]
+    RaggedRows {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    /// There's no erasure grid with this many dimensions
+    #[error("there are no {0}d grids")]
+    UnsupportedDims(usize),
+    /// A sampling or mask strategy that can't be used as asked
+    #[error("invalid strategy: {0}")]
+    InvalidStrategy(String),
+    /// Reconstruction thresholds that a grid doesn't support or can't meet
+    #[error("invalid thresholds: {0}")]
+    InvalidThresholds(String),
+    /// A fraction or probability outside of `0..=1`
+    #[error("{0} isn't between 0 and 1")]
+    InvalidFraction(f64),
+    /// A search range that doesn't bracket the target, see `find_critical_clients`
+    #[error("{0}")]
+    InvalidBracket(String),
+    /// Every field of an `ExperimentConfig` that can't be run, see
+    /// `ExperimentConfig::validate`
+    #[error(
+        "invalid config: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    InvalidConfig(Vec<ConfigError>),
+    /// Writing a Parquet file failed, see `write_parquet`. Holds the Parquet error's message,
+    /// so errors can still be cloned and compared.
+    #[cfg(feature = "parquet")]
+    #[error("couldn't write Parquet: {0}")]
+    Parquet(String),
+}
+
+/// A field of an `ExperimentConfig` that can't be run as it is
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{field}: {message}")]
+pub struct ConfigError {
+    /// The field's name, as in `ExperimentConfig::header`
+    pub field: &'static str,
+    pub message: String,
+}
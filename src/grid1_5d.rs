@@ -0,0 +1,174 @@
+use std::fmt::Debug;
+
+use rand::{distributions::Uniform, prelude::Distribution, RngCore};
+
+use crate::{
+    base_grid::{Grid, SampleStrategy},
+    traits::{Dims, Reconstructable, Shape},
+};
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+/// A 2d grid that is only erasure encoded along its rows, so the original `rows x cols`
+/// data is extended to `rows x e * cols`. There's no encoding across rows, so a cell can
+/// only be reconstructed if
+/// - It exist in the grid
+/// - It belongs to a row with at least cols points
+#[allow(non_camel_case_types)]
+pub struct Grid1_5dErasure {
+    shape: Shape,
+    // the grid stored column wise, same as every other grid
+    grid: Grid,
+}
+
+impl Grid1_5dErasure {
+    #[cfg(test)]
+    pub fn from_grid(grid: Grid, shape: Shape) -> Result<Self, crate::error::Error> {
+        let expected = (shape.extension * shape.cols, shape.rows);
+        if (grid.w(), grid.h()) != expected {
+            return Err(crate::error::Error::ShapeMismatch {
+                expected,
+                got: (grid.w(), grid.h()),
+            });
+        }
+        Ok(Self { shape, grid })
+    }
+}
+
+impl Reconstructable for Grid1_5dErasure {
+    type Index = (usize, usize);
+
+    fn dims() -> Dims {
+        Dims::OneAndAHalf
+    }
+
+    fn random_index<R: RngCore>(rng: &mut R, shape: Shape) -> Self::Index {
+        // A point in the original data
+        let row = Uniform::from(0..shape.rows).sample(rng);
+        let col = Uniform::from(0..shape.cols).sample(rng);
+        (row, col)
+    }
+
+    fn new_mask_at(shape: Shape, (row, col): Self::Index) -> (Grid, Self::Index) {
+        let Shape {
+            rows,
+            cols,
+            extension: e,
+        } = shape;
+        assert!(
+            row < rows && col < cols,
+            "target must be in the original data"
+        );
+        let mut mask = Grid::new(e * cols, rows);
+        mask.set(row, col, true);
+        // Censor every extended point in its row:
+        // that is (row, cols)..(row, e*cols)
+        for j in cols..e * cols {
+            mask.set(row, j, true);
+        }
+        assert_eq!(mask.count_ones(), e * cols - cols + 1);
+        (mask.not(), (row, col))
+    }
+
+    fn new(shape: Shape) -> Self {
+        Grid1_5dErasure {
+            shape,
+            grid: Grid::new(shape.extension * shape.cols, shape.rows),
+        }
+    }
+
+    fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    fn can_reconstruct(&self, (row, col): Self::Index) -> bool {
+        if self.grid.get(row, col) {
+            return true;
+        }
+        self.grid.count_row(row) >= self.shape.cols
+    }
+
+    fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn reconstruction_closure(&self) -> Grid {
+        let mut rgrid = self.grid.clone();
+        for (row, count) in self.grid.row_counts().into_iter().enumerate() {
+            if count >= self.shape.cols {
+                for col in 0..rgrid.w() {
+                    rgrid.set(row, col, true);
+                }
+            }
+        }
+        rgrid
+    }
+
+    #[inline(always)]
+    fn sample<R: RngCore>(&mut self, rng: &mut R, amount: usize, sample_strategy: &SampleStrategy) {
+        self.grid.sample(rng, amount, sample_strategy)
+    }
+
+    #[inline(always)]
+    fn sample_exclusion<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        amount: usize,
+        sample_strategy: &SampleStrategy,
+        mask: &Grid,
+    ) -> usize {
+        self.sample(rng, amount, sample_strategy);
+        let sampled = self.grid.count_ones();
+        self.grid.and_inplace(mask);
+        sampled - self.grid.count_ones()
+    }
+
+    #[inline(always)]
+    fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64) {
+        self.grid.thin(rng, keep)
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.grid.clear()
+    }
+
+    #[inline(always)]
+    fn merge_from(&mut self, other: &Self) {
+        assert_eq!(self.shape, other.shape);
+        self.grid.or_inplace(&other.grid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::thread_rng;
+
+    use super::*;
+
+    fn from_bool_grid(bools: [[bool; 4]; 2]) -> Grid1_5dErasure {
+        let grid = Grid::from_bool_grid(bools);
+        Grid1_5dErasure::from_grid(grid, Shape::square(2)).unwrap()
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let g = from_bool_grid([[false, false, true, true], [true, false, false, false]]);
+        // Two of the first row's four cells are enough
+        assert!(g.can_reconstruct((0, 0)));
+        assert!(g.can_reconstruct((0, 1)));
+        // But columns carry no redundancy
+        assert!(g.can_reconstruct((1, 0)));
+        assert!(!g.can_reconstruct((1, 1)));
+        assert_eq!(g.reconstruction_closure().count_ones(), 5);
+    }
+
+    #[test]
+    fn test_mask() {
+        let shape = Shape::new(3, 4);
+        let (mask, target) = Grid1_5dErasure::new_mask(&mut thread_rng(), shape);
+        assert_eq!((mask.w(), mask.h()), (8, 3));
+        assert_eq!(mask.count_ones(), 24 - 5);
+        let g = Grid1_5dErasure::from_grid(mask, shape).unwrap();
+        assert!(!g.can_reconstruct(target));
+    }
+}
@@ -1,68 +1,188 @@
 use std::fmt::Debug;
 
-use rand::{distributions::Uniform, prelude::Distribution, RngCore};
+use rand::{
+    distributions::{Uniform, WeightedIndex},
+    prelude::Distribution,
+    RngCore,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    base_grid::{Grid, SampleStrategy},
-    traits::Reconstructable,
+    base_grid::{Grid, MaskError, SampleStrategy},
+    error::Error,
+    traits::{Dims, ErasureParts, Reconstructable, Shape},
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "ErasureParts")]
+/// Each of the `cols` columns is extended from `rows` to `extension * rows` cells, and a
+/// column can be reconstructed once any `rows` of its cells are present. A cell that is
+/// present itself is always available, however few of its column's cells are.
 pub struct Grid1dErasure {
-    n: usize,
+    shape: Shape,
     // the grid stored column wise to make adding along columns more efficient
     grid: Grid,
 }
 
 impl Grid1dErasure {
-    #[cfg(test)]
-    pub fn from_grid(grid: Grid, n: usize) -> Result<Self, ()> {
-        if grid.w() != n || grid.h() != 2 * n {
-            return Err(());
+    /// Wraps an already sampled `cols x extension * rows` grid. Errors if it's a different size.
+    pub fn from_grid(grid: Grid, shape: Shape) -> Result<Self, Error> {
+        let expected = (shape.cols, shape.extension * shape.rows);
+        if (grid.w(), grid.h()) != expected {
+            return Err(Error::ShapeMismatch {
+                expected,
+                got: (grid.w(), grid.h()),
+            });
+        }
+        Ok(Self { shape, grid })
+    }
+
+    /// The cells present, without any reconstruction
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Fills every column with at least `rows` cells in place. Returns whether anything
+    /// was recovered.
+    pub fn reconstruct_step(&mut self) -> bool {
+        let closure = self.reconstruction_closure();
+        let changed = closure != self.grid;
+        self.grid = closure;
+        changed
+    }
+
+    /// Same as `new_mask`, but censors column `col` with probability proportional to
+    /// `col_weights[col]`, for adversaries that go after some columns more than others.
+    /// Errors unless there's a weight for every column and they're valid `WeightedIndex`
+    /// weights.
+    pub fn new_mask_weighted<R: RngCore>(
+        rng: &mut R,
+        shape: Shape,
+        col_weights: &[f64],
+    ) -> Result<(Grid, (usize, usize)), Error> {
+        if col_weights.len() != shape.cols {
+            return Err(Error::InvalidStrategy(format!(
+                "{} column weights for {} columns",
+                col_weights.len(),
+                shape.cols
+            )));
         }
-        Ok(Self { n, grid })
+        let cols = WeightedIndex::new(col_weights).map_err(|e| {
+            Error::InvalidStrategy(format!("bad column weights {:?}: {}", col_weights, e))
+        })?;
+        let row = Uniform::from(0..shape.rows).sample(rng);
+        let col = cols.sample(rng);
+        Ok(Self::new_mask_at(shape, (row, col)))
+    }
+
+    /// Checks that `mask`, as returned by `new_mask_at`, censors `target` in a `shape` grid:
+    /// the target is withheld and its column is left with too few cells to recover it.
+    /// Masks may withhold more than `new_mask_at` does.
+    pub fn validate_mask(
+        mask: &Grid,
+        shape: Shape,
+        target: (usize, usize),
+    ) -> Result<(), MaskError> {
+        MaskError::check(
+            mask,
+            (shape.cols, shape.extension * shape.rows),
+            (shape.rows, shape.cols),
+            target,
+        )?;
+        let grid = Self::from_grid(mask.clone(), shape).expect("the mask's size was checked");
+        if grid.can_reconstruct(target) {
+            return Err(MaskError::Recoverable);
+        }
+        Ok(())
+    }
+
+    /// Reconstructs in place until nothing more can be recovered, returning the number of
+    /// steps that recovered something. Columns don't depend on each other, so that's at
+    /// most 1.
+    pub fn reconstruct_fixpoint(&mut self) -> usize {
+        self.reconstruct_step() as usize
+    }
+}
+
+impl TryFrom<ErasureParts> for Grid1dErasure {
+    type Error = Error;
+
+    fn try_from(ErasureParts { shape, grid }: ErasureParts) -> Result<Self, Self::Error> {
+        Self::from_grid(grid, shape)
     }
 }
 
-// Height in P must be even
 impl Reconstructable for Grid1dErasure {
     type Index = (usize, usize);
 
-    fn dims() -> usize {
-        1
+    fn dims() -> Dims {
+        Dims::One
     }
 
-    fn new_mask<R: RngCore>(rng: &mut R, n: usize) -> (Grid, Self::Index) {
-        let mut mask = Grid::new(n, 2 * n);
-        // Pick a point in the lower half to censor
-        let row = Uniform::from(0..n).sample(rng);
-        let col = Uniform::from(0..n).sample(rng);
+    fn random_index<R: RngCore>(rng: &mut R, shape: Shape) -> Self::Index {
+        // A point in the original data
+        let row = Uniform::from(0..shape.rows).sample(rng);
+        let col = Uniform::from(0..shape.cols).sample(rng);
+        (row, col)
+    }
+
+    fn new_mask_at(shape: Shape, (row, col): Self::Index) -> (Grid, Self::Index) {
+        let Shape {
+            rows,
+            cols,
+            extension: e,
+        } = shape;
+        assert!(
+            row < rows && col < cols,
+            "target must be in the original data"
+        );
+        let mut mask = Grid::new(cols, e * rows);
         mask.set(row, col, true);
-        // Censor n points from the upper half of the grid:
-        // that is (n, col)..(2*n, col)
-        for i in n..2 * n {
+        // Censor every extended point in its column:
+        // that is (rows, col)..(e*rows, col)
+        for i in rows..e * rows {
             mask.set(i, col, true);
         }
-        assert_eq!(mask.count_ones(), n + 1);
+        assert_eq!(mask.count_ones(), e * rows - rows + 1);
         (mask.not(), (row, col))
     }
 
-    fn new(n: usize) -> Self {
+    fn new(shape: Shape) -> Self {
         Grid1dErasure {
-            n,
-            grid: Grid::new(n, 2 * n),
+            shape,
+            grid: Grid::new(shape.cols, shape.extension * shape.rows),
         }
     }
 
-    fn grid_size(&self) -> usize {
-        self.n
+    fn shape(&self) -> Shape {
+        self.shape
     }
 
     fn can_reconstruct(&self, (row, col): Self::Index) -> bool {
         if self.grid.get(row, col) {
             return true;
         }
-        self.grid.count_columnar(col) >= self.n
+        self.grid.count_columnar(col) >= self.shape.rows
+    }
+
+    fn can_reconstruct_all(&self) -> bool {
+        (0..self.grid.w()).all(|col| self.grid.count_columnar(col) >= self.shape.rows)
+    }
+
+    fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn reconstruction_closure(&self) -> Grid {
+        let mut rgrid = self.grid.clone();
+        for col in 0..rgrid.w() {
+            if self.grid.count_columnar(col) >= self.shape.rows {
+                for row in 0..rgrid.h() {
+                    rgrid.set(row, col, true);
+                }
+            }
+        }
+        rgrid
     }
 
     #[inline(always)]
@@ -77,18 +197,27 @@ impl Reconstructable for Grid1dErasure {
         amount: usize,
         sample_strategy: &SampleStrategy,
         mask: &Grid,
-    ) {
+    ) -> usize {
         self.sample(rng, amount, sample_strategy);
-        self.grid.and_inplace(&mask)
+        let sampled = self.grid.count_ones();
+        self.grid.and_inplace(mask);
+        sampled - self.grid.count_ones()
     }
 
     #[inline(always)]
-    fn merge(self, other: Self) -> Self {
-        assert_eq!(self.n, other.n);
-        Self {
-            n: self.n,
-            grid: self.grid | other.grid,
-        }
+    fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64) {
+        self.grid.thin(rng, keep)
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.grid.clear()
+    }
+
+    #[inline(always)]
+    fn merge_from(&mut self, other: &Self) {
+        assert_eq!(self.shape, other.shape);
+        self.grid.or_inplace(&other.grid);
     }
 }
 
@@ -96,9 +225,42 @@ impl Reconstructable for Grid1dErasure {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_validate_mask() {
+        let shape = Shape::new(4, 3).with_extension(3);
+        let (mask, target) = Grid1dErasure::new_mask_at(shape, (1, 2));
+        assert_eq!(Grid1dErasure::validate_mask(&mask, shape, target), Ok(()));
+
+        // Handing back one of the extended cells of the target's column makes it recoverable
+        let mut tampered = mask.clone();
+        tampered.set(5, 2, true);
+        assert_eq!(
+            Grid1dErasure::validate_mask(&tampered, shape, target),
+            Err(MaskError::Recoverable)
+        );
+        tampered.set(1, 2, true);
+        assert_eq!(
+            Grid1dErasure::validate_mask(&tampered, shape, target),
+            Err(MaskError::TargetAvailable)
+        );
+        // The mask is for a different target
+        assert_eq!(
+            Grid1dErasure::validate_mask(&mask, shape, (1, 0)),
+            Err(MaskError::TargetAvailable)
+        );
+        assert_eq!(
+            Grid1dErasure::validate_mask(&mask, shape, (4, 2)),
+            Err(MaskError::TargetOutside { row: 4, col: 2 })
+        );
+        assert!(matches!(
+            Grid1dErasure::validate_mask(&mask, Shape::new(4, 4), target),
+            Err(MaskError::WrongSize { .. })
+        ));
+    }
+
     fn from_bool_grid(bools: [[bool; 2]; 4]) -> Grid1dErasure {
         let grid = Grid::from_bool_grid(bools);
-        Grid1dErasure::from_grid(grid, 2).unwrap()
+        Grid1dErasure::from_grid(grid, Shape::square(2)).unwrap()
     }
 
     #[test]
@@ -114,4 +276,183 @@ mod test {
         println!("{:?}", &res);
         assert_eq!(res, res_cmp);
     }
+
+    #[test]
+    fn test_can_reconstruct_all() {
+        let t = true;
+        let f = false;
+        let g = from_bool_grid([[t, f], [f, t], [t, f], [t, t]]);
+        assert!(g.can_reconstruct_all());
+        assert!(g.can_reconstruct_all_cells());
+        // Column 1 is one cell short
+        let g = from_bool_grid([[t, f], [f, f], [t, f], [t, t]]);
+        assert!(!g.can_reconstruct_all());
+        assert!(!g.can_reconstruct_all_cells());
+    }
+
+    #[test]
+    fn test_reconstruct_step() {
+        let t = true;
+        let f = false;
+        let mut g = from_bool_grid([[t, f], [f, f], [t, t], [f, f]]);
+        assert_eq!(g.reconstruction_closure().count_ones(), 5);
+        assert!(g.reconstruct_step());
+        // Column 0 is filled, column 1 is still one short
+        assert_eq!(
+            g.grid(),
+            &Grid::from_bool_grid([[t, f], [t, f], [t, t], [t, f]])
+        );
+        assert!(!g.reconstruct_step());
+
+        let mut g = from_bool_grid([[t, f], [f, t], [t, t], [f, f]]);
+        assert_eq!(g.reconstruct_fixpoint(), 1);
+        assert_eq!(g.grid().count_ones(), 8);
+        assert_eq!(g.reconstruct_fixpoint(), 0);
+        assert_eq!(
+            Grid1dErasure::from_grid(Grid::new(2, 2), Shape::square(2)).unwrap_err(),
+            Error::ShapeMismatch {
+                expected: (2, 4),
+                got: (2, 2)
+            }
+        );
+    }
+
+    #[test]
+    fn test_extension() {
+        let t = true;
+        let f = false;
+        // 2 columns extended to 8 cells each, only 2 of which are needed
+        let g = Grid1dErasure::from_grid(
+            Grid::from_bool_grid([
+                [f, f],
+                [f, f],
+                [f, f],
+                [f, t],
+                [f, f],
+                [t, f],
+                [f, f],
+                [t, f],
+            ]),
+            Shape::square(2).with_extension(4),
+        )
+        .unwrap();
+        assert!(g.can_reconstruct((0, 0)));
+        assert!(!g.can_reconstruct((0, 1)));
+
+        let shape = Shape::square(2).with_extension(4);
+        let (mask, target) = Grid1dErasure::new_mask(&mut rand::thread_rng(), shape);
+        assert_eq!(mask.count_ones(), 16 - (8 - 2 + 1));
+        let g = Grid1dErasure::from_grid(mask, shape).unwrap();
+        assert!(!g.can_reconstruct(target));
+    }
+
+    #[test]
+    fn test_new_mask_weighted() {
+        let shape = Shape::new(4, 8);
+        let mut weights = vec![0.001; 8];
+        weights[0] = 100.0;
+        let mut rng = rand::thread_rng();
+        let col_0 = (0..200)
+            .filter(|_| {
+                let (mask, (_, col)) =
+                    Grid1dErasure::new_mask_weighted(&mut rng, shape, &weights).unwrap();
+                // Only the target's column is censored
+                assert!(!mask.get(7, col));
+                col == 0
+            })
+            .count();
+        assert!(col_0 >= 190, "{}", col_0);
+
+        for weights in [&[1.0; 4][..], &[0.0; 8][..], &[-1.0; 8][..]] {
+            assert!(matches!(
+                Grid1dErasure::new_mask_weighted(&mut rng, shape, weights),
+                Err(Error::InvalidStrategy(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_censor_set() {
+        let n = 4;
+        let (censored, (row, col)) =
+            Grid1dErasure::new_censor_set(&mut rand::thread_rng(), Shape::square(n));
+        let cells = censored.masked_cells();
+        // The target and the n extended cells in its column
+        assert_eq!(cells.len(), n + 1);
+        assert!(cells.contains(&(row, col)));
+        assert!(cells.iter().all(|&(_, j)| j == col));
+        assert!((n..2 * n).all(|i| cells.contains(&(i, col))));
+    }
+
+    // Either rule is enough on its own: the cell itself, or enough of its column
+    #[test]
+    fn test_direct_cell() {
+        let mut g = Grid1dErasure::new(Shape::square(4));
+        g.grid.set(2, 1, true);
+        assert!(g.can_reconstruct((2, 1)));
+        assert!(!g.can_reconstruct((3, 1)));
+        for i in 5..8 {
+            g.grid.set(i, 1, true);
+        }
+        assert!(g.can_reconstruct((3, 1)));
+    }
+
+    #[test]
+    fn test_rectangular() {
+        // 16 columns of 4 cells each, extended to 8
+        let shape = Shape::new(4, 16);
+        let mut g = Grid1dErasure::new(shape);
+        assert_eq!((g.grid.w(), g.grid.h()), (16, 8));
+        for i in [0, 2, 5] {
+            g.grid.set(i, 11, true);
+        }
+        assert!(!g.can_reconstruct((1, 11)));
+        g.grid.set(7, 11, true);
+        assert!(g.can_reconstruct((1, 11)));
+        assert!(!g.can_reconstruct((1, 10)));
+        assert_eq!(g.reconstruction_closure().count_ones(), 8);
+
+        let (mask, target) = Grid1dErasure::new_mask(&mut rand::thread_rng(), shape);
+        assert_eq!(mask.count_ones(), 16 * 8 - 5);
+        let g = Grid1dErasure::from_grid(mask, shape).unwrap();
+        assert!(!g.can_reconstruct(target));
+    }
+
+    // Nothing about the encoding needs n to be even
+    #[test]
+    fn test_odd_n() {
+        for n in [3, 5] {
+            let shape = Shape::square(n);
+            let (mask, target) = Grid1dErasure::new_mask(&mut rand::thread_rng(), shape);
+            assert_eq!((mask.w(), mask.h()), (n, 2 * n));
+            assert_eq!(mask.count_ones(), 2 * n * n - (n + 1));
+            let g = Grid1dErasure::from_grid(mask, shape).unwrap();
+            assert!(!g.can_reconstruct(target));
+
+            // The column needs exactly n cells, not n + 1 or n - 1
+            let mut g = Grid1dErasure::new(shape);
+            for i in n + 1..2 * n {
+                g.grid.set(i, n - 1, true);
+            }
+            assert!(!g.can_reconstruct((0, n - 1)));
+            g.grid.set(n, n - 1, true);
+            assert!(g.can_reconstruct((0, n - 1)));
+            assert!(!g.can_reconstruct((0, 0)));
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let shape = Shape::new(5, 3);
+        let mut g = Grid1dErasure::new(shape);
+        g.sample(&mut rand::thread_rng(), 12, &SampleStrategy::RandomPoints);
+        let json = serde_json::to_string(&g).unwrap();
+        assert_eq!(serde_json::from_str::<Grid1dErasure>(&json).unwrap(), g);
+
+        // The grid has to be the size the shape says it is
+        let wrong_shape = json.replace(r#""rows":5"#, r#""rows":4"#);
+        assert_ne!(wrong_shape, json);
+        assert!(serde_json::from_str::<Grid1dErasure>(&wrong_shape).is_err());
+        assert_eq!((g.grid.w(), g.grid.h()), (3, 10));
+    }
 }
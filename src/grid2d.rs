@@ -1,119 +1,605 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::Range};
 
-use rand::{distributions::Uniform, prelude::Distribution, RngCore};
+use rand::{distributions::Uniform, prelude::Distribution, seq::index, RngCore};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    base_grid::{Grid, SampleStrategy},
-    traits::Reconstructable,
+    base_grid::{Grid, MaskError, MaskStrategy, SampleStrategy},
+    error::Error,
+    pbm::GridError,
+    traits::{Dims, Reconstructable, Shape},
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Grid2dParts")]
 /// This is a 2d grid with erasure encoding as follows
-///     First, interpolate points on the 2d grid horizontally, doubling the width
-///     Then interpolate this wide/short grid vertically,doubling the height
+///     First, interpolate points on the 2d grid horizontally, extending the width by `e`
+///     Then interpolate this wide/short grid vertically, extending the height by `e`
+/// The original data is `rows x cols`, so the extended grid is `e * rows x e * cols`
+/// (`e` is 2 unless a different extension is asked for).
 /// Points can be reconstructed if
 /// - It exist in the grid
-/// - It belongs to a row with at least N/2 points
-/// - It belongs to a row where at leastNWIDTH/2 points can be reconstructed
+/// - It belongs to a row with at least cols points
+/// - It belongs to a column with at least rows points
+/// - It belongs to a row/column where enough points can be reconstructed
+///
+/// Other coding rates can be simulated by changing how many points a row or column needs
+/// with `with_thresholds`.
 pub struct Grid2dErasure {
     // undelying size of grid
-    n: usize,
+    shape: Shape,
     // the grid stored column wise to make adding along columns more efficient
     grid: Grid,
+    // how many cells a row needs to be reconstructed, `shape.cols` by default
+    row_threshold: usize,
+    // how many cells a column needs to be reconstructed, `shape.rows` by default
+    col_threshold: usize,
+}
+
+/// A quadrant of a 2d grid with extension 2, where the top left is the original data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Quadrant {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl Quadrant {
+    /// The rows and columns of the quadrant in a `shape` grid
+    fn ranges(self, shape: Shape) -> (Range<usize>, Range<usize>) {
+        let Shape {
+            rows,
+            cols,
+            extension: e,
+        } = shape;
+        let (top, bottom) = (0..rows, rows..e * rows);
+        let (left, right) = (0..cols, cols..e * cols);
+        match self {
+            Quadrant::TopLeft => (top, left),
+            Quadrant::TopRight => (top, right),
+            Quadrant::BottomLeft => (bottom, left),
+            Quadrant::BottomRight => (bottom, right),
+        }
+    }
+
+    /// The quadrant diagonally across from this one
+    pub fn opposite(self) -> Quadrant {
+        match self {
+            Quadrant::TopLeft => Quadrant::BottomRight,
+            Quadrant::TopRight => Quadrant::BottomLeft,
+            Quadrant::BottomLeft => Quadrant::TopRight,
+            Quadrant::BottomRight => Quadrant::TopLeft,
+        }
+    }
+}
+
+/// The serialized fields of a `Grid2dErasure`. Grids serialized before thresholds existed
+/// get the default ones.
+#[derive(Deserialize)]
+struct Grid2dParts {
+    shape: Shape,
+    grid: Grid,
+    row_threshold: Option<usize>,
+    col_threshold: Option<usize>,
 }
 
 impl Grid2dErasure {
-    #[cfg(test)]
-    pub fn from_grid(grid: Grid, n: usize) -> Result<Self, ()> {
-        if grid.w() != 2 * n || grid.h() != 2 * n {
-            return Err(());
+    /// The cells present, without any reconstruction
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Wraps an already sampled `extension * cols x extension * rows` grid, with the default
+    /// thresholds. Errors if it's a different size.
+    pub fn from_grid(grid: Grid, shape: Shape) -> Result<Self, Error> {
+        let expected = (shape.extension * shape.cols, shape.extension * shape.rows);
+        if (grid.w(), grid.h()) != expected {
+            return Err(Error::ShapeMismatch {
+                expected,
+                got: (grid.w(), grid.h()),
+            });
         }
-        Ok(Self { n, grid })
+        Ok(Self {
+            shape,
+            grid,
+            row_threshold: shape.cols,
+            col_threshold: shape.rows,
+        })
+    }
+
+    /// Same as `new_mask`, but fully censors the `censored` quadrant rather than the bottom
+    /// right one, with the target in the opposite quadrant, to look for asymmetries between
+    /// the original data and the parity cells. Quadrants are only the same size with
+    /// extension 2, so errors for anything but `Quadrant::BottomRight` otherwise.
+    pub fn new_mask_in<R: RngCore>(
+        rng: &mut R,
+        shape: Shape,
+        censored: Quadrant,
+    ) -> Result<(Grid, (usize, usize)), Error> {
+        if shape.extension != 2 && censored != Quadrant::BottomRight {
+            return Err(Error::InvalidStrategy(format!(
+                "{:?} masks need extension 2, not {}",
+                censored, shape.extension
+            )));
+        }
+        let (target_rows, target_cols) = censored.opposite().ranges(shape);
+        let row = Uniform::from(target_rows).sample(rng);
+        let col = Uniform::from(target_cols).sample(rng);
+        let (block_rows, block_cols) = censored.ranges(shape);
+
+        // As in `new_mask_at`: the target, its row and column across the block, and the block
+        let mut mask = Grid::new(shape.extension * shape.cols, shape.extension * shape.rows);
+        mask.set(row, col, true);
+        for k in block_cols.clone() {
+            mask.set(row, k, true);
+        }
+        for k in block_rows.clone() {
+            mask.set(k, col, true);
+        }
+        for i in block_rows.clone() {
+            for j in block_cols.clone() {
+                mask.set(i, j, true);
+            }
+        }
+        let (h, w) = (block_rows.len(), block_cols.len());
+        assert_eq!(mask.count_ones(), h * w + h + w + 1);
+
+        Ok((mask.not(), (row, col)))
+    }
+
+    /// Checks that `mask`, as returned by `new_mask_with`, censors `target` in a `shape` grid:
+    /// the target is withheld and can't be reconstructed from the cells that are left, with
+    /// the default thresholds. Masks may withhold more than `new_mask_with` does.
+    pub fn validate_mask(
+        mask: &Grid,
+        shape: Shape,
+        target: (usize, usize),
+    ) -> Result<(), MaskError> {
+        MaskError::check(
+            mask,
+            (shape.extension * shape.cols, shape.extension * shape.rows),
+            (shape.rows, shape.cols),
+            target,
+        )?;
+        let grid = Self::from_grid(mask.clone(), shape).expect("the mask's size was checked");
+        if grid.can_reconstruct(target) {
+            return Err(MaskError::Recoverable);
+        }
+        Ok(())
+    }
+
+    /// An empty grid where a row needs `row_threshold` cells to be reconstructed and a
+    /// column `col_threshold`, rather than `shape.cols` and `shape.rows`. E.g. with
+    /// `extension` 4, a `row_threshold` of `3 * shape.cols` simulates a rate 3/4 code
+    /// horizontally. Errors unless both are between 1 and the extended line's length.
+    pub fn with_thresholds(
+        shape: Shape,
+        row_threshold: usize,
+        col_threshold: usize,
+    ) -> Result<Self, Error> {
+        let (w, h) = (shape.extension * shape.cols, shape.extension * shape.rows);
+        if row_threshold == 0 || row_threshold > w {
+            return Err(Error::InvalidThresholds(format!(
+                "a row threshold of {} doesn't fit rows of {} cells",
+                row_threshold, w
+            )));
+        }
+        if col_threshold == 0 || col_threshold > h {
+            return Err(Error::InvalidThresholds(format!(
+                "a column threshold of {} doesn't fit columns of {} cells",
+                col_threshold, h
+            )));
+        }
+        Ok(Self {
+            row_threshold,
+            col_threshold,
+            ..Self::new(shape)
+        })
+    }
+
+    /// How many cells a row and a column need to be reconstructed
+    pub fn thresholds(&self) -> (usize, usize) {
+        (self.row_threshold, self.col_threshold)
+    }
+
+    /// This grid as seen by a client of a square grid with `target_n` rows and columns of
+    /// original data: every cell keeps its coordinates, and the rest are missing. The
+    /// result has the default thresholds for its new shape. Panics if `target_n` is
+    /// smaller than either side of this grid's data.
+    pub fn project(&self, target_n: usize) -> Self {
+        let Shape {
+            rows,
+            cols,
+            extension: e,
+        } = self.shape;
+        assert!(
+            target_n >= rows && target_n >= cols,
+            "can't project a {}x{} grid down to {}x{}",
+            rows,
+            cols,
+            target_n,
+            target_n
+        );
+        let shape = Shape::square(target_n).with_extension(e);
+        let grid = self.grid.embed(e * target_n, e * target_n).unwrap();
+        Self::from_grid(grid, shape).unwrap()
+    }
+
+    /// The original `rows x cols` data, before any encoding
+    pub fn original(&self) -> Grid {
+        self.quadrant(false, false)
+    }
+
+    /// The cells added by extending the original rows
+    pub fn row_parity(&self) -> Grid {
+        self.quadrant(false, true)
+    }
+
+    /// The cells added by extending the original columns
+    pub fn col_parity(&self) -> Grid {
+        self.quadrant(true, false)
+    }
+
+    /// The cells added by extending both the extended rows and columns
+    pub fn cross_parity(&self) -> Grid {
+        self.quadrant(true, true)
+    }
+
+    fn quadrant(&self, parity_rows: bool, parity_cols: bool) -> Grid {
+        let Shape {
+            rows,
+            cols,
+            extension: e,
+        } = self.shape;
+        let rows = if parity_rows { rows..e * rows } else { 0..rows };
+        let cols = if parity_cols { cols..e * cols } else { 0..cols };
+        self.grid
+            .sub_grid(rows, cols)
+            .expect("quadrants are always inside the grid")
+    }
+
+    /// Same as `can_reconstruct`, but reconstructs in `scratch` rather than a fresh clone of
+    /// the grid, so many queries can share one allocation. `scratch` can start out as any
+    /// grid, e.g. `Grid::new(0, 0)`, and is resized on first use.
+    pub fn can_reconstruct_with(&self, (i, j): (usize, usize), scratch: &mut Grid) -> bool {
+        if self.grid.get(i, j) {
+            return true;
+        }
+        scratch.copy_from(&self.grid);
+        reconstruct_worklist(
+            scratch,
+            self.row_threshold,
+            self.col_threshold,
+            Some((i, j)),
+        )
+    }
+
+    /// Runs a single reconstruction pass in place, filling every column and row that has
+    /// enough cells. Returns whether anything was recovered. Lines are counted from scratch
+    /// each pass unless the grid was given tracked counts (`Grid::track_counts`) beforehand.
+    pub fn reconstruct_step(&mut self) -> bool {
+        reconstruct(&mut self.grid, self.row_threshold, self.col_threshold)
+    }
+
+    /// Runs reconstruction passes in place until one recovers nothing or `max_iters` passes
+    /// have run. Returns the number of passes that recovered something.
+    pub fn reconstruct_to_fixpoint(&mut self, max_iters: usize) -> usize {
+        let mut iters = 0;
+        while iters < max_iters && self.reconstruct_step() {
+            iters += 1;
+        }
+        iters
+    }
+
+    /// Runs reconstruction passes in place until nothing more can be recovered, leaving the
+    /// grid equal to its `reconstruction_closure`. Returns the number of passes that
+    /// recovered something.
+    pub fn reconstruct_fixpoint(&mut self) -> usize {
+        self.reconstruct_to_fixpoint(usize::MAX)
+    }
+
+    /// How many cells are present in each column and each row, without any reconstruction,
+    /// e.g. to see which lines a failed trial was short of
+    pub fn availability_profile(&self) -> (Vec<usize>, Vec<usize>) {
+        self.grid.col_row_counts()
+    }
+
+    /// How many more cells reconstruction recovers than were sampled, as a fraction of the
+    /// sampled cells. 0 if nothing was sampled.
+    pub fn coding_gain(&self) -> f64 {
+        let sampled = self.grid.count_ones();
+        if sampled == 0 {
+            return 0.0;
+        }
+        let recovered = self.reconstruction_closure().count_ones();
+        (recovered - sampled) as f64 / sampled as f64
+    }
+
+    /// Writes the grid to `{path_prefix}.pgm` for debugging, and if `with_closure` is set,
+    /// its reconstruction closure to `{path_prefix}-closure.pgm` too
+    pub fn dump_state(&self, path_prefix: &str, with_closure: bool) -> Result<(), GridError> {
+        self.grid.write_pgm(format!("{}.pgm", path_prefix))?;
+        if with_closure {
+            self.reconstruction_closure()
+                .write_pgm(format!("{}-closure.pgm", path_prefix))?;
+        }
+        Ok(())
     }
 }
 
-fn reconstruct(grid: &mut Grid) -> bool {
-    // Make a copy of the grid we started with for comparison later
-    let starting_grid = grid.clone();
+impl TryFrom<Grid2dParts> for Grid2dErasure {
+    type Error = Error;
+
+    fn try_from(parts: Grid2dParts) -> Result<Self, Self::Error> {
+        let Grid2dParts {
+            shape,
+            grid,
+            row_threshold,
+            col_threshold,
+        } = parts;
+        let (w, h) = (grid.w(), grid.h());
+        let mut res = Self::new_with_thresholds(shape, row_threshold, col_threshold)?;
+        if (w, h) != (res.grid.w(), res.grid.h()) {
+            return Err(Error::ShapeMismatch {
+                expected: (res.grid.w(), res.grid.h()),
+                got: (w, h),
+            });
+        }
+        res.grid = grid;
+        Ok(res)
+    }
+}
+
+/// One pass of recovering every column with at least `col_threshold` cells,
+/// then every row with at least `row_threshold` cells. Tracking the grid's counts first
+/// saves recounting them every pass.
+fn reconstruct(grid: &mut Grid, row_threshold: usize, col_threshold: usize) -> bool {
+    // Cells only ever get added, so the grid changed iff it has more of them afterwards
+    let starting_count = grid.count_ones();
     // count number of cells in each column and row
     let (col_c, row_c) = grid.col_row_counts();
-    // For each column
-    for j in 0..grid.w() {
-        // if we have enough at least n points
-        if col_c[j] >= grid.w() / 2 {
-            // Reconstruct the whole column
-            for i in 0..grid.h() {
-                grid.set(i, j, true);
+    let (w, h) = (grid.w(), grid.h());
+    // Every line with as many cells as it needs that isn't already full. Rows are picked
+    // by their counts before the columns are filled, as a single pass always has.
+    let cols: Vec<usize> = (0..w)
+        .filter(|&j| col_c[j] >= col_threshold && col_c[j] < h)
+        .collect();
+    let rows: Vec<usize> = (0..h)
+        .filter(|&i| row_c[i] >= row_threshold && row_c[i] < w)
+        .collect();
+    // Reconstruct them whole, a word at a time
+    grid.fill_cols(cols);
+    grid.fill_rows(rows);
+    grid.count_ones() != starting_count
+}
+
+/// A line of the grid waiting to be reconstructed
+enum Line {
+    Row(usize),
+    Col(usize),
+}
+
+/// Reconstructs `grid` to the same fixpoint repeated `reconstruct` passes reach, or until
+/// `target` is recovered if there is one. Returns whether the target was recovered.
+/// Instead of passing over the whole grid until nothing changes, only the lines that
+/// crossed their threshold are visited: filling a line can only push the lines through
+/// its newly set cells over theirs. That makes it linear in the cells recovered, rather
+/// than a count of the whole grid per pass.
+fn reconstruct_worklist(
+    grid: &mut Grid,
+    row_threshold: usize,
+    col_threshold: usize,
+    target: Option<(usize, usize)>,
+) -> bool {
+    let (w, h) = (grid.w(), grid.h());
+    let (mut col_c, mut row_c) = grid.col_row_counts();
+    let mut frontier: Vec<Line> = (0..w)
+        .filter(|&j| col_c[j] >= col_threshold && col_c[j] < h)
+        .map(Line::Col)
+        .chain(
+            (0..h)
+                .filter(|&i| row_c[i] >= row_threshold && row_c[i] < w)
+                .map(Line::Row),
+        )
+        .collect();
+    while let Some(line) = frontier.pop() {
+        match line {
+            Line::Col(j) => {
+                for i in 0..h {
+                    if grid.get(i, j) {
+                        continue;
+                    }
+                    grid.set(i, j, true);
+                    col_c[j] += 1;
+                    row_c[i] += 1;
+                    // Only queue the row once, as it crosses the threshold
+                    if row_c[i] == row_threshold && row_c[i] < w {
+                        frontier.push(Line::Row(i));
+                    }
+                }
+                if matches!(target, Some((_, tj)) if tj == j) {
+                    return true;
+                }
             }
-        }
-    }
-    // For each row
-    for i in 0..grid.h() {
-        // if we have enough
-        if row_c[i] >= grid.h() / 2 {
-            // reconstruct everything in the row
-            for j in 0..grid.w() {
-                grid.set(i, j, true);
+            Line::Row(i) => {
+                for j in 0..w {
+                    if grid.get(i, j) {
+                        continue;
+                    }
+                    grid.set(i, j, true);
+                    row_c[i] += 1;
+                    col_c[j] += 1;
+                    if col_c[j] == col_threshold && col_c[j] < h {
+                        frontier.push(Line::Col(j));
+                    }
+                }
+                if matches!(target, Some((ti, _)) if ti == i) {
+                    return true;
+                }
             }
         }
     }
-    // The grid changed if the grid we started with and the
-    // reconstruction we did are not the same
-    grid != &starting_grid
+    target.is_some_and(|(ti, tj)| grid.get(ti, tj))
 }
 
-// Height in P must be even
 impl Reconstructable for Grid2dErasure {
     type Index = (usize, usize);
 
-    fn new(n: usize) -> Self {
+    fn new(shape: Shape) -> Self {
         Grid2dErasure {
-            n,
-            grid: Grid::new(2 * n, 2 * n),
+            shape,
+            grid: Grid::new(shape.extension * shape.cols, shape.extension * shape.rows),
+            row_threshold: shape.cols,
+            col_threshold: shape.rows,
         }
     }
 
-    fn new_mask<R: RngCore>(rng: &mut R, n: usize) -> (Grid, Self::Index) {
-        let mut mask = Grid::new(2 * n, 2 * n);
-        // pick a point to censor in the first quadrant of the grid
-        let col = Uniform::from(0..n).sample(rng);
-        let row = Uniform::from(0..n).sample(rng);
+    fn new_with_thresholds(
+        shape: Shape,
+        row_threshold: Option<usize>,
+        col_threshold: Option<usize>,
+    ) -> Result<Self, Error> {
+        Self::with_thresholds(
+            shape,
+            row_threshold.unwrap_or(shape.cols),
+            col_threshold.unwrap_or(shape.rows),
+        )
+    }
+
+    fn random_index<R: RngCore>(rng: &mut R, shape: Shape) -> Self::Index {
+        // a point in the first quadrant of the grid
+        let col = Uniform::from(0..shape.cols).sample(rng);
+        let row = Uniform::from(0..shape.rows).sample(rng);
+        (row, col)
+    }
+
+    fn new_mask_at(shape: Shape, (row, col): Self::Index) -> (Grid, Self::Index) {
+        let Shape {
+            rows,
+            cols,
+            extension: e,
+        } = shape;
+        assert!(
+            row < rows && col < cols,
+            "target must be in the original data"
+        );
+        let mut mask = Grid::new(e * cols, e * rows);
         mask.set(row, col, true);
 
-        // Censor n more points in that specific row and column, so not enough
+        // Censor the extended part of that specific row and column, so not enough
         // erasure encoded data is directly available
-        for k in n..2 * n {
+        for k in cols..e * cols {
             mask.set(row, k, true);
+        }
+        for k in rows..e * rows {
             mask.set(k, col, true);
         }
 
-        // Then censor the n x n block in the last quadrant of the grid
-        for i in n..2 * n {
-            for j in n..2 * n {
+        // Then censor the block where the extended rows and columns cross
+        for i in rows..e * rows {
+            for j in cols..e * cols {
                 mask.set(i, j, true);
             }
         }
         // Check we censor
-        // 1. The n x n block
+        // 1. The (e - 1) * rows x (e - 1) * cols block
         // 2. The point itself
-        // 3. The n points in the points' row/column
-        assert!(mask.count_ones() == n * n + 2 * n + 1);
+        // 3. The extended points in the points' row/column
+        assert!(
+            mask.count_ones()
+                == (e - 1) * rows * (e - 1) * cols + (e - 1) * rows + (e - 1) * cols + 1
+        );
 
         (mask.not(), (row, col))
     }
 
+    fn new_mask_with<R: RngCore>(
+        rng: &mut R,
+        shape: Shape,
+        strategy: &MaskStrategy,
+    ) -> Result<(Grid, Self::Index), Error> {
+        let Shape {
+            rows,
+            cols,
+            extension: e,
+        } = shape;
+        match *strategy {
+            MaskStrategy::Block => Ok(Self::new_mask(rng, shape)),
+            MaskStrategy::WithholdRows { count } => {
+                // Unless more than (e - 1) * rows rows are missing, every column still has
+                // enough cells and the withheld rows can be recovered column by column
+                if count <= (e - 1) * rows || count > e * rows {
+                    return Err(Error::InvalidStrategy(format!(
+                        "WithholdRows needs {} < count <= {}, got {}",
+                        (e - 1) * rows,
+                        e * rows,
+                        count
+                    )));
+                }
+                // pick a point to censor in the first quadrant of the grid
+                let col = Uniform::from(0..cols).sample(rng);
+                let row = Uniform::from(0..rows).sample(rng);
+                // withhold its row along with count - 1 others picked from the rest
+                let withheld: Vec<usize> = index::sample(rng, e * rows - 1, count - 1)
+                    .into_iter()
+                    .map(|i| if i >= row { i + 1 } else { i })
+                    .chain([row])
+                    .collect();
+                let mask = Grid::full_rows(e * cols, e * rows, &withheld)
+                    .expect("the withheld rows are in the grid");
+                assert_eq!(mask.count_ones(), count * e * cols);
+
+                Ok((mask.not(), (row, col)))
+            }
+        }
+    }
+
     fn can_reconstruct(&self, (i, j): Self::Index) -> bool {
         // Is the cell present?
         if self.grid.get(i, j) {
             return true;
         }
         let mut rgrid = self.grid.clone();
-        // Try to reconstruct repeatedly until the grid stops changing
-        let mut changed = true;
-        while changed {
-            changed = reconstruct(&mut rgrid);
+        reconstruct_worklist(
+            &mut rgrid,
+            self.row_threshold,
+            self.col_threshold,
+            Some((i, j)),
+        )
+    }
+
+    fn can_reconstruct_single_pass(&self, (i, j): Self::Index) -> bool {
+        if self.grid.get(i, j) {
+            return true;
         }
-        return rgrid.get(i, j);
+        let mut rgrid = self.grid.clone();
+        reconstruct(&mut rgrid, self.row_threshold, self.col_threshold);
+        rgrid.get(i, j)
+    }
+
+    fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn reconstruction_rounds(&self) -> usize {
+        self.clone().reconstruct_fixpoint()
+    }
+
+    fn reconstruction_closure(&self) -> Grid {
+        let mut rgrid = self.grid.clone();
+        reconstruct_worklist(&mut rgrid, self.row_threshold, self.col_threshold, None);
+        rgrid
+    }
+
+    fn can_reconstruct_all(&self) -> bool {
+        // With raised thresholds, the original data can be complete without the rest
+        let Shape { rows, cols, .. } = self.shape;
+        self.reconstruction_closure()
+            .subgrid(0, 0, rows, cols)
+            .count_ones()
+            == rows * cols
     }
 
     #[inline(always)]
@@ -128,36 +614,67 @@ impl Reconstructable for Grid2dErasure {
         amount: usize,
         strategy: &SampleStrategy,
         mask: &Grid,
-    ) {
+    ) -> usize {
         self.sample(rng, amount, strategy);
-        self.grid.and_inplace(&mask)
+        let sampled = self.grid.count_ones();
+        self.grid.and_inplace(mask);
+        sampled - self.grid.count_ones()
+    }
+
+    #[inline(always)]
+    fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64) {
+        self.grid.thin(rng, keep)
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.grid.clear()
     }
 
     #[inline(always)]
-    fn merge(self, other: Self) -> Self {
-        assert_eq!(self.n, other.n);
-        Self {
-            n: self.n,
-            grid: self.grid | other.grid,
+    fn merge_from(&mut self, other: &Self) {
+        assert_eq!(self.shape, other.shape);
+        assert_eq!(self.thresholds(), other.thresholds());
+        self.grid.or_inplace(&other.grid);
+    }
+
+    fn try_merge_from(&mut self, other: &Self) -> Result<(), Error> {
+        if self.shape != other.shape {
+            return Err(Error::ShapeMismatch {
+                expected: (self.grid.w(), self.grid.h()),
+                got: (other.grid.w(), other.grid.h()),
+            });
         }
+        if self.thresholds() != other.thresholds() {
+            return Err(Error::InvalidThresholds(format!(
+                "can't merge a grid with thresholds {:?} into one with {:?}",
+                other.thresholds(),
+                self.thresholds()
+            )));
+        }
+        self.merge_from(other);
+        Ok(())
     }
 
-    fn dims() -> usize {
-        2
+    fn dims() -> Dims {
+        Dims::Two
     }
 
-    fn grid_size(&self) -> usize {
-        self.n
+    fn shape(&self) -> Shape {
+        self.shape
     }
 }
 
 #[cfg(test)]
 mod test {
+    use rand::{rngs::SmallRng, thread_rng, Rng, SeedableRng};
+
     use super::*;
+    use crate::grid1d::Grid1dErasure;
 
     fn from_bool_grid(bools: [[bool; 4]; 4]) -> Grid2dErasure {
         let grid = Grid::from_bool_grid(bools);
-        Grid2dErasure::from_grid(grid, 2).unwrap()
+        Grid2dErasure::from_grid(grid, Shape::square(2)).unwrap()
     }
 
     // Example of reconstruction with less than (W/2 + 1) * (H/2 + 1) points
@@ -169,7 +686,9 @@ mod test {
             [false, false, false, false],
             [false, false, false, true],
         ]);
-        reconstruct(&mut g1.grid);
+        // Rows, then columns, then the rest of the rows
+        assert_eq!(g1.reconstruction_rounds(), 3);
+        assert!(g1.reconstruct_step());
         let g2 = from_bool_grid([
             [true, true, true, true],
             [false, false, true, false],
@@ -177,7 +696,7 @@ mod test {
             [false, false, false, true],
         ]);
         assert_eq!(g1, g2);
-        reconstruct(&mut g1.grid);
+        assert!(g1.reconstruct_step());
         let g3 = from_bool_grid([
             [true, true, true, true],
             [false, false, true, true],
@@ -187,6 +706,537 @@ mod test {
         assert_eq!(g1, g3);
     }
 
+    #[test]
+    fn test_single_pass() {
+        // The fixture from `test_reconstruct`, where (2, 2) is only recovered by the second
+        // pass, once row 0 has filled column 2
+        let g = from_bool_grid([
+            [true, true, false, false],
+            [false, false, true, false],
+            [false, false, false, false],
+            [false, false, false, true],
+        ]);
+        assert!(g.can_reconstruct_single_pass((0, 3)));
+        assert!(!g.can_reconstruct_single_pass((2, 2)));
+        assert!(g.can_reconstruct((2, 2)));
+        // Present cells don't need a pass at all
+        assert!(g.can_reconstruct_single_pass((3, 3)));
+    }
+
+    #[test]
+    fn test_can_reconstruct_with() {
+        let mut rng = thread_rng();
+        let mut scratch = Grid::new(0, 0);
+        for amount in [8, 20, 40] {
+            let mut g = Grid2dErasure::new(Shape::new(3, 5));
+            g.sample(&mut rng, amount, &SampleStrategy::RandomPoints);
+            for i in 0..6 {
+                for j in 0..10 {
+                    assert_eq!(
+                        g.can_reconstruct_with((i, j), &mut scratch),
+                        g.can_reconstruct((i, j))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_availability_profile() {
+        let g = from_bool_grid([
+            [true, true, false, false],
+            [false, false, true, false],
+            [false, false, false, false],
+            [false, false, false, true],
+        ]);
+        let (cols, rows) = g.availability_profile();
+        assert_eq!(cols, [1, 1, 1, 1]);
+        assert_eq!(rows, [2, 1, 0, 1]);
+
+        let mut g = Grid2dErasure::new(Shape::new(3, 5));
+        g.sample(&mut thread_rng(), 12, &SampleStrategy::RandomPoints);
+        let (cols, rows) = g.availability_profile();
+        assert_eq!((cols.len(), rows.len()), (10, 6));
+        assert_eq!(cols.iter().sum::<usize>(), g.grid().count_ones());
+        assert_eq!(rows.iter().sum::<usize>(), g.grid().count_ones());
+    }
+
+    // The cell by cell pass `reconstruct` replaced
+    fn reconstruct_naive(grid: &mut Grid, row_threshold: usize, col_threshold: usize) -> bool {
+        let starting_grid = grid.clone();
+        let (col_c, row_c) = grid.col_row_counts();
+        for (j, &count) in col_c.iter().enumerate() {
+            if count >= col_threshold {
+                for i in 0..grid.h() {
+                    grid.set(i, j, true);
+                }
+            }
+        }
+        for (i, &count) in row_c.iter().enumerate() {
+            if count >= row_threshold {
+                for j in 0..grid.w() {
+                    grid.set(i, j, true);
+                }
+            }
+        }
+        grid != &starting_grid
+    }
+
+    #[test]
+    fn test_reconstruct_matches_naive() {
+        // Random grids of every density, in sizes whose lines straddle word boundaries, so
+        // the word-level fills have to agree with setting cells one at a time
+        let mut rng = SmallRng::seed_from_u64(310);
+        for (shape, row_threshold, col_threshold) in [
+            (Shape::square(4), 4, 4),
+            (Shape::new(3, 5), 5, 3),
+            (Shape::square(8).with_extension(3), 8, 8),
+            (Shape::new(33, 17), 17, 33),
+            (Shape::new(5, 40), 30, 4),
+        ] {
+            let (w, h) = (shape.extension * shape.cols, shape.extension * shape.rows);
+            for density in [0.05, 0.2, 0.4, 0.5, 0.7] {
+                let mut g = Grid::random(w, h, density, &mut rng).unwrap();
+                if rng.gen_bool(0.5) {
+                    g.track_counts();
+                }
+                let mut naive = g.clone();
+                loop {
+                    let changed = reconstruct(&mut g, row_threshold, col_threshold);
+                    let naive_changed = reconstruct_naive(&mut naive, row_threshold, col_threshold);
+                    assert_eq!(changed, naive_changed);
+                    assert_eq!(g, naive);
+                    assert_eq!(g.col_row_counts(), naive.col_row_counts());
+                    if !changed {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_step() {
+        let start = [
+            [true, true, false, false],
+            [false, false, true, false],
+            [false, false, false, false],
+            [false, false, false, true],
+        ];
+        let mut g = from_bool_grid(start);
+        assert!(g.reconstruct_step());
+        // Same as the first pass in `test_reconstruct`
+        let g2 = from_bool_grid([
+            [true, true, true, true],
+            [false, false, true, false],
+            [false, false, false, false],
+            [false, false, false, true],
+        ]);
+        assert_eq!(g, g2);
+
+        let mut g = from_bool_grid(start);
+        assert_eq!(g.reconstruct_to_fixpoint(1), 1);
+        assert_eq!(g, g2);
+        let mut g = from_bool_grid(start);
+        // Three passes recover everything, the fourth finds nothing left to do
+        assert_eq!(g.reconstruct_to_fixpoint(10), 3);
+        assert_eq!(g.grid.count_ones(), 16);
+        assert!(!g.reconstruct_step());
+
+        let mut g = from_bool_grid(start);
+        let closure = g.reconstruction_closure();
+        assert_eq!(g.reconstruct_fixpoint(), 3);
+        assert_eq!(g.grid(), &closure);
+        assert_eq!(g.reconstruct_fixpoint(), 0);
+    }
+
+    // Columns are h cells long and rows w cells long, so on a non-square grid the
+    // thresholds for each differ
+    #[test]
+    fn test_reconstruct_rectangular() {
+        let f = false;
+        let t = true;
+        let grid = Grid::from_bool_grid([
+            [t, f, f, f],
+            [t, f, f, f],
+            [f, f, f, f],
+            [f, f, f, f],
+            [f, f, f, f],
+            [f, t, t, f],
+            [f, f, f, f],
+            [f, f, f, f],
+        ]);
+        // Rows need 2 cells and columns 4
+        let mut g = Grid2dErasure::from_grid(grid, Shape::new(4, 2)).unwrap();
+        assert!(g.reconstruct_step());
+        // Column 0 only has 2 of 8 cells, row 5 has 2 of 4
+        let expected = Grid::from_bool_grid([
+            [t, f, f, f],
+            [t, f, f, f],
+            [f, f, f, f],
+            [f, f, f, f],
+            [f, f, f, f],
+            [t, t, t, t],
+            [f, f, f, f],
+            [f, f, f, f],
+        ]);
+        assert_eq!(g.grid(), &expected);
+    }
+
+    // The original data is 2 x 4, so columns need 2 cells and rows need 4
+    #[test]
+    fn test_reconstruct_rectangular_erasure() {
+        let f = false;
+        let t = true;
+        let shape = Shape::new(2, 4);
+        let g = Grid2dErasure::from_grid(
+            Grid::from_bool_grid([
+                [f, t, t, t, f, f, f, f],
+                [f, f, f, f, f, f, f, f],
+                [f, f, f, f, f, f, f, f],
+                [t, f, f, f, f, f, f, f],
+            ]),
+            shape,
+        )
+        .unwrap();
+        // 3 of the 4 cells needed in the row, 1 of the 2 needed in the column
+        assert!(!g.can_reconstruct((0, 0)));
+        let g = g.merge(
+            Grid2dErasure::from_grid(
+                Grid::from_bool_grid([
+                    [f, f, f, f, f, f, f, f],
+                    [f, f, f, f, f, f, f, f],
+                    [t, f, f, f, f, f, f, f],
+                    [f, f, f, f, f, f, f, f],
+                ]),
+                shape,
+            )
+            .unwrap(),
+        );
+        // Two cells is enough for a column of 4
+        assert!(g.can_reconstruct((0, 0)));
+    }
+
+    #[test]
+    fn test_mask_rectangular() {
+        let shape = Shape::new(3, 5);
+        let (mask, target) = Grid2dErasure::new_mask(&mut thread_rng(), shape);
+        assert_eq!((mask.w(), mask.h()), (10, 6));
+        assert!(target.0 < 3 && target.1 < 5);
+        // Everything the adversary doesn't censor still isn't enough
+        let g = Grid2dErasure::from_grid(mask, shape).unwrap();
+        assert_eq!(g.grid.count_ones(), 60 - (15 + 3 + 5 + 1));
+        assert!(!g.can_reconstruct(target));
+    }
+
+    // Nothing about the encoding needs n to be even
+    #[test]
+    fn test_odd_n() {
+        for n in [3, 5] {
+            let shape = Shape::square(n);
+            let (mask, target) = Grid2dErasure::new_mask(&mut thread_rng(), shape);
+            assert_eq!((mask.w(), mask.h()), (2 * n, 2 * n));
+            assert!(target.0 < n && target.1 < n);
+            let mut g = Grid2dErasure::from_grid(mask, shape).unwrap();
+            assert_eq!(g.grid.count_ones(), 4 * n * n - (n * n + 2 * n + 1));
+            // The target's row and column are each one cell short
+            assert!(!g.can_reconstruct(target));
+            g.grid.set(target.0, n, true);
+            assert!(g.can_reconstruct(target));
+
+            // A column needs exactly n cells, not n + 1 or n - 1
+            let mut g = Grid2dErasure::new(shape);
+            for i in n + 1..2 * n {
+                g.grid.set(i, 0, true);
+            }
+            assert!(!g.can_reconstruct((0, 0)));
+            assert!(!g.reconstruct_step());
+            g.grid.set(n, 0, true);
+            assert!(g.can_reconstruct((0, 0)));
+            assert!(!g.can_reconstruct((0, 1)));
+        }
+    }
+
+    #[test]
+    fn test_closure_matches_can_reconstruct() {
+        let mut rng = thread_rng();
+        let shape = Shape::new(3, 5);
+        for amount in [5, 15, 30] {
+            let mut g = Grid2dErasure::new(shape);
+            g.sample(&mut rng, amount, &SampleStrategy::RandomPoints);
+            let closure = g.reconstruction_closure();
+            assert_eq!(&closure & &g.grid, g.grid);
+            for i in 0..6 {
+                for j in 0..10 {
+                    assert_eq!(g.can_reconstruct((i, j)), closure.get(i, j));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_worklist_matches_passes() {
+        // The worklist has to reach the same fixpoint as running every pass, and stopping
+        // at a target has to give the same answer
+        let mut rng = thread_rng();
+        for (shape, row_threshold, col_threshold) in [
+            (Shape::square(4), 4, 4),
+            (Shape::new(3, 5), 5, 3),
+            (Shape::new(3, 5), 7, 4),
+            (Shape::square(4).with_extension(3), 6, 9),
+        ] {
+            let (w, h) = (shape.extension * shape.cols, shape.extension * shape.rows);
+            for amount in [w * h / 8, w * h / 4, w * h / 2, w * h] {
+                let mut g =
+                    Grid2dErasure::with_thresholds(shape, row_threshold, col_threshold).unwrap();
+                g.sample(&mut rng, amount, &SampleStrategy::RandomPoints);
+                let mut closure = g.grid.clone();
+                while reconstruct(&mut closure, row_threshold, col_threshold) {}
+                assert_eq!(g.reconstruction_closure(), closure);
+                for i in 0..h {
+                    for j in 0..w {
+                        let mut rgrid = g.grid.clone();
+                        let found = reconstruct_worklist(
+                            &mut rgrid,
+                            row_threshold,
+                            col_threshold,
+                            Some((i, j)),
+                        );
+                        assert_eq!(found, closure.get(i, j));
+                        assert_eq!(g.can_reconstruct((i, j)), closure.get(i, j));
+                        // Stopping early never recovers more than the closure
+                        assert_eq!(&rgrid & &closure, rgrid);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_all_cells() {
+        let f = false;
+        let t = true;
+        // Two cells in each of the first two columns recover them, and with them every row
+        let g = from_bool_grid([[t, f, f, f], [f, t, f, f], [f, f, f, f], [t, t, f, f]]);
+        assert!(g.can_reconstruct_all_cells());
+        // One cell in every row and column is only enough for the cells themselves
+        let g = from_bool_grid([[t, f, f, f], [f, t, f, f], [f, f, t, f], [f, f, f, t]]);
+        assert!(g.can_reconstruct((0, 0)));
+        assert!(!g.can_reconstruct_all_cells());
+    }
+
+    #[test]
+    fn test_project() {
+        let f = false;
+        let t = true;
+        let small = from_bool_grid([[t, f, f, f], [f, f, f, t], [f, f, f, f], [f, t, f, f]]);
+        let projected = small.project(4);
+        assert_eq!(projected.shape(), Shape::square(4));
+        let mut full = Grid2dErasure::new(Shape::square(4));
+        full.grid.set(7, 7, true);
+        full.grid.set(0, 0, true);
+        let merged = full.merge(projected);
+        let expected = Grid::from_indices(8, 8, [(0, 0), (1, 3), (3, 1), (7, 7)]).unwrap();
+        assert_eq!(merged.grid, expected);
+        assert_eq!(small.project(2), small);
+    }
+
+    #[test]
+    fn test_mask_at() {
+        let shape = Shape::new(3, 4);
+        // The corner furthest from the extended data
+        let (mask, target) = Grid2dErasure::new_mask_at(shape, (0, 0));
+        assert_eq!(target, (0, 0));
+        let censored = mask.not();
+        assert!(censored.get(0, 0));
+        // The rest of the original data is available
+        assert_eq!(censored.subgrid(0, 0, 3, 4).count_ones(), 1);
+        // as are the extended cells outside the target's row and column
+        assert_eq!(censored.subgrid(1, 4, 2, 4).count_ones(), 0);
+        assert_eq!(censored.subgrid(3, 1, 3, 3).count_ones(), 0);
+        // but its extended row and column, and the crossing quadrant, are not
+        assert_eq!(censored.subgrid(0, 4, 1, 4).count_ones(), 4);
+        assert_eq!(censored.subgrid(3, 0, 3, 1).count_ones(), 3);
+        assert_eq!(censored.subgrid(3, 4, 3, 4).count_ones(), 12);
+
+        // A random mask is the same as the one built for its target
+        let (random, target) = Grid2dErasure::new_mask(&mut thread_rng(), shape);
+        assert_eq!(Grid2dErasure::new_mask_at(shape, target).0, random);
+        // and every target passes the censored count check
+        for row in 0..3 {
+            for col in 0..4 {
+                Grid2dErasure::new_mask_at(shape, (row, col));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "target must be in the original data")]
+    fn test_mask_at_extended_target() {
+        Grid2dErasure::new_mask_at(Shape::square(4), (4, 0));
+    }
+
+    #[test]
+    fn test_censored_quadrant() {
+        let n = 4;
+        let (censored, _) = Grid2dErasure::new_censor_set(&mut thread_rng(), Shape::square(n));
+        let quadrant = censored.subgrid(n, n, n, n);
+        assert_eq!((quadrant.w(), quadrant.h()), (n, n));
+        assert_eq!(quadrant.count_ones(), n * n);
+    }
+
+    #[test]
+    fn test_quadrants() {
+        let shape = Shape::new(3, 5);
+        let mut g = Grid2dErasure::new(shape);
+        g.sample(&mut thread_rng(), 30, &SampleStrategy::RandomPoints);
+        let quadrants = [
+            (g.original(), 0, 0),
+            (g.row_parity(), 0, 5),
+            (g.col_parity(), 3, 0),
+            (g.cross_parity(), 3, 5),
+        ];
+        let mut total = 0;
+        for (quadrant, row_offset, col_offset) in quadrants {
+            assert_eq!((quadrant.w(), quadrant.h()), (5, 3));
+            for i in 0..3 {
+                for j in 0..5 {
+                    let cell = g.grid.get(i + row_offset, j + col_offset);
+                    assert_eq!(quadrant.get(i, j), cell);
+                }
+            }
+            total += quadrant.count_ones();
+        }
+        assert_eq!(total, g.grid.count_ones());
+    }
+
+    #[test]
+    fn test_extension() {
+        let shape = Shape::square(2).with_extension(4);
+        let mut g = Grid2dErasure::new(shape);
+        assert_eq!((g.grid.w(), g.grid.h()), (8, 8));
+        // Two cells out of a column of eight is enough
+        g.grid.set(5, 3, true);
+        g.grid.set(7, 3, true);
+        assert!(g.can_reconstruct((0, 3)));
+        assert!(!g.can_reconstruct((0, 2)));
+
+        let (mask, target) = Grid2dErasure::new_mask(&mut thread_rng(), shape);
+        assert_eq!(mask.count_ones(), 64 - (6 * 6 + 6 + 6 + 1));
+        let g = Grid2dErasure::from_grid(mask, shape).unwrap();
+        assert!(!g.can_reconstruct(target));
+    }
+
+    #[test]
+    fn test_withhold_rows() {
+        let n = 4;
+        let shape = Shape::new(n, 2 * n);
+        let strategy = MaskStrategy::WithholdRows { count: n + 1 };
+        let (mask, target) =
+            Grid2dErasure::new_mask_with(&mut thread_rng(), shape, &strategy).unwrap();
+        // Every row that isn't withheld is fully available, but it still isn't enough
+        let g = Grid2dErasure::from_grid(mask.clone(), shape).unwrap();
+        assert_eq!(g.grid.count_ones(), (n - 1) * 4 * n);
+        assert!(!g.grid.get(target.0, target.1));
+        assert!(!g.can_reconstruct(target));
+
+        let trivial = MaskStrategy::WithholdRows { count: n };
+        assert!(matches!(
+            Grid2dErasure::new_mask_with(&mut thread_rng(), shape, &trivial),
+            Err(Error::InvalidStrategy(_))
+        ));
+        assert!(matches!(
+            Grid1dErasure::new_mask_with(&mut thread_rng(), shape, &strategy),
+            Err(Error::InvalidStrategy(_))
+        ));
+    }
+
+    #[test]
+    fn test_new_mask_in() {
+        let shape = Shape::new(3, 4);
+        for censored in [
+            Quadrant::TopLeft,
+            Quadrant::TopRight,
+            Quadrant::BottomLeft,
+            Quadrant::BottomRight,
+        ] {
+            let (mask, target) =
+                Grid2dErasure::new_mask_in(&mut thread_rng(), shape, censored).unwrap();
+            let (block_rows, block_cols) = censored.ranges(shape);
+            for i in block_rows.clone() {
+                for j in block_cols.clone() {
+                    assert!(!mask.get(i, j), "{:?} withholds ({}, {})", censored, i, j);
+                }
+            }
+            let (target_rows, target_cols) = censored.opposite().ranges(shape);
+            assert!(target_rows.contains(&target.0) && target_cols.contains(&target.1));
+            // The block, the target, and its row and column across the block
+            assert_eq!(mask.count_ones(), 48 - (3 * 4 + 3 + 4 + 1));
+            assert_eq!(Grid2dErasure::validate_mask(&mask, shape, target), Ok(()));
+            if censored == Quadrant::BottomRight {
+                assert_eq!(mask, Grid2dErasure::new_mask_at(shape, target).0);
+            }
+        }
+
+        let wide = shape.with_extension(3);
+        assert!(matches!(
+            Grid2dErasure::new_mask_in(&mut thread_rng(), wide, Quadrant::TopLeft),
+            Err(Error::InvalidStrategy(_))
+        ));
+        assert!(Grid2dErasure::new_mask_in(&mut thread_rng(), wide, Quadrant::BottomRight).is_ok());
+    }
+
+    #[test]
+    fn test_validate_mask() {
+        let shape = Shape::new(3, 4);
+        let (mask, target) = Grid2dErasure::new_mask_at(shape, (2, 1));
+        assert_eq!(Grid2dErasure::validate_mask(&mask, shape, target), Ok(()));
+        let strategy = MaskStrategy::WithholdRows { count: 4 };
+        let (rows_mask, rows_target) =
+            Grid2dErasure::new_mask_with(&mut thread_rng(), shape, &strategy).unwrap();
+        assert_eq!(
+            Grid2dErasure::validate_mask(&rows_mask, shape, rows_target),
+            Ok(())
+        );
+
+        // Handing back any single censored cell but the target lets it be reconstructed
+        for i in 0..6 {
+            for j in 0..8 {
+                if mask.get(i, j) || (i, j) == target {
+                    continue;
+                }
+                let mut tampered = mask.clone();
+                tampered.set(i, j, true);
+                assert_eq!(
+                    Grid2dErasure::validate_mask(&tampered, shape, target),
+                    Err(MaskError::Recoverable),
+                    "({}, {})",
+                    i,
+                    j
+                );
+            }
+        }
+        let mut tampered = mask.clone();
+        tampered.set(2, 1, true);
+        assert_eq!(
+            Grid2dErasure::validate_mask(&tampered, shape, target),
+            Err(MaskError::TargetAvailable)
+        );
+        assert_eq!(
+            Grid2dErasure::validate_mask(&mask, shape, (3, 1)),
+            Err(MaskError::TargetOutside { row: 3, col: 1 })
+        );
+        assert_eq!(
+            Grid2dErasure::validate_mask(&mask, Shape::square(3), target),
+            Err(MaskError::WrongSize {
+                w: 8,
+                h: 6,
+                expected_w: 6,
+                expected_h: 6
+            })
+        );
+    }
+
     #[test]
     fn test_merge() {
         let g1 = from_bool_grid([
@@ -214,4 +1264,194 @@ mod test {
         println!("cm: {:?}", res_cmp);
         assert_eq!(res, res_cmp);
     }
+
+    #[test]
+    fn test_merge_lossy() {
+        let mut rng = thread_rng();
+        let shape = Shape::square(16);
+        let (mut g1, mut g2) = (Grid2dErasure::new(shape), Grid2dErasure::new(shape));
+        g1.sample(&mut rng, 300, &SampleStrategy::RandomPoints);
+        g2.sample(&mut rng, 300, &SampleStrategy::RandomPoints);
+        let merged = g1.clone().merge(g2.clone());
+
+        let lossless = g1.clone().merge_lossy(g2.clone(), &mut rng, 0.0);
+        assert_eq!(lossless, merged);
+        let lost = g1.clone().merge_lossy(g2.clone(), &mut rng, 1.0);
+        assert_eq!(lost, g1);
+
+        // Everything `g1` had survives, and nothing `g2` didn't have appears
+        let half = g1.clone().merge_lossy(g2, &mut rng, 0.5);
+        assert_eq!(half.grid() & g1.grid(), *g1.grid());
+        assert_eq!(half.grid().clone() | merged.grid().clone(), *merged.grid());
+    }
+
+    #[test]
+    fn test_try_merge() {
+        let mut rng = thread_rng();
+        let shape = Shape::square(4);
+        let (mut g1, mut g2) = (Grid2dErasure::new(shape), Grid2dErasure::new(shape));
+        g1.sample(&mut rng, 10, &SampleStrategy::RandomPoints);
+        g2.sample(&mut rng, 10, &SampleStrategy::RandomPoints);
+        assert_eq!(g1.clone().try_merge(g2.clone()), Ok(g1.clone().merge(g2)));
+
+        let bigger = Grid2dErasure::new(Shape::new(4, 8));
+        assert_eq!(
+            g1.clone().try_merge(bigger),
+            Err(Error::ShapeMismatch {
+                expected: (8, 8),
+                got: (16, 8)
+            })
+        );
+        let strict = Grid2dErasure::with_thresholds(shape, 6, 6).unwrap();
+        assert!(matches!(
+            g1.try_merge_from(&strict),
+            Err(Error::InvalidThresholds(_))
+        ));
+    }
+
+    #[test]
+    fn test_dump_state() {
+        let f = false;
+        let t = true;
+        // The first two rows are enough to reconstruct the whole grid
+        let g = from_bool_grid([[t, t, f, f], [t, f, t, f], [f, f, f, f], [f, f, f, f]]);
+        let prefix = std::env::temp_dir()
+            .join(format!("grid2d-{}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        g.dump_state(&prefix, true).unwrap();
+        let grid = std::fs::read(format!("{}.pgm", prefix)).unwrap();
+        let closure = std::fs::read(format!("{}-closure.pgm", prefix)).unwrap();
+        std::fs::remove_file(format!("{}.pgm", prefix)).unwrap();
+        std::fs::remove_file(format!("{}-closure.pgm", prefix)).unwrap();
+        let header = b"P5\n4 4\n255\n";
+        assert!(grid.starts_with(header) && closure.starts_with(header));
+        assert_eq!(&grid[header.len()..header.len() + 4], [255, 255, 0, 0]);
+        assert!(closure[header.len()..].iter().all(|&p| p == 255));
+    }
+
+    #[test]
+    fn test_can_reconstruct_all() {
+        let shape = Shape::new(3, 4);
+        let full = Grid::new(8, 6).not();
+        let g = Grid2dErasure::from_grid(full.clone(), shape).unwrap();
+        assert!(g.can_reconstruct_all());
+        // Everything the adversary leaves is still missing the target
+        let (mask, _) = Grid2dErasure::new_mask_at(shape, (1, 2));
+        let g = Grid2dErasure::from_grid(mask, shape).unwrap();
+        assert!(!g.can_reconstruct_all());
+
+        // Needing every cell of a row or column to rebuild it, having all the original
+        // data isn't enough to recover the parity, but it's all full nodes need
+        let mut g = Grid2dErasure::with_thresholds(shape, 8, 6).unwrap();
+        g.grid = full.sub_grid(0..3, 0..4).unwrap().embed(8, 6).unwrap();
+        assert!(g.can_reconstruct_all());
+        assert!(!g.can_reconstruct_all_cells());
+    }
+
+    #[test]
+    fn test_coding_gain() {
+        let shape = Shape::square(4);
+        assert_eq!(Grid2dErasure::new(shape).coding_gain(), 0.0);
+        // Half a row fills the rest of it, but nothing else
+        let mut g = Grid2dErasure::new(shape);
+        for j in [0, 2, 5, 7] {
+            g.grid.set(0, j, true);
+        }
+        assert_eq!(g.coding_gain(), 1.0);
+        // Three full rows leave every column one cell short
+        let mut g = Grid2dErasure::new(shape);
+        for i in 0..3 {
+            g.grid.fill_row(i);
+        }
+        assert_eq!(g.coding_gain(), 0.0);
+    }
+
+    #[test]
+    fn test_reset() {
+        let shape = Shape::new(3, 5);
+        let mut g = Grid2dErasure::new(shape);
+        g.sample(&mut thread_rng(), 20, &SampleStrategy::RandomPoints);
+        g.reconstruct_step();
+        g.reset();
+        assert_eq!(g, Grid2dErasure::new(shape));
+        assert!(!g.reconstruct_step());
+
+        // Thresholds aren't cells, so they survive
+        let mut g = Grid2dErasure::with_thresholds(shape, 6, 4).unwrap();
+        g.sample(&mut thread_rng(), 20, &SampleStrategy::RandomPoints);
+        g.reset();
+        assert_eq!(g, Grid2dErasure::with_thresholds(shape, 6, 4).unwrap());
+
+        let mut g = Grid1dErasure::new(shape);
+        g.sample(&mut thread_rng(), 20, &SampleStrategy::RandomPoints);
+        g.reset();
+        assert_eq!(g, Grid1dErasure::new(shape));
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let shape = Shape::square(4);
+        let mut g = Grid2dErasure::new(shape);
+        assert_eq!(g.thresholds(), (4, 4));
+        // Column 0 has exactly n cells
+        for i in 0..4 {
+            g.grid.set(i, 0, true);
+        }
+        assert!(g.can_reconstruct((7, 0)));
+
+        let mut strict = Grid2dErasure::with_thresholds(shape, 4, 5).unwrap();
+        strict.grid = g.grid.clone();
+        assert!(!strict.can_reconstruct((7, 0)));
+        strict.grid.set(4, 0, true);
+        assert!(strict.can_reconstruct((7, 0)));
+
+        // Rows and columns can use different rates
+        let mut asymmetric = Grid2dErasure::with_thresholds(shape, 6, 4).unwrap();
+        for j in 0..4 {
+            asymmetric.grid.set(0, j, true);
+        }
+        assert!(!asymmetric.can_reconstruct((0, 7)));
+        asymmetric.grid.set(0, 4, true);
+        asymmetric.grid.set(0, 5, true);
+        assert!(asymmetric.can_reconstruct((0, 7)));
+
+        assert!(matches!(
+            Grid2dErasure::with_thresholds(shape, 0, 4),
+            Err(Error::InvalidThresholds(_))
+        ));
+        assert!(matches!(
+            Grid2dErasure::with_thresholds(shape, 4, 9),
+            Err(Error::InvalidThresholds(_))
+        ));
+        assert!(matches!(
+            Grid1dErasure::new_with_thresholds(shape, Some(4), None),
+            Err(Error::InvalidThresholds(_))
+        ));
+        assert!(Grid1dErasure::new_with_thresholds(shape, None, None).is_ok());
+    }
+
+    #[test]
+    fn test_serde() {
+        let shape = Shape::new(5, 3);
+        let mut g = Grid2dErasure::new(shape);
+        g.sample(&mut rand::thread_rng(), 12, &SampleStrategy::RandomPoints);
+        let json = serde_json::to_string(&g).unwrap();
+        assert_eq!(serde_json::from_str::<Grid2dErasure>(&json).unwrap(), g);
+
+        // The grid has to be the size the shape says it is
+        let wrong_shape = json.replace(r#""rows":5"#, r#""rows":4"#);
+        assert_ne!(wrong_shape, json);
+        assert!(serde_json::from_str::<Grid2dErasure>(&wrong_shape).is_err());
+        assert_eq!((g.grid.w(), g.grid.h()), (6, 10));
+
+        let g = Grid2dErasure::with_thresholds(shape, 4, 7).unwrap();
+        let json = serde_json::to_string(&g).unwrap();
+        assert_eq!(serde_json::from_str::<Grid2dErasure>(&json).unwrap(), g);
+        // Grids without thresholds get the default ones
+        let old = json.replace(r#","row_threshold":4,"col_threshold":7"#, "");
+        assert_ne!(old, json);
+        let old = serde_json::from_str::<Grid2dErasure>(&old).unwrap();
+        assert_eq!(old.thresholds(), (3, 5));
+    }
 }
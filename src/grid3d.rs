@@ -0,0 +1,240 @@
+use std::fmt::Debug;
+
+use rand::{distributions::Uniform, prelude::Distribution, RngCore};
+
+use crate::{
+    base_grid::{Grid, SampleStrategy},
+    traits::{Dims, Reconstructable, Shape},
+};
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+/// A cube of n x n x n original data, erasure encoded along all three axes into an
+/// `e * n` cube. Points can be reconstructed if
+/// - It exist in the grid
+/// - It belongs to an axis aligned line with at least n points
+/// - It belongs to a line where enough points can be reconstructed
+pub struct Grid3dErasure {
+    n: usize,
+    extension: usize,
+    // The cube flattened into a grid `e * n` wide and `(e * n)^2` high,
+    // see `Grid3dErasure::coord`
+    grid: Grid,
+}
+
+impl Grid3dErasure {
+    #[cfg(test)]
+    pub fn from_grid(grid: Grid, n: usize, extension: usize) -> Result<Self, crate::error::Error> {
+        let side = extension * n;
+        if (grid.w(), grid.h()) != (side, side * side) {
+            return Err(crate::error::Error::ShapeMismatch {
+                expected: (side, side * side),
+                got: (grid.w(), grid.h()),
+            });
+        }
+        Ok(Self { n, extension, grid })
+    }
+
+    /// Where (x, y, z) lives in the flattened grid: x is the column, and each
+    /// z layer is a block of `side` rows
+    #[inline(always)]
+    fn coord(side: usize, (x, y, z): (usize, usize, usize)) -> (usize, usize) {
+        (y + z * side, x)
+    }
+}
+
+type Line = fn(usize, usize, usize) -> (usize, usize, usize);
+
+/// One pass of recovering every line along each of the three axes with at least n cells
+fn reconstruct(grid: &mut Grid, n: usize) -> bool {
+    let side = grid.w();
+    let mut changed = false;
+    // Along x, then y, then z
+    let lines: [Line; 3] = [
+        |a, b, k| (k, a, b),
+        |a, b, k| (a, k, b),
+        |a, b, k| (a, b, k),
+    ];
+    for line in lines {
+        for a in 0..side {
+            for b in 0..side {
+                let cells = || (0..side).map(|k| Grid3dErasure::coord(side, line(a, b, k)));
+                let count = cells().filter(|&(i, j)| grid.get(i, j)).count();
+                if count >= n && count < side {
+                    for (i, j) in cells() {
+                        grid.set(i, j, true);
+                    }
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+impl Reconstructable for Grid3dErasure {
+    type Index = (usize, usize, usize);
+
+    fn dims() -> Dims {
+        Dims::Three
+    }
+
+    // Only `shape.rows` is used, the cube is always n x n x n
+    fn random_index<R: RngCore>(rng: &mut R, shape: Shape) -> Self::Index {
+        // a point in the original octant of the cube
+        let point = Uniform::from(0..shape.rows);
+        (point.sample(rng), point.sample(rng), point.sample(rng))
+    }
+
+    fn new_mask_at(shape: Shape, target: Self::Index) -> (Grid, Self::Index) {
+        let n = shape.rows;
+        let side = shape.extension * n;
+        assert!(
+            target.0 < n && target.1 < n && target.2 < n,
+            "target must be in the original data"
+        );
+        let mut mask = Grid::new(side, side * side);
+
+        // Along each axis, the target's coordinate plus every extended one. Hiding just the
+        // lines through the target and the extended octant isn't enough in 3d: the hidden
+        // extended cells on the target's lines can be rebuilt along the other axes. Hiding
+        // the whole product leaves every line through a hidden cell with n - 1 points.
+        let axis = |t: usize| [t].into_iter().chain(n..side);
+        for x in axis(target.0) {
+            for y in axis(target.1) {
+                for z in axis(target.2) {
+                    let (i, j) = Self::coord(side, (x, y, z));
+                    mask.set(i, j, true);
+                }
+            }
+        }
+        assert_eq!(mask.count_ones(), (side - n + 1).pow(3));
+
+        (mask.not(), target)
+    }
+
+    // Only `shape.rows` is used, the cube is always n x n x n
+    fn new(shape: Shape) -> Self {
+        let side = shape.extension * shape.rows;
+        Grid3dErasure {
+            n: shape.rows,
+            extension: shape.extension,
+            grid: Grid::new(side, side * side),
+        }
+    }
+
+    fn shape(&self) -> Shape {
+        Shape::square(self.n).with_extension(self.extension)
+    }
+
+    fn can_reconstruct(&self, (x, y, z): Self::Index) -> bool {
+        let (i, j) = Self::coord(self.grid.w(), (x, y, z));
+        self.grid.get(i, j) || self.reconstruction_closure().get(i, j)
+    }
+
+    fn can_reconstruct_single_pass(&self, (x, y, z): Self::Index) -> bool {
+        let (i, j) = Self::coord(self.grid.w(), (x, y, z));
+        let mut rgrid = self.grid.clone();
+        reconstruct(&mut rgrid, self.n);
+        rgrid.get(i, j)
+    }
+
+    fn reconstruction_closure(&self) -> Grid {
+        let mut rgrid = self.grid.clone();
+        while reconstruct(&mut rgrid, self.n) {}
+        rgrid
+    }
+
+    fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn reconstruction_rounds(&self) -> usize {
+        let mut rgrid = self.grid.clone();
+        let mut rounds = 0;
+        while reconstruct(&mut rgrid, self.n) {
+            rounds += 1;
+        }
+        rounds
+    }
+
+    #[inline(always)]
+    fn sample<R: RngCore>(&mut self, rng: &mut R, amount: usize, strategy: &SampleStrategy) {
+        self.grid.sample(rng, amount, strategy);
+    }
+
+    #[inline(always)]
+    fn sample_exclusion<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        amount: usize,
+        strategy: &SampleStrategy,
+        mask: &Grid,
+    ) -> usize {
+        self.sample(rng, amount, strategy);
+        let sampled = self.grid.count_ones();
+        self.grid.and_inplace(mask);
+        sampled - self.grid.count_ones()
+    }
+
+    #[inline(always)]
+    fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64) {
+        self.grid.thin(rng, keep)
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.grid.clear()
+    }
+
+    #[inline(always)]
+    fn merge_from(&mut self, other: &Self) {
+        assert_eq!(self.shape(), other.shape());
+        self.grid.or_inplace(&other.grid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::thread_rng;
+
+    use super::*;
+
+    fn set(g: &mut Grid3dErasure, cell: (usize, usize, usize)) {
+        let (i, j) = Grid3dErasure::coord(g.grid.w(), cell);
+        g.grid.set(i, j, true);
+    }
+
+    #[test]
+    fn test_reconstruct_line() {
+        let mut g = Grid3dErasure::new(Shape::square(2));
+        assert_eq!((g.grid.w(), g.grid.h()), (4, 16));
+        // Half of a line along z
+        set(&mut g, (1, 2, 0));
+        set(&mut g, (1, 2, 3));
+        assert!(g.can_reconstruct((1, 2, 1)));
+        assert!(!g.can_reconstruct((1, 1, 1)));
+        assert_eq!(g.reconstruction_closure().count_ones(), 4);
+    }
+
+    #[test]
+    fn test_reconstruct_iterated() {
+        let mut g = Grid3dErasure::new(Shape::square(2));
+        // Two z lines through (0, 0) and (1, 0) recover the x lines at every z,
+        // which then fill the whole y = 0 plane
+        for cell in [(0, 0, 0), (0, 0, 1), (1, 0, 2), (1, 0, 3)] {
+            set(&mut g, cell);
+        }
+        assert!(g.can_reconstruct((3, 0, 3)));
+        assert!(!g.can_reconstruct((0, 1, 0)));
+        assert_eq!(g.reconstruction_closure().count_ones(), 16);
+    }
+
+    #[test]
+    fn test_mask() {
+        let shape = Shape::square(2);
+        let (mask, target) = Grid3dErasure::new_mask(&mut thread_rng(), shape);
+        assert_eq!(mask.count_ones(), 64 - 27);
+        let g = Grid3dErasure::from_grid(mask, 2, 2).unwrap();
+        assert!(!g.can_reconstruct(target));
+    }
+}
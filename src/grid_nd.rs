@@ -0,0 +1,306 @@
+use rand::{distributions::Uniform, prelude::Distribution, RngCore};
+
+use crate::{
+    base_grid::{Grid, SampleStrategy},
+    error::Error,
+    traits::{Dims, Reconstructable, Shape},
+};
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+/// A hypercube of n^D original data, erasure encoded along every axis into an `e * n`
+/// hypercube, generalizing `Grid2dErasure` and `Grid3dErasure` to any number of
+/// dimensions. Points can be reconstructed if
+/// - It exist in the grid
+/// - It belongs to an axis aligned line with at least n points
+/// - It belongs to a line where enough points can be reconstructed
+pub struct GridNdErasure<const D: usize> {
+    n: usize,
+    extension: usize,
+    // The hypercube flattened into a grid `e * n` wide and `(e * n)^(D - 1)` high,
+    // see `GridNdErasure::coord`
+    grid: Grid,
+}
+
+impl<const D: usize> GridNdErasure<D> {
+    /// Wraps an already sampled grid, flattened as `coord` does. Errors if it's a different
+    /// size.
+    pub fn from_grid(grid: Grid, n: usize, extension: usize) -> Result<Self, Error> {
+        let side = extension * n;
+        let expected = (side, side.pow(D as u32 - 1));
+        if (grid.w(), grid.h()) != expected {
+            return Err(Error::ShapeMismatch {
+                expected,
+                got: (grid.w(), grid.h()),
+            });
+        }
+        Ok(Self { n, extension, grid })
+    }
+
+    /// The cells present, flattened as `coord` does, without any reconstruction
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Where a point lives in the flattened grid: the last axis is the column, and the
+    /// others pick the row, the first varying slowest. In 2d a point is `[row, col]`.
+    #[inline(always)]
+    fn coord(side: usize, point: [usize; D]) -> (usize, usize) {
+        let row = point[..D - 1].iter().fold(0, |row, &x| row * side + x);
+        (row, point[D - 1])
+    }
+
+    /// The point at `(i, j)` in the flattened grid, the inverse of `coord`
+    fn point(side: usize, (mut i, j): (usize, usize)) -> [usize; D] {
+        let mut point = [0; D];
+        point[D - 1] = j;
+        for x in point[..D - 1].iter_mut().rev() {
+            *x = i % side;
+            i /= side;
+        }
+        point
+    }
+}
+
+/// One pass of recovering every line along each axis, in order, with at least n cells
+fn reconstruct<const D: usize>(grid: &mut Grid, n: usize) -> bool {
+    let side = grid.w();
+    let mut changed = false;
+    for axis in 0..D {
+        // A line along `axis` is picked out by its coordinates on the other axes
+        for line in 0..side.pow(D as u32 - 1) {
+            let mut start = [0; D];
+            let mut rest = line;
+            for (_, x) in start.iter_mut().enumerate().filter(|&(k, _)| k != axis) {
+                *x = rest % side;
+                rest /= side;
+            }
+            let cells = || {
+                (0..side).map(move |k| {
+                    let mut point = start;
+                    point[axis] = k;
+                    GridNdErasure::<D>::coord(side, point)
+                })
+            };
+            let count = cells().filter(|&(i, j)| grid.get(i, j)).count();
+            if count >= n && count < side {
+                for (i, j) in cells() {
+                    grid.set(i, j, true);
+                }
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+impl<const D: usize> Reconstructable for GridNdErasure<D> {
+    type Index = [usize; D];
+
+    fn dims() -> Dims {
+        Dims::try_from(D).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // Only `shape.rows` is used, the hypercube always has sides of n
+    fn random_index<R: RngCore>(rng: &mut R, shape: Shape) -> Self::Index {
+        // a point in the original data
+        let point = Uniform::from(0..shape.rows);
+        let mut target = [0; D];
+        for x in target.iter_mut() {
+            *x = point.sample(rng);
+        }
+        target
+    }
+
+    fn new_mask_at(shape: Shape, target: Self::Index) -> (Grid, Self::Index) {
+        let mut mask = Self::new(shape).grid;
+        let (n, side) = (shape.rows, mask.w());
+        assert!(
+            target.iter().all(|&x| x < n),
+            "target must be in the original data"
+        );
+        // As in 3d, hide every point whose coordinate along each axis is the target's or an
+        // extended one, which leaves every line through a hidden cell with n - 1 points
+        for i in 0..mask.h() {
+            for j in 0..side {
+                let point = Self::point(side, (i, j));
+                if point.iter().zip(target).all(|(&x, t)| x == t || x >= n) {
+                    mask.set(i, j, true);
+                }
+            }
+        }
+        assert_eq!(mask.count_ones(), (side - n + 1).pow(D as u32));
+
+        (mask.not(), target)
+    }
+
+    // Only `shape.rows` is used, the hypercube always has sides of n
+    fn new(shape: Shape) -> Self {
+        assert!(D > 0, "grids need at least one dimension");
+        let side = shape.extension * shape.rows;
+        GridNdErasure {
+            n: shape.rows,
+            extension: shape.extension,
+            grid: Grid::new(side, side.pow(D as u32 - 1)),
+        }
+    }
+
+    fn shape(&self) -> Shape {
+        Shape::square(self.n).with_extension(self.extension)
+    }
+
+    fn can_reconstruct(&self, point: Self::Index) -> bool {
+        let (i, j) = Self::coord(self.grid.w(), point);
+        self.grid.get(i, j) || self.reconstruction_closure().get(i, j)
+    }
+
+    fn can_reconstruct_single_pass(&self, point: Self::Index) -> bool {
+        let (i, j) = Self::coord(self.grid.w(), point);
+        let mut rgrid = self.grid.clone();
+        reconstruct::<D>(&mut rgrid, self.n);
+        rgrid.get(i, j)
+    }
+
+    fn reconstruction_closure(&self) -> Grid {
+        let mut rgrid = self.grid.clone();
+        while reconstruct::<D>(&mut rgrid, self.n) {}
+        rgrid
+    }
+
+    fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn reconstruction_rounds(&self) -> usize {
+        let mut rgrid = self.grid.clone();
+        let mut rounds = 0;
+        while reconstruct::<D>(&mut rgrid, self.n) {
+            rounds += 1;
+        }
+        rounds
+    }
+
+    #[inline(always)]
+    fn sample<R: RngCore>(&mut self, rng: &mut R, amount: usize, strategy: &SampleStrategy) {
+        self.grid.sample(rng, amount, strategy);
+    }
+
+    #[inline(always)]
+    fn sample_exclusion<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        amount: usize,
+        strategy: &SampleStrategy,
+        mask: &Grid,
+    ) -> usize {
+        self.sample(rng, amount, strategy);
+        let sampled = self.grid.count_ones();
+        self.grid.and_inplace(mask);
+        sampled - self.grid.count_ones()
+    }
+
+    #[inline(always)]
+    fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64) {
+        self.grid.thin(rng, keep)
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.grid.clear()
+    }
+
+    #[inline(always)]
+    fn merge_from(&mut self, other: &Self) {
+        assert_eq!(self.shape(), other.shape());
+        self.grid.or_inplace(&other.grid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::thread_rng;
+
+    use super::*;
+    use crate::{grid2d::Grid2dErasure, grid3d::Grid3dErasure};
+
+    #[test]
+    fn test_matches_2d() {
+        // The fixture from `Grid2dErasure`'s `test_reconstruct`, which needs three passes
+        let grid = Grid::from_bool_grid([
+            [true, true, false, false],
+            [false, false, true, false],
+            [false, false, false, false],
+            [false, false, false, true],
+        ]);
+        let g2 = Grid2dErasure::from_grid(grid.clone(), Shape::square(2)).unwrap();
+        let nd = GridNdErasure::<2>::from_grid(grid, 2, 2).unwrap();
+        assert_eq!(nd.reconstruction_closure(), g2.reconstruction_closure());
+        for i in 0..4 {
+            for j in 0..4 {
+                assert_eq!(nd.can_reconstruct([i, j]), g2.can_reconstruct((i, j)));
+                assert_eq!(
+                    nd.can_reconstruct_single_pass([i, j]),
+                    g2.can_reconstruct_single_pass((i, j))
+                );
+            }
+        }
+        assert!(!nd.can_reconstruct_single_pass([2, 2]));
+        assert!(nd.can_reconstruct([2, 2]));
+
+        let mut rng = thread_rng();
+        for amount in [16, 32, 64] {
+            let mut grid = Grid::new(16, 16);
+            grid.sample(&mut rng, amount, &SampleStrategy::RandomPoints);
+            let g2 = Grid2dErasure::from_grid(grid.clone(), Shape::square(8)).unwrap();
+            let nd = GridNdErasure::<2>::from_grid(grid, 8, 2).unwrap();
+            assert_eq!(nd.reconstruction_closure(), g2.reconstruction_closure());
+        }
+    }
+
+    #[test]
+    fn test_matches_3d() {
+        // `Grid3dErasure` flattens (x, y, z) to the same cell as [z, y, x]
+        let mut rng = thread_rng();
+        for amount in [8, 16, 32] {
+            let mut grid = Grid::new(4, 16);
+            grid.sample(&mut rng, amount, &SampleStrategy::RandomPoints);
+            let g3 = Grid3dErasure::from_grid(grid.clone(), 2, 2).unwrap();
+            let nd = GridNdErasure::<3>::from_grid(grid, 2, 2).unwrap();
+            assert_eq!(nd.reconstruction_closure(), g3.reconstruction_closure());
+            assert_eq!(nd.can_reconstruct([2, 1, 0]), g3.can_reconstruct((0, 1, 2)));
+        }
+    }
+
+    #[test]
+    fn test_coord() {
+        let side = 3;
+        for i in 0..27 {
+            for j in 0..side {
+                let point = GridNdErasure::<4>::point(side, (i, j));
+                assert_eq!(GridNdErasure::<4>::coord(side, point), (i, j));
+            }
+        }
+        assert_eq!(GridNdErasure::<4>::coord(side, [1, 0, 2, 1]), (11, 1));
+    }
+
+    #[test]
+    fn test_mask_4d() {
+        let shape = Shape::square(2);
+        let (mask, target) = GridNdErasure::<4>::new_mask(&mut thread_rng(), shape);
+        assert_eq!((mask.w(), mask.h()), (4, 64));
+        assert_eq!(mask.count_ones(), 256 - 81);
+        let g = GridNdErasure::<4>::from_grid(mask, 2, 2).unwrap();
+        assert!(!g.can_reconstruct(target));
+    }
+
+    #[test]
+    fn test_wrong_size() {
+        assert_eq!(
+            GridNdErasure::<3>::from_grid(Grid::new(4, 4), 2, 2).unwrap_err(),
+            Error::ShapeMismatch {
+                expected: (4, 16),
+                got: (4, 4)
+            }
+        );
+        assert!(GridNdErasure::<2>::from_grid(Grid::new(4, 4), 2, 2).is_ok());
+    }
+}
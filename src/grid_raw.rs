@@ -0,0 +1,152 @@
+use std::fmt::Debug;
+
+use rand::{distributions::Uniform, prelude::Distribution, RngCore};
+
+use crate::{
+    base_grid::{Grid, SampleStrategy},
+    traits::{Dims, Reconstructable, Shape},
+};
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+/// The `rows x cols` data with no erasure encoding at all, as a baseline for what coding
+/// buys. `shape.extension` is ignored, and a cell can only be reconstructed if it exists
+/// in the grid.
+pub struct GridRawErasure {
+    shape: Shape,
+    // the grid stored column wise, same as every other grid
+    grid: Grid,
+}
+
+impl GridRawErasure {
+    #[cfg(test)]
+    pub fn from_grid(grid: Grid, shape: Shape) -> Result<Self, crate::error::Error> {
+        let expected = (shape.cols, shape.rows);
+        if (grid.w(), grid.h()) != expected {
+            return Err(crate::error::Error::ShapeMismatch {
+                expected,
+                got: (grid.w(), grid.h()),
+            });
+        }
+        Ok(Self { shape, grid })
+    }
+}
+
+impl Reconstructable for GridRawErasure {
+    type Index = (usize, usize);
+
+    fn dims() -> Dims {
+        Dims::Zero
+    }
+
+    fn random_index<R: RngCore>(rng: &mut R, shape: Shape) -> Self::Index {
+        let row = Uniform::from(0..shape.rows).sample(rng);
+        let col = Uniform::from(0..shape.cols).sample(rng);
+        (row, col)
+    }
+
+    fn new_mask_at(shape: Shape, (row, col): Self::Index) -> (Grid, Self::Index) {
+        let Shape { rows, cols, .. } = shape;
+        assert!(
+            row < rows && col < cols,
+            "target must be in the original data"
+        );
+        let mut mask = Grid::new(cols, rows);
+        // Without any redundancy, censoring the point itself is enough
+        mask.set(row, col, true);
+        (mask.not(), (row, col))
+    }
+
+    fn new(shape: Shape) -> Self {
+        GridRawErasure {
+            shape,
+            grid: Grid::new(shape.cols, shape.rows),
+        }
+    }
+
+    fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    fn can_reconstruct(&self, (row, col): Self::Index) -> bool {
+        self.grid.get(row, col)
+    }
+
+    fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn reconstruction_closure(&self) -> Grid {
+        self.grid.clone()
+    }
+
+    #[inline(always)]
+    fn sample<R: RngCore>(&mut self, rng: &mut R, amount: usize, sample_strategy: &SampleStrategy) {
+        self.grid.sample(rng, amount, sample_strategy)
+    }
+
+    #[inline(always)]
+    fn sample_exclusion<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        amount: usize,
+        sample_strategy: &SampleStrategy,
+        mask: &Grid,
+    ) -> usize {
+        self.sample(rng, amount, sample_strategy);
+        let sampled = self.grid.count_ones();
+        self.grid.and_inplace(mask);
+        sampled - self.grid.count_ones()
+    }
+
+    #[inline(always)]
+    fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64) {
+        self.grid.thin(rng, keep)
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.grid.clear()
+    }
+
+    #[inline(always)]
+    fn merge_from(&mut self, other: &Self) {
+        assert_eq!(self.shape, other.shape);
+        self.grid.or_inplace(&other.grid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn test_reconstruct() {
+        let f = false;
+        let t = true;
+        let g = GridRawErasure::from_grid(
+            Grid::from_bool_grid([[t, t, f], [f, f, f], [f, f, t]]),
+            Shape::square(3),
+        )
+        .unwrap();
+        // A full row or column doesn't help, only the sampled cells themselves
+        assert!(g.can_reconstruct((0, 1)));
+        assert!(g.can_reconstruct((2, 2)));
+        assert!(!g.can_reconstruct((0, 2)));
+        assert!(!g.can_reconstruct((1, 1)));
+        assert_eq!(g.reconstruction_closure().count_ones(), 3);
+    }
+
+    #[test]
+    fn test_mask() {
+        let shape = Shape::new(3, 4);
+        let (mask, target) = GridRawErasure::new_mask(&mut thread_rng(), shape);
+        assert_eq!((mask.w(), mask.h()), (4, 3));
+        assert_eq!(mask.count_ones(), 12 - 1);
+        let mut g = GridRawErasure::from_grid(mask, shape).unwrap();
+        assert!(!g.can_reconstruct(target));
+        g.grid.set(target.0, target.1, true);
+        assert!(g.can_reconstruct(target));
+    }
+}
@@ -1,96 +1,2420 @@
-use grid1d::Grid1dErasure;
-use grid2d::Grid2dErasure;
-use rand::{rngs::SmallRng, thread_rng, SeedableRng};
-use traits::Reconstructable;
+use grid1_5d::Grid1_5dErasure;
+use grid3d::Grid3dErasure;
+use grid_raw::GridRawErasure;
+#[cfg(feature = "progress")]
+use indicatif::ParallelProgressIterator;
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+#[cfg(feature = "progress")]
+use rayon::prelude::IndexedParallelIterator;
+#[cfg(feature = "parallel")]
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, hash::Hash, ops::Range, str::FromStr};
 
-pub use base_grid::SampleStrategy;
+pub use grid1d::Grid1dErasure;
+pub use grid2d::{Grid2dErasure, Quadrant};
+pub use grid_nd::GridNdErasure;
+pub use traits::{Dims, Reconstructable, Shape};
+
+pub use base_grid::{Axis, Grid, GridIndexError, MaskError, MaskStrategy, SampleStrategy};
+pub use error::{ConfigError, Error};
+pub use manifest::Manifest;
+#[cfg(feature = "parquet")]
+pub use parquet_writer::write_parquet;
+pub use pbm::GridError;
+#[cfg(feature = "plot")]
+pub use plot::{draw_heatmaps, heatmap_size, prob_color, write_heatmaps_png, PlotField};
+pub use profile::{write_profiles_csv, FailureProfile};
+#[cfg(feature = "arrow")]
+pub use record_batch::{ResultBatchBuilder, ResultIpcWriter};
+#[cfg(feature = "sqlite")]
+pub use result_db::ResultDb;
+pub use search::{
+    find_critical_clients, find_critical_clients_seeded, find_critical_samples,
+    find_critical_samples_seeded, CriticalPoint, CriticalSamples,
+};
+pub use stats::{merge_results_csv, Moments, TrialStats};
+pub use timeline::{write_timeline_csv, Arrivals};
+pub use topology::{Evaluator, Topology};
+#[cfg(feature = "wasm")]
+pub use wasm::{run_config, run_config_seeded};
 
 mod base_grid;
+mod error;
+mod grid1_5d;
 mod grid1d;
 mod grid2d;
+mod grid3d;
+mod grid_nd;
+mod grid_raw;
+mod manifest;
+#[cfg(feature = "parquet")]
+mod parquet_writer;
+mod pbm;
+#[cfg(feature = "plot")]
+mod plot;
+mod profile;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "arrow")]
+mod record_batch;
+#[cfg(feature = "sqlite")]
+mod result_db;
+mod search;
+mod stats;
+mod timeline;
+mod topology;
 mod traits;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+/// How `n_clients * percent_censored` is turned into a whole number of censored clients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundingMode {
+    /// Round down, so a small enough `percent_censored` censors nobody
+    #[default]
+    Floor,
+    /// Round up, so any `percent_censored > 0` censors at least one client
+    Ceil,
+    /// Round to the nearest client
+    Round,
+}
+
+impl Display for RoundingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoundingMode::Floor => write!(f, "Floor"),
+            RoundingMode::Ceil => write!(f, "Ceil"),
+            RoundingMode::Round => write!(f, "Round"),
+        }
+    }
+}
+
+impl FromStr for RoundingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "Floor" => Ok(RoundingMode::Floor),
+            "Ceil" => Ok(RoundingMode::Ceil),
+            "Round" => Ok(RoundingMode::Round),
+            _ => Err(format!("unknown rounding mode {:?}", s)),
+        }
+    }
+}
+
+/// How the clients of a trial are simulated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientModel {
+    /// Every honest client's samples are drawn into one grid at once, and the same for
+    /// censored clients, unless another setting needs clients simulated one by one. The
+    /// same as `Individual` for `RandomPoints`, which samples with replacement.
+    #[default]
+    Aggregate,
+    /// Every client samples into its own grid, which is then merged into the network's
+    Individual,
+}
 
-#[derive(Debug)]
+impl Display for ClientModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientModel::Aggregate => write!(f, "Aggregate"),
+            ClientModel::Individual => write!(f, "Individual"),
+        }
+    }
+}
+
+impl FromStr for ClientModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "Aggregate" => Ok(ClientModel::Aggregate),
+            "Individual" => Ok(ClientModel::Individual),
+            _ => Err(format!("unknown client model {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExperimentConfig {
+    /// The number of rows in the non-erasure encoded data
     pub n: usize,
-    pub dims: usize,
+    /// The number of columns in the non-erasure encoded data, `n` if not set.
+    /// 3d grids are always cubes.
+    pub cols: Option<usize>,
+    /// How many times larger erasure coding makes each encoded dimension
+    pub extension: usize,
+    pub dims: Dims,
     pub n_clients: usize,
     pub percent_censored: f64,
+    /// An absolute number of censored clients, which overrides `percent_censored` when set.
+    /// Must be at most `n_clients`.
+    pub n_censored_abs: Option<usize>,
+    /// How the number of censored clients is rounded
+    pub rounding_mode: RoundingMode,
     pub n_samples: usize,
-    pub sample_strategy: SampleStrategy,
+    /// How honest clients pick the cells they sample
+    pub honest_strategy: SampleStrategy,
+    /// How censored clients pick the cells they sample
+    pub censor_strategy: SampleStrategy,
+    /// The fraction of its sampled cells each client manages to share with the network
+    pub share_fraction: f64,
+    /// The fraction of honest clients that sample but go offline before sharing anything
+    pub offline_fraction: f64,
+    /// How the adversary picks the cells it withholds from censored clients
+    pub mask_strategy: MaskStrategy,
+    /// Give every censored client its own independently drawn mask instead of sharing one.
+    /// Success is then measured on the first censored client's target.
+    pub per_client_mask: bool,
+    /// The fraction of each censored client's samples that are answered honestly anyway,
+    /// as an adversary trying to look well behaved would
+    pub honest_fraction: f64,
+    /// Only count a trial as a success if every cell of the grid can be reconstructed,
+    /// rather than just the censored target
+    pub full_recovery: bool,
+    /// How many cells a row needs to be reconstructed, the default for the grid if not set.
+    /// Only 2d grids support custom thresholds.
+    pub row_threshold: Option<usize>,
+    /// Same as `row_threshold`, but for columns
+    pub col_threshold: Option<usize>,
+    /// The probability each cell the network ends up with is wrong and has to be discarded
+    /// before reconstruction
+    pub error_rate: f64,
+    /// Whether clients are simulated all at once or one by one
+    pub client_model: ClientModel,
+    /// The network clients gossip their grids over, if they don't all reach everyone.
+    /// Clients are always simulated one by one when set.
+    pub topology: Option<Topology>,
+    /// Only run a single reconstruction pass when checking the target, rather than
+    /// reconstructing until nothing more can be recovered. Doesn't affect `full_recovery`.
+    pub single_pass: bool,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        Self {
+            n: 32,
+            cols: None,
+            extension: 2,
+            dims: Dims::Two,
+            n_clients: 100,
+            percent_censored: 0.0,
+            n_censored_abs: None,
+            rounding_mode: RoundingMode::Floor,
+            n_samples: 10,
+            honest_strategy: SampleStrategy::RandomPoints,
+            censor_strategy: SampleStrategy::RandomPoints,
+            share_fraction: 1.0,
+            offline_fraction: 0.0,
+            mask_strategy: MaskStrategy::Block,
+            per_client_mask: false,
+            honest_fraction: 0.0,
+            full_recovery: false,
+            row_threshold: None,
+            col_threshold: None,
+            error_rate: 0.0,
+            client_model: ClientModel::Aggregate,
+            topology: None,
+            single_pass: false,
+        }
+    }
 }
 
+/// The outcome of running an experiment's trials
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RunResult {
+    /// The fraction of trials that succeeded, see `ExperimentConfig::full_recovery`
+    pub prob: f32,
+    /// The fraction of trials in which all of the original data could be reconstructed
+    pub all_recoverable: f32,
+    /// The fraction of censored clients that had every one of their requests answered, and
+    /// so would accept the block, averaged over trials. Only known when censored clients are
+    /// simulated one by one (e.g. with `ClientModel::Individual`) and there are some.
+    pub accept_frac_mean: Option<f32>,
+    /// The population standard deviation of that fraction across trials, known whenever
+    /// `accept_frac_mean` is
+    pub accept_frac_std: Option<f32>,
+    /// The raw counts behind `prob`, so results can be pooled, see `TrialStats`
+    pub successes: usize,
+    pub trials: usize,
+    /// The fraction of trials in which a uniformly random original cell could be
+    /// reconstructed from the same grid, as a control for how much worse the censored
+    /// target fares than a typical cell
+    pub control_prob: f32,
+}
+
+const N_EXPERIMENTS: usize = 500;
+
+/// A fresh seed from the OS, for experiments that weren't given one
+fn entropy_seed() -> u64 {
+    SmallRng::from_entropy().next_u64()
+}
+
+/// The largest network `min_clients_for_prob` will search
+pub const MAX_CLIENTS: usize = 1 << 20;
+
 impl ExperimentConfig {
-    pub fn run(&self) -> f32 {
-        if self.dims == 1 {
-            self.run_generic::<Grid1dErasure>()
-        } else if self.dims == 2 {
-            self.run_generic::<Grid2dErasure>()
+    /// Has every client, honest or censored, sample with `strategy`
+    pub fn with_sample_strategy(self, strategy: SampleStrategy) -> Self {
+        Self {
+            honest_strategy: strategy.clone(),
+            censor_strategy: strategy,
+            ..self
+        }
+    }
+
+    /// Censors exactly `n` clients, whatever `percent_censored` is
+    pub fn with_n_censored(self, n: usize) -> Self {
+        Self {
+            n_censored_abs: Some(n),
+            ..self
+        }
+    }
+
+    /// Checks everything `run` needs of this config, and returns every field that breaks
+    /// it: the grid must have data and clients must sample something, every fraction must
+    /// be between 0 and 1, no more clients can be censored than there are, boxes must fit the
+    /// extended grid for `dims` (see `SampleStrategy::check_fits`), and that grid must
+    /// support the mask strategy and thresholds.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        match self.dims {
+            Dims::Zero => self.validate_for::<GridRawErasure>(),
+            Dims::One => self.validate_for::<Grid1dErasure>(),
+            Dims::OneAndAHalf => self.validate_for::<Grid1_5dErasure>(),
+            Dims::Two => self.validate_for::<Grid2dErasure>(),
+            Dims::Three => self.validate_for::<Grid3dErasure>(),
+            Dims::Four => self.validate_for::<GridNdErasure<4>>(),
+        }
+    }
+
+    /// Same as `validate`, but checks the grid-dependent fields against `R` rather than the
+    /// grid `dims` picks, as `run_generic::<R>` needs
+    pub fn validate_for<R: Reconstructable>(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let mut fail = |field, message: String| errors.push(ConfigError { field, message });
+        if self.n == 0 {
+            fail("n", "must be at least 1".to_owned());
+        }
+        if self.cols == Some(0) {
+            fail("cols", "must be at least 1 if set".to_owned());
+        }
+        if self.extension == 0 {
+            fail("extension", "must be at least 1".to_owned());
+        }
+        if self.n_samples == 0 {
+            fail("n_samples", "must be at least 1".to_owned());
+        }
+        for (field, fraction) in [
+            ("percent_censored", self.percent_censored),
+            ("share_fraction", self.share_fraction),
+            ("offline_fraction", self.offline_fraction),
+            ("honest_fraction", self.honest_fraction),
+            ("error_rate", self.error_rate),
+        ] {
+            if !(0.0..=1.0).contains(&fraction) {
+                fail(field, Error::InvalidFraction(fraction).to_string());
+            }
+        }
+        if let Some(n_censored) = self.n_censored_abs {
+            if n_censored > self.n_clients {
+                fail(
+                    "n_censored_abs",
+                    format!("can't censor {} of {} clients", n_censored, self.n_clients),
+                );
+            }
+        }
+        // The rest needs an actual grid
+        if self.n > 0 && self.cols != Some(0) && self.extension > 0 {
+            self.validate_generic::<R>(&mut fail);
+        }
+        if errors.is_empty() {
+            Ok(())
         } else {
-            unimplemented!()
+            Err(errors)
         }
     }
 
+    fn validate_generic<R: Reconstructable>(&self, fail: &mut impl FnMut(&'static str, String)) {
+        let shape = self.shape();
+        // Checked one at a time so each error names the threshold that's wrong
+        for (field, thresholds) in [
+            ("row_threshold", (self.row_threshold, None)),
+            ("col_threshold", (None, self.col_threshold)),
+        ] {
+            if thresholds == (None, None) {
+                continue;
+            }
+            if let Err(e) = R::new_with_thresholds(shape, thresholds.0, thresholds.1) {
+                fail(field, e.to_string());
+            }
+        }
+        let mut rng = SmallRng::seed_from_u64(0);
+        if let Err(e) = R::new_mask_with(&mut rng, shape, &self.mask_strategy) {
+            fail("mask_strategy", e.to_string());
+        }
+        let grid = R::new(shape);
+        let (w, h) = (grid.grid().w(), grid.grid().h());
+        for (field, strategy) in [
+            ("honest_strategy", &self.honest_strategy),
+            ("censor_strategy", &self.censor_strategy),
+        ] {
+            if let Err(e) = strategy.check_fits(w, h) {
+                fail(field, e.to_string());
+            }
+        }
+    }
+
+    /// The probability the censored target can be reconstructed. Panics if the config is
+    /// invalid, see `validate`.
+    pub fn run(&self) -> f32 {
+        self.run_with_seed(None).prob
+    }
+
+    /// Same as `run_result`, but errors rather than running an invalid config, see `validate`
+    pub fn try_run_result(&self) -> Result<RunResult, Error> {
+        self.validate().map_err(Error::InvalidConfig)?;
+        Ok(self.run_result())
+    }
+
+    /// Same as `try_run_result`, but all randomness is derived from `seed`
+    pub fn try_run_result_seeded(&self, seed: u64) -> Result<RunResult, Error> {
+        self.validate().map_err(Error::InvalidConfig)?;
+        Ok(self.run_result_seeded(seed))
+    }
+
+    /// Same as `run`, but all randomness is derived from `seed` so results are reproducible
+    pub fn run_seeded(&self, seed: u64) -> f32 {
+        self.run_with_seed(Some(seed)).prob
+    }
+
+    /// A key covering every field, including the strategies' parameters and the topology,
+    /// so equal configs have equal keys and configs differing in anything don't. It's the
+    /// config as JSON, so it stays the same between runs of the same build.
+    pub fn cache_key(&self) -> String {
+        serde_json::to_string(self).expect("configs always serialize")
+    }
+
+    /// Same as `run`, but reuses the probability in `cache` if a config with the same
+    /// `cache_key` was already run, instead of running its trials again
+    pub fn run_cached(&self, cache: &mut HashMap<String, f32>) -> f32 {
+        *cache.entry(self.cache_key()).or_insert_with(|| self.run())
+    }
+
+    /// Same as `run`, but also reports how often the whole original data was recoverable
+    pub fn run_result(&self) -> RunResult {
+        self.run_with_seed(None)
+    }
+
+    /// Same as `run_result`, but all randomness is derived from `seed`
+    pub fn run_result_seeded(&self, seed: u64) -> RunResult {
+        self.run_with_seed(Some(seed))
+    }
+
+    /// Same as `run_result`, but returns the raw counts, e.g. to pool with another run's
+    pub fn run_stats(&self) -> TrialStats {
+        self.stats_with_seed(None, 0..N_EXPERIMENTS)
+    }
+
+    /// Same as `run_stats`, but all randomness is derived from `seed`
+    pub fn run_stats_seeded(&self, seed: u64) -> TrialStats {
+        self.stats_with_seed(Some(seed), 0..N_EXPERIMENTS)
+    }
+
+    /// Runs only the given trials of `run_stats_seeded`, e.g. `0..1` to time a single
+    /// trial. Splitting the trials up and merging their stats gives the same stats as
+    /// running them all at once.
+    pub fn run_trials_seeded(&self, seed: u64, trials: Range<usize>) -> TrialStats {
+        self.stats_with_seed(Some(seed), trials)
+    }
+
+    fn run_with_seed(&self, seed: Option<u64>) -> RunResult {
+        self.stats_with_seed(seed, 0..N_EXPERIMENTS).result()
+    }
+
+    fn stats_with_seed(&self, seed: Option<u64>, trials: Range<usize>) -> TrialStats {
+        if let Err(errors) = self.validate() {
+            panic!("{}", Error::InvalidConfig(errors));
+        }
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!(
+            "run",
+            n = self.n,
+            dims = %self.dims,
+            n_clients = self.n_clients,
+            percent_censored = self.percent_censored,
+            n_samples = self.n_samples,
+            strategy = %self.honest_strategy,
+        )
+        .entered();
+        let stats = match self.dims {
+            Dims::Zero => self.run_generic_seeded::<GridRawErasure>(seed, trials),
+            Dims::One => self.run_generic_seeded::<Grid1dErasure>(seed, trials),
+            Dims::OneAndAHalf => self.run_generic_seeded::<Grid1_5dErasure>(seed, trials),
+            Dims::Two => self.run_generic_seeded::<Grid2dErasure>(seed, trials),
+            Dims::Three => self.run_generic_seeded::<Grid3dErasure>(seed, trials),
+            Dims::Four => self.run_generic_seeded::<GridNdErasure<4>>(seed, trials),
+        };
+        #[cfg(feature = "trace")]
+        tracing::info!(
+            successes = stats.successes,
+            trials = stats.trials,
+            "finished"
+        );
+        stats
+    }
+
+    /// Same as `run`, but on grids of type `R` rather than the grid `dims` picks, e.g. to
+    /// simulate a coding scheme implemented outside this crate. Every other field is used
+    /// as `run` uses it: `R`'s grids are built from `shape`, the adversary's mask comes from
+    /// `R::new_mask_with`, clients fill their grids with `sample`, `sample_exclusion` and
+    /// `merge_from`, and a trial succeeds if `R::can_reconstruct` holds for the target (or
+    /// `can_reconstruct_all_cells` with `full_recovery`). The config isn't validated, call
+    /// `validate_for::<R>` first.
     pub fn run_generic<R: Reconstructable>(&self) -> f32 {
-        let (mask, censor_target) = R::new_mask(&mut thread_rng(), self.n);
+        self.run_generic_seeded::<R>(None, 0..N_EXPERIMENTS)
+            .result()
+            .prob
+    }
+
+    /// Same as `run_generic`, but also reports how often the whole original data was
+    /// recoverable, like `run_result`
+    pub fn run_generic_result<R: Reconstructable>(&self) -> RunResult {
+        self.run_generic_seeded::<R>(None, 0..N_EXPERIMENTS)
+            .result()
+    }
+
+    /// Same as `run_generic_result`, but all randomness is derived from `seed`
+    pub fn run_generic_result_seeded<R: Reconstructable>(&self, seed: u64) -> RunResult {
+        self.run_generic_seeded::<R>(Some(seed), 0..N_EXPERIMENTS)
+            .result()
+    }
+
+    fn run_generic_seeded<R: Reconstructable>(
+        &self,
+        seed: Option<u64>,
+        trials: Range<usize>,
+    ) -> TrialStats {
+        // Only go to the OS for entropy once per experiment, every trial is then seeded
+        // cheaply from a counter
+        let seed = seed.unwrap_or_else(entropy_seed);
+        let (mask, censor_target) = self.new_mask::<R>(&mut SmallRng::seed_from_u64(seed));
+        self.trial_stats::<R>(&mask, &censor_target, seed, trials)
+    }
+
+    /// Same as `run_generic`, but censors with `mask` and checks `target` rather than
+    /// drawing them, to study one attack across many random trials. `mask` has the cells
+    /// the adversary answers set, and must be the size of this config's `R` grids.
+    pub fn run_with_mask<R: Reconstructable>(&self, mask: &Grid, target: R::Index) -> f32 {
+        self.run_with_mask_seeded::<R>(mask, target, entropy_seed())
+    }
+
+    /// Same as `run_with_mask`, but the trials are seeded from `seed`
+    pub fn run_with_mask_seeded<R: Reconstructable>(
+        &self,
+        mask: &Grid,
+        target: R::Index,
+        seed: u64,
+    ) -> f32 {
+        self.result_with_mask::<R>(mask, &target, seed).prob
+    }
+
+    /// Runs every trial against an already drawn mask and target, seeding trial `i`
+    /// with `seed + i + 1`
+    fn result_with_mask<R: Reconstructable>(
+        &self,
+        mask: &Grid,
+        target: &R::Index,
+        seed: u64,
+    ) -> RunResult {
+        self.trial_stats::<R>(mask, target, seed, 0..N_EXPERIMENTS)
+            .result()
+    }
 
-        let mut recon_count = 0;
-        const N_EXPERIMENTS: usize = 500;
-        let n_censored = (self.n_clients as f64 * self.percent_censored).floor() as usize;
-        for _ in 0..N_EXPERIMENTS {
-            let mut rng = SmallRng::from_entropy();
+    /// Runs the given trials of `result_with_mask`, so any split of them merges back into
+    /// the same stats
+    fn trial_stats<R: Reconstructable>(
+        &self,
+        mask: &Grid,
+        target: &R::Index,
+        seed: u64,
+        trials: Range<usize>,
+    ) -> TrialStats {
+        let n_censored = self.n_censored();
+        let mut stats = TrialStats::default();
+        for trial in trials {
+            #[cfg(feature = "trace")]
+            let _span = tracing::debug_span!(
+                "trial",
+                trial,
+                n = self.n,
+                dims = %self.dims,
+                strategy = %self.honest_strategy,
+            )
+            .entered();
+            let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(trial as u64 + 1));
+            let (res, accepted) = self.trial::<R>(&mut rng, mask);
+            // Only computed when the event is enabled, reconstructing again isn't free
+            #[cfg(feature = "trace")]
+            tracing::debug!(
+                rounds = res.reconstruction_rounds(),
+                density = {
+                    let grid = res.grid();
+                    grid.count_ones() as f64 / (grid.w() * grid.h()) as f64
+                },
+                "merged"
+            );
+            // Drawn after the trial, so it doesn't change any of the trial's draws
+            let control = R::random_index(&mut rng, self.shape());
+            let accept_frac = accepted
+                .filter(|_| n_censored > 0)
+                .map(|accepted| accepted as f64 / n_censored as f64);
+            stats.record(
+                self.succeeded(&res, target),
+                res.can_reconstruct_all(),
+                self.recovers(&res, control),
+                accept_frac,
+            );
+        }
+        stats
+    }
+
+    /// Whether a trial that ended up with `res` counts as a success
+    fn succeeded<R: Reconstructable>(&self, res: &R, target: &R::Index) -> bool {
+        if self.full_recovery {
+            res.can_reconstruct_all_cells()
+        } else {
+            self.recovers(res, target.clone())
+        }
+    }
+
+    /// Whether `i` can be reconstructed from `res`, in a single pass if `single_pass` is set
+    fn recovers<R: Reconstructable>(&self, res: &R, i: R::Index) -> bool {
+        if self.single_pass {
+            res.can_reconstruct_single_pass(i)
+        } else {
+            res.can_reconstruct(i)
+        }
+    }
+
+    /// Runs every config like `run`, but draws one mask and target for each group of configs
+    /// that would draw them the same way (the same `dims`, shape and `mask_strategy`) instead
+    /// of one per config. Each config still runs its trials from its own seed.
+    /// Returns the probabilities in the same order as `configs`, or `Error::InvalidConfig`
+    /// for the first config that doesn't validate, before anything is run.
+    pub fn run_batch(configs: &[ExperimentConfig]) -> Result<Vec<f32>, Error> {
+        Self::run_batch_seeded(configs, entropy_seed())
+    }
+
+    /// Same as `run_batch`, but all randomness is derived from `seed`. Each config's seed is
+    /// drawn from it in order, and each group's mask from its first config's seed, so a
+    /// batch of one config runs like `run_seeded` with that seed.
+    pub fn run_batch_seeded(configs: &[ExperimentConfig], seed: u64) -> Result<Vec<f32>, Error> {
+        for e in configs {
+            e.validate().map_err(Error::InvalidConfig)?;
+        }
+        let mut seeds = SmallRng::seed_from_u64(seed);
+        let seeds: Vec<u64> = configs.iter().map(|_| seeds.next_u64()).collect();
+        let mut groups: HashMap<_, Vec<usize>> = HashMap::new();
+        for (i, e) in configs.iter().enumerate() {
+            let key = (e.dims, e.shape(), e.mask_strategy.clone());
+            groups.entry(key).or_default().push(i);
+        }
+        let mut probs = vec![0.0; configs.len()];
+        for ((dims, _, _), indices) in groups {
+            let group: Vec<(&ExperimentConfig, u64)> =
+                indices.iter().map(|&i| (&configs[i], seeds[i])).collect();
+            let group_probs = match dims {
+                Dims::Zero => Self::run_group::<GridRawErasure>(&group),
+                Dims::One => Self::run_group::<Grid1dErasure>(&group),
+                Dims::OneAndAHalf => Self::run_group::<Grid1_5dErasure>(&group),
+                Dims::Two => Self::run_group::<Grid2dErasure>(&group),
+                Dims::Three => Self::run_group::<Grid3dErasure>(&group),
+                Dims::Four => Self::run_group::<GridNdErasure<4>>(&group),
+            };
+            for (i, prob) in indices.into_iter().zip(group_probs) {
+                probs[i] = prob;
+            }
+        }
+        Ok(probs)
+    }
+
+    fn run_group<R: Reconstructable>(group: &[(&ExperimentConfig, u64)]) -> Vec<f32> {
+        let (first, first_seed) = group[0];
+        let (mask, censor_target) = first.new_mask::<R>(&mut SmallRng::seed_from_u64(first_seed));
+        group
+            .iter()
+            .map(|(e, seed)| e.result_with_mask::<R>(&mask, &censor_target, *seed).prob)
+            .collect()
+    }
+
+    /// Runs a clone of `self` for each `n_clients` in `clients`, in parallel, keeping every
+    /// other parameter fixed. Returns `(n_clients, prob)` pairs in the order given.
+    pub fn sweep_clients(&self, clients: impl Iterator<Item = usize>) -> Vec<(usize, f32)> {
+        self.sweep_clients_by(clients, |e| e.run())
+    }
+
+    fn sweep_clients_by(
+        &self,
+        clients: impl Iterator<Item = usize>,
+        prob: impl Fn(&ExperimentConfig) -> f32 + Sync,
+    ) -> Vec<(usize, f32)> {
+        let clients = clients.collect::<Vec<_>>();
+        #[cfg(feature = "parallel")]
+        let clients = clients.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let clients = clients.into_iter();
+        clients
+            .map(|n_clients| {
+                let e = ExperimentConfig {
+                    n_clients,
+                    ..self.clone()
+                };
+                (n_clients, prob(&e))
+            })
+            .collect()
+    }
+
+    /// How much each additional client raises the probability of reconstruction: entry `k`
+    /// is the probability with `k + 1` clients minus the probability with `k`, for every
+    /// `k < max_clients`. The probabilities all come from one `sweep_clients`.
+    pub fn marginal_gains(&self, max_clients: usize) -> Vec<f32> {
+        self.marginal_gains_by(max_clients, |e| e.run())
+    }
+
+    fn marginal_gains_by(
+        &self,
+        max_clients: usize,
+        prob: impl Fn(&ExperimentConfig) -> f32 + Sync,
+    ) -> Vec<f32> {
+        self.sweep_clients_by(0..=max_clients, prob)
+            .windows(2)
+            .map(|pair| pair[1].1 - pair[0].1)
+            .collect()
+    }
+
+    /// The probability of reconstruction with 1d and with 2d erasure coding, running clones
+    /// of `self` that only differ in `dims` from the same seed. Panics like `run` if `self`
+    /// uses settings only 2d grids support.
+    pub fn compare_dims(&self) -> (f32, f32) {
+        self.compare_dims_seeded(entropy_seed())
+    }
+
+    /// Same as `compare_dims`, but from a given seed
+    pub fn compare_dims_seeded(&self, seed: u64) -> (f32, f32) {
+        let run_as = |dims| {
+            ExperimentConfig {
+                dims,
+                ..self.clone()
+            }
+            .run_seeded(seed)
+        };
+        (run_as(Dims::One), run_as(Dims::Two))
+    }
+
+    /// How much censorship lowers the probability of reconstruction: the probability for
+    /// a clone of `self` with no censored clients minus the probability for `self`
+    pub fn censorship_delta(&self) -> f32 {
+        self.censorship_delta_by(|e| e.run())
+    }
+
+    fn censorship_delta_by(&self, prob: impl Fn(&ExperimentConfig) -> f32) -> f32 {
+        let uncensored = ExperimentConfig {
+            percent_censored: 0.0,
+            n_censored_abs: None,
+            ..self.clone()
+        };
+        prob(&uncensored) - prob(self)
+    }
+
+    /// The smallest `n_clients` for which `run` reaches at least `target` probability,
+    /// keeping every other parameter fixed. Each probe runs a clone of `self` with only
+    /// `n_clients` changed. Assumes the probability grows with the number of clients, and
+    /// panics if `target` isn't reached by `MAX_CLIENTS` clients.
+    pub fn min_clients_for_prob(&self, target: f32) -> usize {
+        self.min_clients_by(target, |e| e.run())
+    }
+
+    fn min_clients_by(&self, target: f32, prob: impl Fn(&ExperimentConfig) -> f32) -> usize {
+        let meets_target = |n_clients| {
+            let e = ExperimentConfig {
+                n_clients,
+                ..self.clone()
+            };
+            prob(&e) >= target
+        };
+        // Nobody samples anything with no clients, so that only works for a trivial target
+        if target <= 0.0 {
+            return 0;
+        }
+        // Double until we overshoot, then bisect between the last two probes
+        let mut lo = 0;
+        let mut hi = self.n_clients.max(1);
+        while !meets_target(hi) {
+            assert!(
+                hi < MAX_CLIENTS,
+                "probability {} not reached with {} clients",
+                target,
+                MAX_CLIENTS
+            );
+            lo = hi;
+            hi = (hi * 2).min(MAX_CLIENTS);
+        }
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if meets_target(mid) {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        hi
+    }
+
+    /// For every cell of the (extended) grid, the fraction of trials in which it could be
+    /// reconstructed. Indexed as `map[row][col]`.
+    pub fn run_coverage_map(&self) -> Vec<Vec<f64>> {
+        match self.dims {
+            Dims::Zero => self.run_coverage_map_generic::<GridRawErasure>(),
+            Dims::One => self.run_coverage_map_generic::<Grid1dErasure>(),
+            Dims::OneAndAHalf => self.run_coverage_map_generic::<Grid1_5dErasure>(),
+            Dims::Two => self.run_coverage_map_generic::<Grid2dErasure>(),
+            Dims::Three => self.run_coverage_map_generic::<Grid3dErasure>(),
+            Dims::Four => self.run_coverage_map_generic::<GridNdErasure<4>>(),
+        }
+    }
+
+    pub fn run_coverage_map_generic<R: Reconstructable>(&self) -> Vec<Vec<f64>> {
+        let seed = entropy_seed();
+        let (mask, _) = self.new_mask::<R>(&mut SmallRng::seed_from_u64(seed));
+
+        let mut counts = vec![vec![0usize; mask.w()]; mask.h()];
+        for trial in 0..N_EXPERIMENTS {
+            let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(trial as u64 + 1));
+            let closure = self
+                .trial_grid::<R>(&mut rng, &mask)
+                .reconstruction_closure();
+            for (i, row) in counts.iter_mut().enumerate() {
+                for (j, count) in row.iter_mut().enumerate() {
+                    *count += closure.get(i, j) as usize;
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|count| count as f64 / N_EXPERIMENTS as f64)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn shape(&self) -> Shape {
+        Shape::new(self.n, self.cols.unwrap_or(self.n)).with_extension(self.extension)
+    }
+
+    /// The width and height of what one sample of `strategy` covers in this config's grid
+    pub(crate) fn sample_size(&self, strategy: &SampleStrategy) -> (usize, usize) {
+        match *strategy {
+            SampleStrategy::Box { width, height }
+            | SampleStrategy::SlidingBox { width, height } => (width, height),
+            SampleStrategy::FullLines { lines, axis } => {
+                let (w, h) = self.grid_size();
+                match axis {
+                    Axis::Row => (w, lines),
+                    Axis::Column => (lines, h),
+                }
+            }
+            SampleStrategy::RandomPoints => (1, 1),
+        }
+    }
+
+    /// The size of the extended grid `dims` picks, as `(w, h)` like `Grid::new`
+    fn grid_size(&self) -> (usize, usize) {
+        match self.dims {
+            Dims::Zero => self.grid_size_generic::<GridRawErasure>(),
+            Dims::One => self.grid_size_generic::<Grid1dErasure>(),
+            Dims::OneAndAHalf => self.grid_size_generic::<Grid1_5dErasure>(),
+            Dims::Two => self.grid_size_generic::<Grid2dErasure>(),
+            Dims::Three => self.grid_size_generic::<Grid3dErasure>(),
+            Dims::Four => self.grid_size_generic::<GridNdErasure<4>>(),
+        }
+    }
+
+    fn grid_size_generic<R: Reconstructable>(&self) -> (usize, usize) {
+        let grid = R::new(self.shape());
+        (grid.grid().w(), grid.grid().h())
+    }
+
+    fn new_mask<R: Reconstructable>(&self, rng: &mut SmallRng) -> (Grid, R::Index) {
+        R::new_mask_with(rng, self.shape(), &self.mask_strategy).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn new_grid<R: Reconstructable>(&self) -> R {
+        R::new_with_thresholds(self.shape(), self.row_threshold, self.col_threshold)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn n_censored(&self) -> usize {
+        if let Some(n_censored) = self.n_censored_abs {
+            assert!(
+                n_censored <= self.n_clients,
+                "can't censor {} of {} clients",
+                n_censored,
+                self.n_clients
+            );
+            return n_censored;
+        }
+        let n_censored = self.n_clients as f64 * self.percent_censored;
+        let n_censored = match self.rounding_mode {
+            RoundingMode::Floor => n_censored.floor(),
+            RoundingMode::Ceil => n_censored.ceil(),
+            RoundingMode::Round => n_censored.round(),
+        };
+        // Don't let floating point error round past the number of clients
+        (n_censored as usize).min(self.n_clients)
+    }
+
+    /// Simulates every client sampling once and returns the grid reconstruction is checked
+    /// against: what the whole network ends up with, or what the evaluator of `topology` sees
+    fn trial_grid<R: Reconstructable>(&self, rng: &mut SmallRng, mask: &Grid) -> R {
+        self.trial(rng, mask).0
+    }
+
+    /// Same as `trial_grid`, but also returns how many censored clients had all their
+    /// requests answered, if they were simulated one by one
+    fn trial<R: Reconstructable>(&self, rng: &mut SmallRng, mask: &Grid) -> (R, Option<usize>) {
+        let (mut res, accepted) = match &self.topology {
+            Some(topology) => {
+                // Every client's grid is needed to gossip them
+                let mut clients = Vec::new();
+                let mut keep = |client: &R| {
+                    let mut grid = self.new_grid::<R>();
+                    grid.merge_from(client);
+                    clients.push(grid);
+                };
+                let accepted = self.censored_clients(rng, mask, &mut keep);
+                self.honest_clients(rng, &mut keep);
+                let res = topology.gossip(rng, clients, || self.new_grid::<R>());
+                (res, Some(accepted))
+            }
+            None => self.merged_grid(rng, mask),
+        };
+        // Corrupted responses are only noticed once everything has been shared
+        if self.error_rate > 0.0 {
+            res.thin(rng, 1.0 - self.error_rate);
+        }
+        (res, accepted)
+    }
+
+    /// How each censored client's samples are split between honest and censored answers
+    fn censored_client_samples(&self) -> (usize, usize) {
+        let n_honest_samples = (self.n_samples as f64 * self.honest_fraction).round() as usize;
+        (n_honest_samples, self.n_samples - n_honest_samples)
+    }
+
+    /// Simulates the censored clients one by one, passing each one's shared grid to `share`.
+    /// Returns how many of them had all of their requests answered.
+    fn censored_clients<R: Reconstructable>(
+        &self,
+        rng: &mut SmallRng,
+        mask: &Grid,
+        mut share: impl FnMut(&R),
+    ) -> usize {
+        let (n_honest_samples, n_censored_samples) = self.censored_client_samples();
+        let mut client = self.new_grid::<R>();
+        let mut accepted = 0;
+        for i in 0..self.n_censored() {
+            // The first client is the one whose target we try to reconstruct
+            let client_mask = if self.per_client_mask && i > 0 {
+                Some(self.new_mask::<R>(rng).0)
+            } else {
+                None
+            };
+            client.reset();
+            let withheld = client.sample_exclusion(
+                rng,
+                n_censored_samples,
+                &self.censor_strategy,
+                client_mask.as_ref().unwrap_or(mask),
+            );
+            accepted += (withheld == 0) as usize;
+            client.sample(rng, n_honest_samples, &self.censor_strategy);
+            client.thin(rng, self.share_fraction);
+            share(&client);
+        }
+        accepted
+    }
+
+    /// Simulates the honest clients one by one, passing the grid of each one that stays
+    /// online to `share`
+    fn honest_clients<R: Reconstructable>(&self, rng: &mut SmallRng, mut share: impl FnMut(&R)) {
+        let n_honest = self.n_clients - self.n_censored();
+        let n_offline = (n_honest as f64 * self.offline_fraction).floor() as usize;
+        let mut client = self.new_grid::<R>();
+        for i in 0..n_honest {
+            client.reset();
+            client.sample(rng, self.n_samples, &self.honest_strategy);
+            client.thin(rng, self.share_fraction);
+            // The first n_offline honest clients drop out before sharing
+            if i >= n_offline {
+                share(&client);
+            }
+        }
+    }
+
+    /// Simulates every client sampling once and returns the merged grid the network ends
+    /// up with, assuming everything shared reaches everyone. Also returns how many censored
+    /// clients had all their requests answered, if they were simulated one by one.
+    fn merged_grid<R: Reconstructable>(
+        &self,
+        rng: &mut SmallRng,
+        mask: &Grid,
+    ) -> (R, Option<usize>) {
+        let n_censored = self.n_censored();
+        let n_honest = self.n_clients - n_censored;
+        let n_offline = (n_honest as f64 * self.offline_fraction).floor() as usize;
+        let (n_honest_samples, n_censored_samples) = self.censored_client_samples();
+
+        let individual = self.client_model == ClientModel::Individual;
+        let individual_censored = individual || self.share_fraction < 1.0 || self.per_client_mask;
+        let (censor_grid, accepted) = if individual_censored {
+            // Each client thins its own samples or gets its own mask, so they have to be
+            // simulated individually
+            let mut res = self.new_grid::<R>();
+            let accepted = self.censored_clients(rng, mask, |client: &R| res.merge_from(client));
+            (res, Some(accepted))
+        } else {
             // Grid that mimmics n_censored clients each making n_samples with censorship
-            let mut censor_grid = R::new(self.n);
+            let mut censor_grid = self.new_grid::<R>();
             censor_grid.sample_exclusion(
-                &mut rng,
-                self.n_samples * n_censored, // n_censored nodes making n_samples requests
-                &self.sample_strategy,
-                &mask,
+                rng,
+                n_censored_samples * n_censored, // n_censored nodes making n_samples requests
+                &self.censor_strategy,
+                mask,
             );
-            // Grid that mimmics n_clients - n_censored clients making n_samples with censorship
-            let mut honest_grid = R::new(self.n);
+            (censor_grid, None)
+        };
+
+        let honest_grid = if individual || self.share_fraction < 1.0 {
+            let mut res = self.new_grid::<R>();
+            self.honest_clients(rng, |client: &R| res.merge_from(client));
+            res
+        } else {
+            // Grid that mimmics the n_clients - n_censored clients that stay online making n_samples.
+            // Samples from offline clients never reach the network, so they are never drawn.
+            // Unless they were simulated individually, the censored clients' honestly
+            // answered samples land here too.
+            let censored_client_samples = match individual_censored {
+                true => 0,
+                false => n_honest_samples * n_censored,
+            };
+            let mut honest_grid = self.new_grid::<R>();
             honest_grid.sample(
-                &mut rng,
-                self.n_samples * (self.n_clients - n_censored),
-                &self.sample_strategy,
+                rng,
+                self.n_samples * (n_honest - n_offline),
+                &self.honest_strategy,
             );
-            let res = censor_grid.merge(honest_grid);
-
-            let recon = res.can_reconstruct(censor_target.clone());
-            recon_count += recon as i32;
-        }
-        (recon_count as f32) / (N_EXPERIMENTS as f32)
+            honest_grid.sample(rng, censored_client_samples, &self.censor_strategy);
+            honest_grid
+        };
+        (censor_grid.merge(honest_grid), accepted)
     }
 
+    /// How many of the last columns of `header` are results rather than part of the config
+    const RESULT_COLUMNS: usize = 10;
+
     pub fn header() -> &'static [&'static str] {
         &[
             "dims",
             "n",
+            "cols",
+            "extension",
             "n_clients",
             "percent_censored",
+            "n_censored_abs",
+            "rounding_mode",
             "n_samples",
             "strategy",
             "box_width",
             "box_height",
+            "censor_strategy",
+            "censor_box_width",
+            "censor_box_height",
+            "share_fraction",
+            "offline_fraction",
+            "mask",
+            "withheld_rows",
+            "per_client_mask",
+            "honest_fraction",
+            "full_recovery",
+            "row_threshold",
+            "col_threshold",
+            "error_rate",
+            "client_model",
+            "edge_prob",
+            "gossip_rounds",
+            "evaluator",
+            "evaluator_clients",
+            "single_pass",
             "prob",
+            "all_recoverable",
+            "accept_frac_mean",
+            "successes",
+            "trials",
+            "prob_ci_low",
+            "prob_ci_high",
+            "control_prob",
+            "prob_std_err",
+            "accept_frac_std",
         ]
     }
 
-    pub fn to_row(&self, prob: f32) -> Vec<String> {
-        let (box_width, box_height) = match self.sample_strategy {
-            SampleStrategy::Box { width, height } => (width, height),
-            SampleStrategy::RandomPoints => (1, 1),
+    pub fn to_row(&self, result: &RunResult) -> Vec<String> {
+        let mut row = self.config_row();
+        row.extend(Self::result_row(result));
+        row
+    }
+
+    /// Same as `to_row`, but for a result of `run_generic`: `scheme` names the grid that was
+    /// run in the dims column, since `dims` didn't pick it
+    pub fn to_row_for_scheme(&self, scheme: &str, result: &RunResult) -> Vec<String> {
+        let mut row = self.to_row(result);
+        row[0] = scheme.to_owned();
+        row
+    }
+
+    /// The results columns of `to_row`
+    fn result_row(result: &RunResult) -> Vec<String> {
+        let (prob_ci_low, prob_ci_high) = result.prob_interval();
+        vec![
+            format!("{:.10}", result.prob),
+            format!("{:.10}", result.all_recoverable),
+            result
+                .accept_frac_mean
+                .map(|frac| format!("{:.10}", frac))
+                .unwrap_or_default(),
+            result.successes.to_string(),
+            result.trials.to_string(),
+            format!("{:.10}", prob_ci_low),
+            format!("{:.10}", prob_ci_high),
+            format!("{:.10}", result.control_prob),
+            format!("{:.10}", result.prob_std_err()),
+            result
+                .accept_frac_std
+                .map(|std| format!("{:.10}", std))
+                .unwrap_or_default(),
+        ]
+    }
+
+    /// `to_row` without the results, which are always the last columns
+    fn config_row(&self) -> Vec<String> {
+        let (box_width, box_height) = self.sample_size(&self.honest_strategy);
+        let (censor_box_width, censor_box_height) = self.sample_size(&self.censor_strategy);
+        // The grid's default threshold is left blank
+        let threshold = |t: Option<usize>| t.map(|t| t.to_string()).unwrap_or_default();
+        // Everything is blank without a topology
+        let topology = match &self.topology {
+            Some(topology) => {
+                let evaluator_clients = match topology.evaluator {
+                    Evaluator::Clients { count } => count.to_string(),
+                    Evaluator::FullNode => String::new(),
+                };
+                [
+                    topology.edge_prob.to_string(),
+                    topology.rounds.to_string(),
+                    topology.evaluator.to_string(),
+                    evaluator_clients,
+                ]
+            }
+            None => Default::default(),
+        };
+        let withheld_rows = match self.mask_strategy {
+            MaskStrategy::WithholdRows { count } => count,
+            MaskStrategy::Block => 0,
         };
         vec![
             self.dims.to_string(),
             self.n.to_string(),
+            self.shape().cols.to_string(),
+            self.extension.to_string(),
             self.n_clients.to_string(),
             self.percent_censored.to_string(),
+            self.n_censored_abs
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            self.rounding_mode.to_string(),
             self.n_samples.to_string(),
-            self.sample_strategy.to_string(),
+            self.honest_strategy.to_string(),
             box_width.to_string(),
             box_height.to_string(),
-            format!("{:.10}", prob),
+            self.censor_strategy.to_string(),
+            censor_box_width.to_string(),
+            censor_box_height.to_string(),
+            self.share_fraction.to_string(),
+            self.offline_fraction.to_string(),
+            self.mask_strategy.to_string(),
+            withheld_rows.to_string(),
+            self.per_client_mask.to_string(),
+            self.honest_fraction.to_string(),
+            self.full_recovery.to_string(),
+            threshold(self.row_threshold),
+            threshold(self.col_threshold),
+            self.error_rate.to_string(),
+            self.client_model.to_string(),
         ]
+        .into_iter()
+        .chain(topology)
+        .chain([self.single_pass.to_string()])
+        .collect()
+    }
+}
+
+/// Runs every config like `ExperimentConfig::run`, in parallel with the `parallel` feature
+/// and with a progress bar on stderr with the `progress` feature. Returns each config with
+/// its probability, in the same order as `configs`.
+pub fn run_sweep(configs: Vec<ExperimentConfig>) -> Vec<(ExperimentConfig, f32)> {
+    sweep_by(configs, |e| e.run())
+}
+
+/// Same as `run_sweep`, but with each config's whole `RunResult`, e.g. to write with
+/// `ExperimentConfig::to_row`
+pub fn run_sweep_results(configs: Vec<ExperimentConfig>) -> Vec<(ExperimentConfig, RunResult)> {
+    sweep_by(configs, |e| e.run_result())
+}
+
+fn sweep_by<T: Send>(
+    configs: Vec<ExperimentConfig>,
+    run: impl Fn(&ExperimentConfig) -> T + Sync + Send,
+) -> Vec<(ExperimentConfig, T)> {
+    #[cfg(feature = "parallel")]
+    let configs = configs.into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let configs = configs.into_iter();
+    #[cfg(feature = "progress")]
+    let configs = {
+        let len = configs.len() as u64;
+        configs.progress_count(len)
+    };
+    configs
+        .map(|e| {
+            let result = run(&e);
+            (e, result)
+        })
+        .collect()
+}
+
+/// The mean probability and number of results in each group of `results` with the same
+/// `key_fn`, averaging over every other field, e.g. `aggregate_by(&results, |e| e.dims)`.
+/// Floats aren't `Hash`, so group by e.g. `e.percent_censored.to_bits()` instead.
+pub fn aggregate_by<K: Eq + Hash, F: Fn(&ExperimentConfig) -> K>(
+    results: &[(ExperimentConfig, f32)],
+    key_fn: F,
+) -> HashMap<K, (f32, usize)> {
+    let mut groups: HashMap<K, (f32, usize)> = HashMap::new();
+    for (e, prob) in results {
+        let (sum, count) = groups.entry(key_fn(e)).or_default();
+        *sum += prob;
+        *count += 1;
+    }
+    for (sum, count) in groups.values_mut() {
+        *sum /= *count as f32;
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(prob: f32) -> RunResult {
+        RunResult {
+            prob,
+            all_recoverable: prob,
+            accept_frac_mean: None,
+            accept_frac_std: None,
+            successes: (prob * N_EXPERIMENTS as f32) as usize,
+            trials: N_EXPERIMENTS,
+            control_prob: prob,
+        }
+    }
+
+    fn small_config() -> ExperimentConfig {
+        ExperimentConfig {
+            n: 8,
+            dims: Dims::Two,
+            n_clients: 20,
+            percent_censored: 0.2,
+            n_samples: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_share_fraction() {
+        let full = small_config();
+        assert_eq!(full.share_fraction, 1.0);
+        assert!(full.run_seeded(7) > 0.0);
+        // Sharing everything draws exactly the grid the aggregate path drew before
+        // share_fraction existed, which never thins
+        let (mask, _) = Grid2dErasure::new_mask(&mut SmallRng::seed_from_u64(7), full.shape());
+        let (merged, _) = full.merged_grid::<Grid2dErasure>(&mut SmallRng::seed_from_u64(8), &mask);
+        let mut rng = SmallRng::seed_from_u64(8);
+        let n_censored = full.n_censored();
+        let (n_honest_samples, n_censored_samples) = full.censored_client_samples();
+        let mut expected = full.new_grid::<Grid2dErasure>();
+        expected.sample_exclusion(
+            &mut rng,
+            n_censored_samples * n_censored,
+            &full.censor_strategy,
+            &mask,
+        );
+        let mut honest = full.new_grid::<Grid2dErasure>();
+        honest.sample(
+            &mut rng,
+            full.n_samples * (full.n_clients - n_censored),
+            &full.honest_strategy,
+        );
+        honest.sample(
+            &mut rng,
+            n_honest_samples * n_censored,
+            &full.censor_strategy,
+        );
+        assert_eq!(merged.grid(), expected.merge(honest).grid());
+
+        let none = ExperimentConfig {
+            share_fraction: 0.0,
+            ..small_config()
+        };
+        assert_eq!(none.run_seeded(7), 0.0);
+    }
+
+    #[test]
+    fn test_offline_fraction() {
+        let all_online = ExperimentConfig {
+            percent_censored: 0.0,
+            ..small_config()
+        };
+        assert!(all_online.run_seeded(3) > 0.0);
+
+        // Nobody is left to share anything
+        let all_offline = ExperimentConfig {
+            offline_fraction: 1.0,
+            ..all_online.clone()
+        };
+        assert_eq!(all_offline.run_seeded(3), 0.0);
+        let all_offline_individual = ExperimentConfig {
+            share_fraction: 0.99,
+            ..all_offline
+        };
+        assert_eq!(all_offline_individual.run_seeded(3), 0.0);
+    }
+
+    #[test]
+    fn test_coverage_map_fully_available() {
+        for dims in [Dims::One, Dims::OneAndAHalf, Dims::Two] {
+            let e = ExperimentConfig {
+                n: 4,
+                dims,
+                n_clients: 100,
+                percent_censored: 0.0,
+                ..Default::default()
+            };
+            let map = e.run_coverage_map();
+            let n_cells = match dims {
+                Dims::Two => 8 * 8,
+                _ => 4 * 8,
+            };
+            assert_eq!(map.len() * map[0].len(), n_cells);
+            assert!(map.iter().flatten().all(|&freq| freq == 1.0));
+        }
+    }
+
+    #[test]
+    fn test_withhold_rows_config() {
+        let e = ExperimentConfig {
+            n: 4,
+            n_clients: 50,
+            percent_censored: 1.0,
+            mask_strategy: MaskStrategy::WithholdRows { count: 5 },
+            ..Default::default()
+        };
+        assert_eq!(e.run_seeded(1), 0.0);
+    }
+
+    #[test]
+    fn test_per_client_mask() {
+        let shared = ExperimentConfig {
+            n: 4,
+            n_clients: 20,
+            percent_censored: 1.0,
+            n_samples: 20,
+            ..Default::default()
+        };
+        let per_client = ExperimentConfig {
+            per_client_mask: true,
+            ..shared.clone()
+        };
+        let mut rng = SmallRng::seed_from_u64(11);
+        let (mask, _) = shared.new_mask::<Grid2dErasure>(&mut rng);
+        let censored = mask.clone().not();
+
+        // With one shared mask nobody ever sees a censored cell...
+        let mut g: Grid2dErasure = shared.trial_grid(&mut SmallRng::seed_from_u64(12), &mask);
+        let mut seen = g.grid().clone();
+        seen.and_inplace(&censored);
+        assert_eq!(seen.count_ones(), 0);
+        // ...but clients with other masks can sample some of the first client's censored cells
+        g = per_client.trial_grid(&mut SmallRng::seed_from_u64(12), &mask);
+        let mut seen = g.grid().clone();
+        seen.and_inplace(&censored);
+        assert!(seen.count_ones() > 0);
+    }
+
+    #[test]
+    fn test_rectangular_config() {
+        let e = ExperimentConfig {
+            n: 4,
+            cols: Some(16),
+            n_clients: 100,
+            ..Default::default()
+        };
+        assert_eq!(e.run_seeded(5), 1.0);
+        let map = e.run_coverage_map();
+        assert_eq!((map.len(), map[0].len()), (8, 32));
+        assert_eq!(e.to_row(&result(1.0))[2], "16");
+
+        // 16 blobs, each 4 cells long
+        let e = ExperimentConfig {
+            dims: Dims::One,
+            ..e
+        };
+        let map = e.run_coverage_map();
+        assert_eq!((map.len(), map[0].len()), (8, 16));
+    }
+
+    #[test]
+    fn test_min_clients_for_prob() {
+        let e = ExperimentConfig {
+            n: 4,
+            n_clients: 1,
+            n_samples: 2,
+            ..Default::default()
+        };
+        let prob = |e: &ExperimentConfig| e.run_seeded(9);
+        let n_clients = e.min_clients_by(0.5, prob);
+        assert!(n_clients > 1);
+        let at = |n_clients| ExperimentConfig {
+            n_clients,
+            ..e.clone()
+        };
+        assert!(prob(&at(n_clients)) >= 0.5);
+        assert!(prob(&at(n_clients - 1)) < 0.5);
+    }
+
+    #[test]
+    fn test_extension_config() {
+        for dims in [Dims::One, Dims::OneAndAHalf, Dims::Two] {
+            let e = ExperimentConfig {
+                n: 4,
+                dims,
+                extension: 4,
+                n_clients: 40,
+                ..Default::default()
+            };
+            let map = e.run_coverage_map();
+            let n_cells = match dims {
+                Dims::Two => 16 * 16,
+                _ => 4 * 16,
+            };
+            assert_eq!(map.len() * map[0].len(), n_cells);
+            assert_eq!(e.run_seeded(2), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_honest_fraction() {
+        let uncensored = ExperimentConfig {
+            n: 4,
+            n_clients: 10,
+            n_samples: 4,
+            percent_censored: 0.0,
+            ..Default::default()
+        };
+        let censored = ExperimentConfig {
+            percent_censored: 0.9,
+            ..uncensored.clone()
+        };
+        assert!(censored.run_seeded(4) < uncensored.run_seeded(4));
+        // Answering everything honestly is the same as not censoring at all
+        let all_honest = ExperimentConfig {
+            honest_fraction: 1.0,
+            ..censored
+        };
+        assert_eq!(all_honest.run_seeded(4), uncensored.run_seeded(4));
+    }
+
+    #[test]
+    fn test_parse_names() {
+        // Everything parses back from the name it's written to CSVs with
+        for dims in [
+            Dims::Zero,
+            Dims::One,
+            Dims::OneAndAHalf,
+            Dims::Two,
+            Dims::Three,
+            Dims::Four,
+        ] {
+            assert_eq!(dims.to_string().parse(), Ok(dims));
+        }
+        for mode in [RoundingMode::Floor, RoundingMode::Ceil, RoundingMode::Round] {
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+        for model in [ClientModel::Aggregate, ClientModel::Individual] {
+            assert_eq!(model.to_string().parse(), Ok(model));
+        }
+        assert!("2d".parse::<Dims>().is_err());
+        assert!("floor".parse::<RoundingMode>().is_err());
+        assert_eq!(Dims::try_from(3_usize), Ok(Dims::Three));
+        assert_eq!(Dims::try_from(5_usize), Err(Error::UnsupportedDims(5)));
+    }
+
+    #[test]
+    fn test_3d() {
+        let e = ExperimentConfig {
+            n: 2,
+            dims: Dims::Three,
+            n_clients: 4,
+            n_samples: 2,
+            percent_censored: 0.5,
+            ..Default::default()
+        };
+        let prob = e.run_seeded(6);
+        assert!(prob > 0.0 && prob < 1.0);
+        assert_eq!(e.to_row(&result(prob))[0], "3");
+    }
+
+    #[test]
+    fn test_4d() {
+        let e = ExperimentConfig {
+            n: 2,
+            dims: Dims::Four,
+            n_clients: 8,
+            n_samples: 4,
+            percent_censored: 0.5,
+            ..Default::default()
+        };
+        let prob = e.run_seeded(6);
+        assert!(prob > 0.0 && prob < 1.0);
+        assert_eq!(e.to_row(&result(prob))[0], "4");
+    }
+
+    #[test]
+    fn test_no_coding() {
+        let e = ExperimentConfig {
+            dims: Dims::Zero,
+            percent_censored: 0.0,
+            ..small_config()
+        };
+        // Without coding, only the cells someone sampled are ever available
+        let map = e.run_coverage_map();
+        assert_eq!((map.len(), map[0].len()), (8, 8));
+        assert!(map.iter().flatten().all(|&freq| freq > 0.0 && freq < 1.0));
+        let coded = ExperimentConfig {
+            dims: Dims::Two,
+            ..e.clone()
+        };
+        assert!(e.run_seeded(8) < coded.run_seeded(8));
+        assert_eq!(e.to_row(&result(0.5))[0], "0");
+    }
+
+    // Every trial used to draw its own rng from the OS. Reseeding from a counter instead
+    // should estimate the same probability.
+    #[test]
+    fn test_counter_seeded_trials() {
+        let e = ExperimentConfig {
+            n: 4,
+            dims: Dims::Zero,
+            n_clients: 11,
+            n_samples: 1,
+            ..Default::default()
+        };
+        // Without coding the target is only available if one of the 11 samples hits it
+        let expected = 1.0 - (15.0f32 / 16.0).powi(11);
+        let (mask, target) = e.new_mask::<GridRawErasure>(&mut SmallRng::from_entropy());
+        let old = (0..N_EXPERIMENTS)
+            .filter(|_| {
+                e.trial_grid::<GridRawErasure>(&mut SmallRng::from_entropy(), &mask)
+                    .can_reconstruct(target)
+            })
+            .count() as f32
+            / N_EXPERIMENTS as f32;
+        assert!((old - expected).abs() < 0.1);
+        assert!((e.run() - expected).abs() < 0.1);
+        assert!((e.run_seeded(10) - expected).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_run_trials_seeded() {
+        let e = small_config();
+        let mut split = e.run_trials_seeded(7, 0..1);
+        assert_eq!(split.trials, 1);
+        split.merge(&e.run_trials_seeded(7, 1..N_EXPERIMENTS));
+        let all = e.run_stats_seeded(7);
+        assert_eq!(
+            (split.trials, split.successes, split.all_recoverable),
+            (all.trials, all.successes, all.all_recoverable)
+        );
+    }
+
+    #[test]
+    fn test_run_batch() {
+        let configs: Vec<ExperimentConfig> = [Dims::Zero, Dims::One, Dims::Two]
+            .into_iter()
+            .flat_map(|dims| {
+                [5, 10].map(|n_clients| ExperimentConfig {
+                    n: 4,
+                    dims,
+                    n_clients,
+                    n_samples: 2,
+                    percent_censored: 0.3,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let batched = ExperimentConfig::run_batch(&configs).unwrap();
+        assert_eq!(batched.len(), configs.len());
+        for (e, prob) in configs.iter().zip(batched) {
+            // Both are estimates from 500 trials, with standard deviation under 0.023
+            assert!((e.run() - prob).abs() < 0.15);
+        }
+        assert!(ExperimentConfig::run_batch(&[]).unwrap().is_empty());
+
+        // Seeded batches are reproducible, whatever order the groups are run in
+        let seeded = ExperimentConfig::run_batch_seeded(&configs, 9).unwrap();
+        assert_eq!(
+            seeded,
+            ExperimentConfig::run_batch_seeded(&configs, 9).unwrap()
+        );
+        // and a batch of one runs like `run_seeded`
+        let first_seed = SmallRng::seed_from_u64(9).next_u64();
+        assert_eq!(
+            ExperimentConfig::run_batch_seeded(&configs[..1], 9).unwrap(),
+            vec![configs[0].run_seeded(first_seed)]
+        );
+
+        // Nothing runs if any config is invalid
+        let mut invalid = configs.clone();
+        invalid[3].n_samples = 0;
+        assert!(matches!(
+            ExperimentConfig::run_batch(&invalid),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_censorship_delta() {
+        let uncensored = ExperimentConfig {
+            percent_censored: 0.0,
+            ..small_config()
+        };
+        assert_eq!(uncensored.censorship_delta_by(|e| e.run_seeded(13)), 0.0);
+        assert!(uncensored.censorship_delta().abs() < 0.15);
+
+        let censored = ExperimentConfig {
+            n: 4,
+            n_clients: 10,
+            n_samples: 4,
+            percent_censored: 0.9,
+            ..Default::default()
+        };
+        assert!(censored.censorship_delta_by(|e| e.run_seeded(13)) > 0.0);
+    }
+
+    #[test]
+    fn test_full_lines() {
+        let rows = |lines| SampleStrategy::FullLines {
+            lines,
+            axis: Axis::Row,
+        };
+        let e = ExperimentConfig {
+            n_samples: 1,
+            ..small_config()
+        }
+        .with_sample_strategy(rows(2));
+        assert!(e.run_seeded(3) > 0.0);
+        // Each sample covers 2 whole rows of the 16x16 extended grid
+        let row = e.to_row(&result(0.5));
+        let column = |name| ExperimentConfig::header().iter().position(|&h| h == name);
+        assert_eq!(row[column("strategy").unwrap()], "FullLines");
+        assert_eq!(row[column("box_width").unwrap()], "16");
+        assert_eq!(row[column("box_height").unwrap()], "2");
+
+        let errors = e.with_sample_strategy(rows(17)).validate().unwrap_err();
+        assert_eq!(errors[0].field, "honest_strategy");
+    }
+
+    #[test]
+    fn test_split_strategies() {
+        let boxes = SampleStrategy::Box {
+            width: 2,
+            height: 2,
+        };
+        let e = ExperimentConfig {
+            censor_strategy: boxes.clone(),
+            honest_fraction: 0.5,
+            ..small_config()
+        };
+        let prob = e.run_seeded(14);
+        assert!((0.0..=1.0).contains(&prob));
+        let column = ExperimentConfig::header()
+            .iter()
+            .position(|h| *h == "censor_strategy")
+            .unwrap();
+        assert_eq!(e.to_row(&result(prob))[column], "Box");
+
+        // Both strategies the same is what a single strategy used to be
+        let same = small_config().with_sample_strategy(SampleStrategy::RandomPoints);
+        assert_eq!(same.run_seeded(14), small_config().run_seeded(14));
+        let same = small_config().with_sample_strategy(boxes);
+        assert!(matches!(same.honest_strategy, SampleStrategy::Box { .. }));
+        assert!(matches!(same.censor_strategy, SampleStrategy::Box { .. }));
+        assert!(same.run_seeded(14) > 0.0);
+    }
+
+    #[test]
+    fn test_sweep_clients() {
+        // Without coding each sample is another independent chance to hit the target, so
+        // the probability is 1 - (15/16)^n_clients
+        let e = ExperimentConfig {
+            n: 4,
+            dims: Dims::Zero,
+            n_samples: 1,
+            ..Default::default()
+        };
+        let curve = e.sweep_clients([0, 8, 16, 32, 64].into_iter());
+        let clients: Vec<usize> = curve.iter().map(|&(n_clients, _)| n_clients).collect();
+        assert_eq!(clients, [0, 8, 16, 32, 64]);
+        assert_eq!(curve[0].1, 0.0);
+        assert!(curve.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+        let seeded = e.sweep_clients_by(10..13, |e| e.run_seeded(15));
+        let eleven = ExperimentConfig { n_clients: 11, ..e };
+        assert_eq!(seeded[1], (11, eleven.run_seeded(15)));
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_trace() {
+        use std::sync::{Arc, Mutex};
+        use tracing::{span, Event, Subscriber};
+        use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+        /// Records the name of every span and the fields of every event
+        struct Capture(Arc<Mutex<Vec<String>>>);
+
+        impl<S: Subscriber> Layer<S> for Capture {
+            fn on_new_span(&self, attrs: &span::Attributes<'_>, _: &span::Id, _: Context<'_, S>) {
+                let fields = attrs.metadata().fields();
+                assert!(["n", "dims", "strategy"]
+                    .iter()
+                    .all(|name| fields.field(name).is_some()));
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(attrs.metadata().name().to_string());
+            }
+
+            fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+                let fields = event.metadata().fields().iter().map(|f| f.name());
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(fields.collect::<Vec<_>>().join(","));
+            }
+        }
+
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(Capture(captured.clone()));
+        tracing::subscriber::with_default(subscriber, || small_config().run_seeded(3));
+        let captured = captured.lock().unwrap();
+        let count = |name: &str| captured.iter().filter(|c| *c == name).count();
+        assert_eq!(captured[0], "run");
+        assert_eq!(count("run"), 1);
+        assert_eq!(count("trial"), N_EXPERIMENTS);
+        assert_eq!(count("message,rounds,density"), N_EXPERIMENTS);
+        assert_eq!(count("message,successes,trials"), 1);
+    }
+
+    #[test]
+    fn test_run_sweep() {
+        let configs: Vec<ExperimentConfig> = [0, 8, 64]
+            .into_iter()
+            .map(|n_clients| ExperimentConfig {
+                n: 4,
+                dims: Dims::Zero,
+                n_clients,
+                n_samples: 1,
+                ..Default::default()
+            })
+            .collect();
+        let results = run_sweep(configs.clone());
+        let swept: Vec<ExperimentConfig> = results.iter().map(|(e, _)| e.clone()).collect();
+        assert_eq!(swept, configs);
+        assert_eq!(results[0].1, 0.0);
+        assert!(results.iter().all(|&(_, prob)| (0.0..=1.0).contains(&prob)));
+
+        let seeded = sweep_by(configs.clone(), |e| e.run_result_seeded(3));
+        for ((e, result), config) in seeded.iter().zip(&configs) {
+            assert_eq!(e, config);
+            assert_eq!(*result, config.run_result_seeded(3));
+        }
+        assert_eq!(run_sweep_results(configs).len(), 3);
+    }
+
+    #[test]
+    fn test_aggregate_by() {
+        let result = |dims, n_clients, prob| {
+            let e = ExperimentConfig {
+                dims,
+                n_clients,
+                ..Default::default()
+            };
+            (e, prob)
+        };
+        let results = [
+            result(Dims::One, 10, 0.25),
+            result(Dims::Two, 10, 0.5),
+            result(Dims::One, 20, 0.75),
+            result(Dims::Two, 20, 1.0),
+            result(Dims::Two, 30, 0.0),
+        ];
+        let by_dims = aggregate_by(&results, |e| e.dims);
+        assert_eq!(by_dims.len(), 2);
+        assert_eq!(by_dims[&Dims::One], (0.5, 2));
+        assert_eq!(by_dims[&Dims::Two], (0.5, 3));
+
+        let by_clients = aggregate_by(&results, |e| e.n_clients);
+        assert_eq!(by_clients[&20], (0.875, 2));
+        assert_eq!(by_clients[&30], (0.0, 1));
+        assert!(aggregate_by(&[], |e| e.dims).is_empty());
+    }
+
+    #[test]
+    fn test_marginal_gains() {
+        let e = ExperimentConfig {
+            n: 4,
+            dims: Dims::Zero,
+            n_samples: 1,
+            ..Default::default()
+        };
+        let prob = |e: &ExperimentConfig| e.run_seeded(3);
+        let gains = e.marginal_gains_by(12, prob);
+        assert_eq!(gains.len(), 12);
+        // Nobody samples anything with no clients
+        let total = prob(&ExperimentConfig {
+            n_clients: 12,
+            ..e.clone()
+        });
+        assert!((gains.iter().sum::<f32>() - total).abs() < 1e-5);
+        assert!(e.marginal_gains(0).is_empty());
+    }
+
+    #[test]
+    fn test_full_recovery() {
+        let e = ExperimentConfig {
+            n: 4,
+            n_clients: 4,
+            n_samples: 4,
+            ..Default::default()
+        };
+        let full = ExperimentConfig {
+            full_recovery: true,
+            ..e.clone()
+        };
+        // Recovering everything is at least as hard as recovering one cell
+        assert!(full.run_seeded(16) <= e.run_seeded(16));
+        // The adversary hides the target from everyone, so nobody gets the whole grid
+        let censored = ExperimentConfig {
+            n_clients: 50,
+            percent_censored: 1.0,
+            ..full
+        };
+        assert_eq!(censored.run_seeded(16), 0.0);
+        let plenty = ExperimentConfig {
+            percent_censored: 0.0,
+            ..censored
+        };
+        assert_eq!(plenty.run_seeded(16), 1.0);
+    }
+
+    #[test]
+    fn test_rounding_mode() {
+        let e = ExperimentConfig {
+            n_clients: 50,
+            percent_censored: 0.01,
+            ..Default::default()
+        };
+        assert_eq!(e.n_censored(), 0);
+        let ceil = ExperimentConfig {
+            rounding_mode: RoundingMode::Ceil,
+            ..e.clone()
+        };
+        assert_eq!(ceil.n_censored(), 1);
+        let round = ExperimentConfig {
+            rounding_mode: RoundingMode::Round,
+            percent_censored: 0.03,
+            ..e.clone()
+        };
+        assert_eq!(round.n_censored(), 2);
+        let all = ExperimentConfig {
+            percent_censored: 1.0,
+            ..ceil
+        };
+        assert_eq!(all.n_censored(), 50);
+        assert_eq!(e.to_row(&result(0.5))[7], "Floor");
+    }
+
+    #[test]
+    fn test_n_censored_abs() {
+        for percent_censored in [0.0, 0.01, 0.5, 1.0] {
+            let e = ExperimentConfig {
+                n_clients: 50,
+                percent_censored,
+                rounding_mode: RoundingMode::Ceil,
+                ..Default::default()
+            };
+            for k in [0, 1, 17, 50] {
+                assert_eq!(e.clone().with_n_censored(k).n_censored(), k);
+            }
+        }
+
+        // The same trials as censoring the same number of clients by percent
+        let e = ExperimentConfig {
+            n: 8,
+            n_clients: 20,
+            percent_censored: 0.25,
+            rounding_mode: RoundingMode::Round,
+            ..Default::default()
+        };
+        let abs = ExperimentConfig {
+            percent_censored: 0.9,
+            ..e.clone()
+        }
+        .with_n_censored(5);
+        assert_eq!(abs.run_result_seeded(3), e.run_result_seeded(3));
+        let column = ExperimentConfig::header()
+            .iter()
+            .position(|h| *h == "n_censored_abs")
+            .unwrap();
+        assert_eq!(abs.to_row(&result(0.5))[column], "5");
+        assert_eq!(e.to_row(&result(0.5))[column], "");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't censor 21 of 20 clients")]
+    fn test_n_censored_abs_too_many() {
+        ExperimentConfig {
+            n_clients: 20,
+            ..Default::default()
+        }
+        .with_n_censored(21)
+        .run_seeded(3);
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let e = small_config();
+        let prob = e.run_seeded(21);
+        let defaults = ExperimentConfig {
+            row_threshold: Some(8),
+            col_threshold: Some(8),
+            ..small_config()
+        };
+        assert_eq!(defaults.run_seeded(21), prob);
+        // Needing more cells can only make reconstruction harder
+        let strict = ExperimentConfig {
+            row_threshold: Some(12),
+            col_threshold: Some(14),
+            ..small_config()
+        };
+        assert!(strict.run_seeded(21) <= prob);
+
+        let row = strict.to_row(&result(0.5));
+        let column = |name| ExperimentConfig::header().iter().position(|h| *h == name);
+        assert_eq!(row[column("row_threshold").unwrap()], "12");
+        assert_eq!(row[column("col_threshold").unwrap()], "14");
+        assert_eq!(e.to_row(&result(0.5))[column("row_threshold").unwrap()], "");
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(small_config().validate(), Ok(()));
+        let fields = |e: ExperimentConfig| -> Vec<&str> {
+            e.validate()
+                .unwrap_err()
+                .iter()
+                .map(|error| error.field)
+                .collect()
+        };
+        let with = |f: fn(&mut ExperimentConfig)| {
+            let mut e = small_config();
+            f(&mut e);
+            e
+        };
+        assert_eq!(fields(with(|e| e.n = 0)), ["n"]);
+        assert_eq!(fields(with(|e| e.cols = Some(0))), ["cols"]);
+        assert_eq!(fields(with(|e| e.extension = 0)), ["extension"]);
+        assert_eq!(fields(with(|e| e.n_samples = 0)), ["n_samples"]);
+        assert_eq!(
+            fields(with(|e| e.percent_censored = 1.3)),
+            ["percent_censored"]
+        );
+        assert_eq!(
+            fields(with(|e| e.share_fraction = -0.1)),
+            ["share_fraction"]
+        );
+        assert_eq!(
+            fields(with(|e| e.offline_fraction = f64::NAN)),
+            ["offline_fraction"]
+        );
+        assert_eq!(
+            fields(with(|e| e.honest_fraction = 2.0)),
+            ["honest_fraction"]
+        );
+        assert_eq!(fields(with(|e| e.error_rate = 1.5)), ["error_rate"]);
+        assert_eq!(
+            fields(with(|e| e.n_censored_abs = Some(21))),
+            ["n_censored_abs"]
+        );
+        // The 2d grid is 16x16
+        assert_eq!(
+            fields(with(|e| e.row_threshold = Some(17))),
+            ["row_threshold"]
+        );
+        assert_eq!(
+            fields(with(|e| e.col_threshold = Some(0))),
+            ["col_threshold"]
+        );
+        assert_eq!(
+            fields(with(|e| {
+                e.dims = Dims::One;
+                e.row_threshold = Some(4);
+            })),
+            ["row_threshold"]
+        );
+        assert_eq!(
+            fields(with(|e| {
+                e.dims = Dims::Three;
+                e.mask_strategy = MaskStrategy::WithholdRows { count: 9 };
+            })),
+            ["mask_strategy"]
+        );
+
+        // Boxes have to tile the grid of the chosen dims, which is 8x16 in 1d
+        let boxes = SampleStrategy::Box {
+            width: 16,
+            height: 8,
+        };
+        let e = small_config().with_sample_strategy(boxes);
+        assert_eq!(e.validate(), Ok(()));
+        let e = ExperimentConfig {
+            dims: Dims::One,
+            ..e
+        };
+        assert_eq!(fields(e), ["honest_strategy", "censor_strategy"]);
+        let errors = with(|e| {
+            e.censor_strategy = SampleStrategy::Box {
+                width: 32,
+                height: 2,
+            }
+        })
+        .validate()
+        .unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "censor_strategy: invalid strategy: 32x2 boxes don't evenly divide a 16x16 grid"
+        );
+
+        // Every violation is reported at once, in field order
+        let e = ExperimentConfig {
+            n_samples: 0,
+            percent_censored: 1.3,
+            censor_strategy: SampleStrategy::Box {
+                width: 3,
+                height: 3,
+            },
+            ..small_config()
+        };
+        let errors = e.validate().unwrap_err();
+        assert_eq!(
+            errors.iter().map(|error| error.field).collect::<Vec<_>>(),
+            ["n_samples", "percent_censored", "censor_strategy"]
+        );
+        assert_eq!(errors[1].message, "1.3 isn't between 0 and 1");
+        assert_eq!(e.try_run_result(), Err(Error::InvalidConfig(errors)));
+        assert!(small_config().try_run_result_seeded(3).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid config: n_samples: must be at least 1")]
+    fn test_run_invalid() {
+        ExperimentConfig {
+            n_samples: 0,
+            ..small_config()
+        }
+        .run_seeded(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "not supported in 1d grids")]
+    fn test_thresholds_unsupported() {
+        let e = ExperimentConfig {
+            dims: Dims::One,
+            row_threshold: Some(4),
+            ..small_config()
+        };
+        e.run_seeded(1);
+    }
+
+    #[test]
+    fn test_control_prob() {
+        // Without censorship the target is just another random cell
+        let uncensored = ExperimentConfig {
+            percent_censored: 0.0,
+            n_clients: 8,
+            ..small_config()
+        };
+        let res = uncensored.run_result_seeded(7);
+        assert!((res.prob - res.control_prob).abs() < 0.15, "{:?}", res);
+        let everything = ExperimentConfig {
+            n_samples: 200,
+            ..uncensored
+        }
+        .run_result_seeded(7);
+        assert_eq!((everything.prob, everything.control_prob), (1.0, 1.0));
+
+        // The attacked target fares worse than a typical cell
+        let res = small_config().run_result_seeded(7);
+        assert!(res.control_prob >= res.prob, "{:?}", res);
+        assert_eq!(res.prob, small_config().run_seeded(7));
+    }
+
+    #[test]
+    fn test_all_recoverable() {
+        let e = small_config();
+        let res = e.run_result_seeded(5);
+        assert_eq!(res.prob, e.run_seeded(5));
+        // Recovering everything recovers the target
+        assert!(res.all_recoverable <= res.prob);
+
+        let uncensored = ExperimentConfig {
+            percent_censored: 0.0,
+            n_samples: 200,
+            ..small_config()
+        };
+        let res = uncensored.run_result_seeded(5);
+        assert_eq!(res.all_recoverable, 1.0);
+        let row = uncensored.to_row(&res);
+        let column = |name| {
+            ExperimentConfig::header()
+                .iter()
+                .position(|h| *h == name)
+                .unwrap()
+        };
+        assert_eq!(row[column("all_recoverable")], "1.0000000000");
+        assert_eq!(row[column("successes")], row[column("trials")]);
+        assert_eq!(row.len(), ExperimentConfig::header().len());
+    }
+
+    #[test]
+    fn test_error_rate() {
+        let e = ExperimentConfig {
+            percent_censored: 0.0,
+            ..small_config()
+        };
+        assert_eq!(e.error_rate, 0.0);
+        assert!(e.run_seeded(8) > 0.0);
+        let all_wrong = ExperimentConfig {
+            error_rate: 1.0,
+            ..e
+        };
+        assert_eq!(all_wrong.run_seeded(8), 0.0);
+    }
+
+    #[test]
+    fn test_client_model() {
+        // Without coding, the target is recovered iff one of the 30 honest clients samples
+        // it, which happens with probability 1 - (63/64)^30, about 0.376
+        let e = ExperimentConfig {
+            n: 8,
+            dims: Dims::Zero,
+            n_clients: 40,
+            percent_censored: 0.25,
+            n_samples: 1,
+            ..Default::default()
+        };
+        let individual = ExperimentConfig {
+            client_model: ClientModel::Individual,
+            ..e.clone()
+        };
+        let mean = |e: &ExperimentConfig| (0..4).map(|seed| e.run_seeded(seed)).sum::<f32>() / 4.0;
+        let expected = 1.0 - (63.0f32 / 64.0).powi(30);
+        // 2000 trials each, so the standard error of each mean is about 0.011
+        for prob in [mean(&e), mean(&individual)] {
+            assert!((prob - expected).abs() < 0.05, "{}", prob);
+        }
+        let column = ExperimentConfig::header()
+            .iter()
+            .position(|h| *h == "client_model")
+            .unwrap();
+        assert_eq!(individual.to_row(&result(0.5))[column], "Individual");
+    }
+
+    #[test]
+    fn test_topology() {
+        let e = ExperimentConfig {
+            n: 4,
+            n_clients: 20,
+            percent_censored: 0.2,
+            n_samples: 3,
+            client_model: ClientModel::Individual,
+            ..Default::default()
+        };
+        // After one round over a complete graph, the full node has everything shared
+        let complete = ExperimentConfig {
+            topology: Some(Topology {
+                edge_prob: 1.0,
+                rounds: 1,
+                evaluator: Evaluator::FullNode,
+            }),
+            ..e.clone()
+        };
+        assert_eq!(complete.run_result_seeded(6), e.run_result_seeded(6));
+        // and nothing before it
+        let no_rounds = ExperimentConfig {
+            topology: Some(Topology {
+                edge_prob: 1.0,
+                rounds: 0,
+                evaluator: Evaluator::FullNode,
+            }),
+            ..e.clone()
+        };
+        assert_eq!(no_rounds.run_seeded(6), 0.0);
+
+        let header = ExperimentConfig::header();
+        let row = complete.to_row(&result(0.5));
+        let column = |name: &str| header.iter().position(|h| *h == name).unwrap();
+        assert_eq!(row[column("edge_prob")], "1");
+        assert_eq!(row[column("evaluator")], "FullNode");
+        assert_eq!(row[column("evaluator_clients")], "");
+        assert_eq!(e.to_row(&result(0.5))[column("gossip_rounds")], "");
+    }
+
+    #[test]
+    fn test_run_with_mask() {
+        let e = ExperimentConfig {
+            n: 4,
+            n_clients: 20,
+            percent_censored: 1.0,
+            n_samples: 10,
+            ..Default::default()
+        };
+        // Every client is censored, so nothing can be recovered if everything is withheld
+        let everything = Grid::new(8, 8);
+        assert_eq!(e.run_with_mask::<Grid2dErasure>(&everything, (1, 2)), 0.0);
+        // but 200 samples of 64 cells almost always recover the target if nothing is
+        let nothing = everything.not();
+        assert!(e.run_with_mask::<Grid2dErasure>(&nothing, (1, 2)) > 0.9);
+
+        // A hand built mask of the target's lines and the parity quadrant
+        let (mask, target) = Grid2dErasure::new_mask_at(e.shape(), (1, 2));
+        let prob = e.run_with_mask_seeded::<Grid2dErasure>(&mask, target, 3);
+        assert!(prob < 0.1, "{}", prob);
+        assert_eq!(
+            e.run_with_mask_seeded::<Grid2dErasure>(&mask, target, 3),
+            prob
+        );
+
+        // `run_seeded` is the same run with the mask it draws from its seed
+        let e = small_config();
+        let (mask, target) = e.new_mask::<Grid2dErasure>(&mut SmallRng::seed_from_u64(3));
+        assert_eq!(
+            e.run_with_mask_seeded::<Grid2dErasure>(&mask, target, 3),
+            e.run_seeded(3)
+        );
+    }
+
+    #[test]
+    fn test_accept_frac() {
+        let e = ExperimentConfig {
+            n: 4,
+            dims: Dims::Zero,
+            n_clients: 10,
+            percent_censored: 0.5,
+            n_samples: 3,
+            client_model: ClientModel::Individual,
+            ..Default::default()
+        };
+        // Nothing withheld, so every censored client accepts
+        let nothing_withheld = Grid::new(4, 4).not();
+        let res = e.result_with_mask::<GridRawErasure>(&nothing_withheld, &(0, 0), 3);
+        assert_eq!(res.accept_frac_mean, Some(1.0));
+        // Everything withheld, so none do
+        let res = e.result_with_mask::<GridRawErasure>(&Grid::new(4, 4), &(0, 0), 3);
+        assert_eq!(res.accept_frac_mean, Some(0.0));
+        let frac = e.run_result_seeded(3).accept_frac_mean.unwrap();
+        assert!((0.0..=1.0).contains(&frac));
+
+        // Censored clients sampled all at once can't be told apart
+        let aggregate = ExperimentConfig {
+            client_model: ClientModel::Aggregate,
+            ..e.clone()
+        };
+        assert_eq!(aggregate.run_result_seeded(3).accept_frac_mean, None);
+        let accept_column = ExperimentConfig::header()
+            .iter()
+            .position(|h| *h == "accept_frac_mean")
+            .unwrap();
+        assert_eq!(
+            aggregate.to_row(&aggregate.run_result_seeded(3))[accept_column],
+            ""
+        );
+        let uncensored = ExperimentConfig {
+            percent_censored: 0.0,
+            ..e
+        };
+        assert_eq!(uncensored.run_result_seeded(3).accept_frac_mean, None);
+    }
+
+    #[test]
+    fn test_single_pass() {
+        let e = ExperimentConfig {
+            n_samples: 20,
+            ..small_config()
+        };
+        let single_pass = ExperimentConfig {
+            single_pass: true,
+            ..e.clone()
+        };
+        // Every trial is the same, and one pass recovers no more than the fixpoint
+        assert!(single_pass.run_seeded(2) <= e.run_seeded(2));
+        // 1d grids only ever need one pass
+        let one_d = ExperimentConfig {
+            dims: Dims::One,
+            ..e
+        };
+        let one_d_single_pass = ExperimentConfig {
+            single_pass: true,
+            ..one_d.clone()
+        };
+        assert_eq!(one_d_single_pass.run_seeded(2), one_d.run_seeded(2));
+        let column = ExperimentConfig::header()
+            .iter()
+            .position(|h| *h == "single_pass")
+            .unwrap();
+        assert_eq!(single_pass.to_row(&result(0.5))[column], "true");
+    }
+
+    #[test]
+    fn test_compare_dims() {
+        let e = small_config();
+        let (one, two) = e.compare_dims();
+        assert!((0.0..=1.0).contains(&one) && (0.0..=1.0).contains(&two));
+        let one_d = ExperimentConfig {
+            dims: Dims::One,
+            ..small_config()
+        };
+        assert_eq!(
+            e.compare_dims_seeded(4),
+            (one_d.run_seeded(4), e.run_seeded(4))
+        );
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let boxes = SampleStrategy::Box {
+            width: 2,
+            height: 2,
+        };
+        let a = small_config().with_sample_strategy(boxes.clone());
+        let b = ExperimentConfig {
+            n: 8,
+            ..ExperimentConfig::default()
+        };
+        let b = ExperimentConfig {
+            dims: Dims::Two,
+            n_clients: 20,
+            percent_censored: 0.2,
+            ..b
+        }
+        .with_sample_strategy(boxes);
+        assert_eq!(a.cache_key(), b.cache_key());
+        // Only the box's height differs
+        let c = small_config().with_sample_strategy(SampleStrategy::Box {
+            width: 2,
+            height: 4,
+        });
+        assert_ne!(a.cache_key(), c.cache_key());
+        assert_ne!(small_config().cache_key(), a.cache_key());
+
+        let mut cache = HashMap::new();
+        cache.insert(a.cache_key(), 0.25);
+        assert_eq!(b.run_cached(&mut cache), 0.25, "b reuses a's result");
+        let prob = c.run_cached(&mut cache);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(c.run_cached(&mut cache), prob);
+    }
+
+    #[test]
+    fn test_random_index() {
+        // The control index is drawn the same way as the target, without building a mask
+        fn check<R: Reconstructable>(shape: Shape)
+        where
+            R::Index: PartialEq + std::fmt::Debug,
+        {
+            for seed in 0..20 {
+                let index = R::random_index(&mut SmallRng::seed_from_u64(seed), shape);
+                let (_, target) = R::new_mask(&mut SmallRng::seed_from_u64(seed), shape);
+                assert_eq!(index, target);
+            }
+        }
+        let shape = Shape::new(4, 6);
+        check::<GridRawErasure>(shape);
+        check::<Grid1dErasure>(shape);
+        check::<Grid1_5dErasure>(shape);
+        check::<Grid2dErasure>(shape);
+        check::<Grid3dErasure>(Shape::square(4));
+        check::<GridNdErasure<4>>(Shape::square(3));
+    }
+
+    #[test]
+    fn test_run_generic() {
+        // Running the grid dims would pick is the same as `run`
+        let e = small_config();
+        let result = e.run_generic_result_seeded::<Grid2dErasure>(3);
+        assert_eq!(result, e.run_result_seeded(3));
+        let row = e.to_row_for_scheme("custom", &result);
+        assert_eq!(row[0], "custom");
+        assert_eq!(row[1..], e.to_row(&result)[1..]);
+
+        // Checked against the grid that's run, not the one dims picks
+        let e = ExperimentConfig {
+            row_threshold: Some(4),
+            ..small_config()
+        };
+        assert!(e.validate().is_ok());
+        let errors = e.validate_for::<Grid1dErasure>().unwrap_err();
+        assert_eq!(errors[0].field, "row_threshold");
+    }
+
+    #[test]
+    fn test_header_matches_row() {
+        let e = small_config();
+        assert_eq!(
+            ExperimentConfig::header().len(),
+            e.to_row(&result(0.5)).len()
+        );
     }
 }
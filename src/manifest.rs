@@ -0,0 +1,143 @@
+use std::{fs, io, path::Path};
+
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::{entropy_seed, ExperimentConfig, RunResult, N_EXPERIMENTS};
+
+/// Everything needed to run a sweep again exactly: the configs, the seed each config's own
+/// seed is drawn from, and how many trials every config runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub seed: u64,
+    pub n_experiments: usize,
+    pub configs: Vec<ExperimentConfig>,
+}
+
+impl Manifest {
+    /// A manifest for `configs` with a fresh seed
+    pub fn new(configs: Vec<ExperimentConfig>) -> Self {
+        Self::with_seed(configs, entropy_seed())
+    }
+
+    pub fn with_seed(configs: Vec<ExperimentConfig>, seed: u64) -> Self {
+        Self {
+            seed,
+            n_experiments: N_EXPERIMENTS,
+            configs,
+        }
+    }
+
+    /// The seed each config is run from, drawn in order from `seed`
+    fn config_seeds(&self) -> Vec<u64> {
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        self.configs.iter().map(|_| rng.next_u64()).collect()
+    }
+
+    /// Runs every config in parallel (with the `parallel` feature), returning the results in
+    /// the same order as `configs`. Running the same manifest always gives the same results.
+    pub fn run(&self) -> Vec<RunResult> {
+        #[cfg(feature = "parallel")]
+        let configs = self.configs.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let configs = self.configs.iter();
+        configs
+            .zip(self.config_seeds())
+            .map(|(e, seed)| e.run_result_seeded(seed))
+            .collect()
+    }
+
+    /// Writes the manifest as JSON, e.g. next to the CSV of its results
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads a manifest written by `save`. Errors if it was made with a different number of
+    /// trials per config, since it can't be run the same way again.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let manifest: Manifest = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if manifest.n_experiments != N_EXPERIMENTS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the manifest runs {} trials per config, but this build runs {}",
+                    manifest.n_experiments, N_EXPERIMENTS
+                ),
+            ));
+        }
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dims, MaskStrategy, SampleStrategy};
+
+    #[test]
+    fn test_round_trip() {
+        let configs = vec![
+            ExperimentConfig {
+                n: 4,
+                dims: Dims::OneAndAHalf,
+                n_clients: 10,
+                percent_censored: 0.3,
+                ..Default::default()
+            },
+            ExperimentConfig {
+                n: 4,
+                cols: Some(2),
+                n_clients: 5,
+                mask_strategy: MaskStrategy::WithholdRows { count: 1 },
+                row_threshold: Some(3),
+                ..Default::default()
+            }
+            .with_sample_strategy(SampleStrategy::Box {
+                width: 2,
+                height: 2,
+            }),
+        ];
+        let manifest = Manifest::with_seed(configs.clone(), 11);
+        let path = std::env::temp_dir().join(format!("manifest-{}.json", std::process::id()));
+        manifest.save(&path).unwrap();
+        let loaded = Manifest::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, manifest);
+        assert_eq!(loaded.configs, configs);
+        assert_eq!(loaded.n_experiments, N_EXPERIMENTS);
+    }
+
+    #[test]
+    fn test_rerun() {
+        let configs = vec![
+            ExperimentConfig {
+                n: 4,
+                n_clients: 10,
+                percent_censored: 0.3,
+                ..Default::default()
+            };
+            3
+        ];
+        let manifest = Manifest::with_seed(configs, 3);
+        let results = manifest.run();
+        assert_eq!(results.len(), 3);
+        assert_eq!(manifest.run(), results);
+        assert_eq!(
+            results[1],
+            manifest.configs[1].run_result_seeded(manifest.config_seeds()[1])
+        );
+    }
+
+    #[test]
+    fn test_wrong_n_experiments() {
+        let mut manifest = Manifest::with_seed(vec![ExperimentConfig::default()], 1);
+        manifest.n_experiments += 1;
+        let path = std::env::temp_dir().join(format!("manifest-bad-{}.json", std::process::id()));
+        manifest.save(&path).unwrap();
+        let loaded = Manifest::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
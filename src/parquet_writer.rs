@@ -0,0 +1,95 @@
+use std::{fs::File, path::Path};
+
+use parquet::{arrow::ArrowWriter, errors::ParquetError};
+
+use crate::{Error, ExperimentConfig, ResultBatchBuilder, RunResult};
+
+/// How many rows go into each row group
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// Writes `results` to a Parquet file at `path`, with the same typed columns as
+/// `ResultBatchBuilder::schema`, so prob, the box sizes and the other numbers load as
+/// numbers rather than strings
+pub fn write_parquet(
+    path: impl AsRef<Path>,
+    results: &[(ExperimentConfig, RunResult)],
+) -> Result<(), Error> {
+    let file = File::create(path).map_err(ParquetError::from)?;
+    let mut writer = ArrowWriter::try_new(file, ResultBatchBuilder::schema(), None)?;
+    let mut builder = ResultBatchBuilder::new(ROW_GROUP_SIZE);
+    for (e, result) in results {
+        if let Some(batch) = builder.push(e, result) {
+            writer.write(&batch)?;
+        }
+    }
+    if let Some(batch) = builder.finish() {
+        writer.write(&batch)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+impl From<ParquetError> for Error {
+    fn from(e: ParquetError) -> Self {
+        Error::Parquet(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::AsArray,
+        datatypes::{Float64Type, UInt32Type},
+    };
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::{SampleStrategy, TrialStats};
+
+    #[test]
+    fn test_write_parquet() {
+        let results: Vec<(ExperimentConfig, RunResult)> = (0..3)
+            .map(|successes| {
+                let e = ExperimentConfig {
+                    n_clients: 10 * (successes + 1),
+                    ..Default::default()
+                }
+                .with_sample_strategy(SampleStrategy::Box {
+                    width: 2,
+                    height: 4,
+                });
+                let result = TrialStats {
+                    trials: 10,
+                    successes,
+                    ..Default::default()
+                }
+                .result();
+                (e, result)
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!("results-{}.parquet", std::process::id()));
+        write_parquet(&path, &results).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+        let missing = std::env::temp_dir()
+            .join("no-such-dir")
+            .join("results.parquet");
+        assert!(matches!(
+            write_parquet(missing, &results),
+            Err(Error::Parquet(_))
+        ));
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+
+        let batch = &batches[0];
+        assert_eq!(batch.schema(), ResultBatchBuilder::schema());
+        let prob = batch.column_by_name("prob").unwrap();
+        assert_eq!(prob.as_primitive::<Float64Type>().value(2), 0.2f32 as f64);
+        let box_height = batch.column_by_name("box_height").unwrap();
+        assert_eq!(box_height.as_primitive::<UInt32Type>().value(0), 4);
+    }
+}
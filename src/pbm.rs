@@ -0,0 +1,236 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::base_grid::Grid;
+
+/// The longest line a plain PBM file is allowed to have
+const PBM_LINE_LEN: usize = 70;
+
+/// `overlay_ppm` colors for a cell set in neither, only the first, only the second, and
+/// both grids
+const OVERLAY_COLORS: [[u8; 3]; 4] = [[0, 0, 0], [255, 0, 0], [0, 0, 255], [255, 255, 255]];
+
+#[derive(Debug)]
+pub enum GridError {
+    Io(io::Error),
+    /// The file isn't a valid plain (P1) PBM image
+    Pbm(String),
+}
+
+impl Display for GridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GridError::Io(e) => write!(f, "{}", e),
+            GridError::Pbm(e) => write!(f, "invalid PBM: {}", e),
+        }
+    }
+}
+
+impl Error for GridError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GridError::Io(e) => Some(e),
+            GridError::Pbm(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for GridError {
+    fn from(e: io::Error) -> Self {
+        GridError::Io(e)
+    }
+}
+
+impl Grid {
+    /// Reads a grid from a plain (P1) PBM image, where `1` is a set cell.
+    /// The image is `w` pixels wide and `h` high.
+    pub fn from_pbm(path: impl AsRef<Path>) -> Result<Grid, GridError> {
+        parse_pbm(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the grid as a plain (P1) PBM image, the inverse of `from_pbm`
+    pub fn to_pbm(&self, path: impl AsRef<Path>) -> Result<(), GridError> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "P1")?;
+        writeln!(file, "{} {}", self.w(), self.h())?;
+        for i in 0..self.h() {
+            let row: Vec<u8> = (0..self.w())
+                .map(|j| if self.get(i, j) { b'1' } else { b'0' })
+                .collect();
+            // Pixels don't need separating, but lines can't be longer than 70 characters
+            for line in row.chunks(PBM_LINE_LEN) {
+                file.write_all(line)?;
+                writeln!(file)?;
+            }
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Writes the grid as a binary (P5) PGM image, with set cells white (255) and the
+    /// rest black (0)
+    pub fn write_pgm(&self, path: impl AsRef<Path>) -> Result<(), GridError> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        write!(file, "P5\n{} {}\n255\n", self.w(), self.h())?;
+        for i in 0..self.h() {
+            let row: Vec<u8> = (0..self.w())
+                .map(|j| if self.get(i, j) { 255 } else { 0 })
+                .collect();
+            file.write_all(&row)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Writes a binary (P6) PPM image comparing this grid to `other`, e.g. a mask against
+    /// the samples taken. Cells set only here are red, only in `other` blue, in both
+    /// white, and in neither black.
+    pub fn overlay_ppm(&self, other: &Grid, path: impl AsRef<Path>) -> Result<(), GridError> {
+        assert_eq!(
+            (self.w(), self.h()),
+            (other.w(), other.h()),
+            "can't overlay grids of different sizes"
+        );
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        write!(file, "P6\n{} {}\n255\n", self.w(), self.h())?;
+        for i in 0..self.h() {
+            let row: Vec<u8> = (0..self.w())
+                .flat_map(|j| {
+                    let color = self.get(i, j) as usize + 2 * other.get(i, j) as usize;
+                    OVERLAY_COLORS[color]
+                })
+                .collect();
+            file.write_all(&row)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Pops the next whitespace separated token off the front of `rest`
+fn next_token<'a>(rest: &mut &'a str) -> &'a str {
+    let trimmed = rest.trim_start();
+    let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    let (token, tail) = trimmed.split_at(end);
+    *rest = tail;
+    token
+}
+
+fn parse_pbm(text: &str) -> Result<Grid, GridError> {
+    // Comments run from a `#` to the end of the line
+    let body = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut rest = body.as_str();
+
+    let magic = next_token(&mut rest);
+    if magic != "P1" {
+        return Err(GridError::Pbm(format!(
+            "expected a plain PBM (P1), found {:?}",
+            magic
+        )));
+    }
+    let mut dimension = |name: &str| {
+        let token = next_token(&mut rest);
+        token
+            .parse::<usize>()
+            .map_err(|_| GridError::Pbm(format!("bad {} {:?}", name, token)))
+    };
+    let w = dimension("width")?;
+    let h = dimension("height")?;
+
+    // Pixels may or may not be separated by whitespace
+    let pixels: Vec<char> = rest.chars().filter(|c| !c.is_whitespace()).collect();
+    if pixels.len() != w * h {
+        return Err(GridError::Pbm(format!(
+            "a {}x{} image needs {} pixels, found {}",
+            w,
+            h,
+            w * h,
+            pixels.len()
+        )));
+    }
+    let mut grid = Grid::new(w, h);
+    for (ind, pixel) in pixels.into_iter().enumerate() {
+        match pixel {
+            '0' => {}
+            '1' => grid.set(ind / w, ind % w, true),
+            _ => return Err(GridError::Pbm(format!("bad pixel {:?}", pixel))),
+        }
+    }
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        // Rows longer than a PBM line have to be wrapped
+        let g = Grid::from_indices(75, 3, [(0, 0), (0, 74), (1, 70), (2, 3)]).unwrap();
+        let path = std::env::temp_dir().join(format!("grid-{}.pbm", std::process::id()));
+        g.to_pbm(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.lines().all(|line| line.len() <= PBM_LINE_LEN));
+        let back = Grid::from_pbm(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(back, g);
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("grid-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_write_pgm() {
+        let g = Grid::from_indices(3, 2, [(0, 1), (1, 2)]).unwrap();
+        let path = temp_path("write.pgm");
+        g.write_pgm(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let header = b"P5\n3 2\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(&bytes[header.len()..], [0, 255, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_overlay_ppm() {
+        let mask = Grid::from_indices(2, 2, [(0, 0), (0, 1)]).unwrap();
+        let samples = Grid::from_indices(2, 2, [(0, 1), (1, 0)]).unwrap();
+        let path = temp_path("overlay.ppm");
+        mask.overlay_ppm(&samples, &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let header = b"P6\n2 2\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let pixels: Vec<&[u8]> = bytes[header.len()..].chunks(3).collect();
+        assert_eq!(
+            pixels,
+            [[255, 0, 0], [255, 255, 255], [0, 0, 255], [0, 0, 0]]
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let t = true;
+        let f = false;
+        let g = parse_pbm("P1\n# a comment\n3 2 # another\n0 1 0\n001\n").unwrap();
+        assert_eq!(g, Grid::from_bool_grid([[f, t, f], [f, f, t]]));
+        assert!(parse_pbm("P4\n3 2\n010001").is_err());
+        assert!(parse_pbm("P1\n3\n010001").is_err());
+        assert!(parse_pbm("P1\n3 2\n01000").is_err());
+        assert!(parse_pbm("P1\n3 2\n010002").is_err());
+        assert!(matches!(
+            Grid::from_pbm("/nonexistent/grid.pbm"),
+            Err(GridError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{error::Error, path::Path};
+
+use plotters::{
+    coord::Shift,
+    prelude::{
+        BitMapBackend, ChartBuilder, DrawingArea, DrawingAreaErrorKind, DrawingBackend,
+        IntoDrawingArea, Rectangle, Text,
+    },
+    style::{Color, RGBColor, WHITE},
+};
+
+use crate::{ExperimentConfig, RunResult};
+
+/// A numeric config field to lay heatmaps out by, see `draw_heatmaps`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlotField {
+    N,
+    Extension,
+    NClients,
+    PercentCensored,
+    NSamples,
+    ShareFraction,
+    OfflineFraction,
+    HonestFraction,
+    ErrorRate,
+}
+
+impl PlotField {
+    /// The field's name, as in `ExperimentConfig::header`
+    pub fn name(self) -> &'static str {
+        match self {
+            PlotField::N => "n",
+            PlotField::Extension => "extension",
+            PlotField::NClients => "n_clients",
+            PlotField::PercentCensored => "percent_censored",
+            PlotField::NSamples => "n_samples",
+            PlotField::ShareFraction => "share_fraction",
+            PlotField::OfflineFraction => "offline_fraction",
+            PlotField::HonestFraction => "honest_fraction",
+            PlotField::ErrorRate => "error_rate",
+        }
+    }
+
+    fn value(self, e: &ExperimentConfig) -> f64 {
+        match self {
+            PlotField::N => e.n as f64,
+            PlotField::Extension => e.extension as f64,
+            PlotField::NClients => e.n_clients as f64,
+            PlotField::PercentCensored => e.percent_censored,
+            PlotField::NSamples => e.n_samples as f64,
+            PlotField::ShareFraction => e.share_fraction,
+            PlotField::OfflineFraction => e.offline_fraction,
+            PlotField::HonestFraction => e.honest_fraction,
+            PlotField::ErrorRate => e.error_rate,
+        }
+    }
+
+    /// Every value the field takes in `results`, in increasing order
+    fn values(self, results: &[(ExperimentConfig, RunResult)]) -> Vec<f64> {
+        let mut values: Vec<f64> = results.iter().map(|(e, _)| self.value(e)).collect();
+        values.sort_by(f64::total_cmp);
+        values.dedup();
+        values
+    }
+}
+
+/// Each panel is this many pixels square
+pub const PANEL_SIZE: u32 = 400;
+const COLORBAR_WIDTH: u32 = 100;
+const TITLE_HEIGHT: u32 = 30;
+const MARGIN: u32 = 10;
+const LABEL_AREA: u32 = 50;
+
+/// How big `draw_heatmaps` draws `facets` panels and the colorbar
+pub fn heatmap_size(facets: usize) -> (u32, u32) {
+    (PANEL_SIZE * facets as u32 + COLORBAR_WIDTH, PANEL_SIZE)
+}
+
+/// The color of a probability, running from purple at 0 through teal to yellow at 1
+pub fn prob_color(prob: f64) -> RGBColor {
+    const STOPS: [(u8, u8, u8); 3] = [(68, 1, 84), (33, 145, 140), (253, 231, 37)];
+    let x = prob.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let k = (x.floor() as usize).min(STOPS.len() - 2);
+    let t = x - k as f64;
+    let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    let (a, b) = (STOPS[k], STOPS[k + 1]);
+    RGBColor(lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+}
+
+/// Labels the cell whose center is at `v` with its value, and nothing in between cells
+fn cell_label(values: &[f64], v: f64) -> String {
+    match values.get(v.round() as usize) {
+        Some(value) if (v - v.round()).abs() < 1e-6 => value.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Draws `prob` as a heatmap over `x` and `y`, with a panel for every value of `facet`
+/// side by side and a colorbar on the right. Cells no config was run for are left blank,
+/// and if several configs land in the same cell the last one is drawn. `area` should be
+/// `heatmap_size` big.
+pub fn draw_heatmaps<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    results: &[(ExperimentConfig, RunResult)],
+    x: PlotField,
+    y: PlotField,
+    facet: PlotField,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    area.fill(&WHITE)?;
+    let facets = facet.values(results);
+    if facets.is_empty() {
+        return Ok(());
+    }
+    let (xs, ys) = (x.values(results), y.values(results));
+    let (panels, colorbar) = area.split_horizontally(PANEL_SIZE * facets.len() as u32);
+
+    for (panel, &facet_value) in panels.split_evenly((1, facets.len())).iter().zip(&facets) {
+        let (title, plot) = panel.split_vertically(TITLE_HEIGHT);
+        title.draw(&Text::new(
+            format!("{} = {}", facet.name(), facet_value),
+            ((LABEL_AREA + MARGIN) as i32, MARGIN as i32),
+            ("sans-serif", 16),
+        ))?;
+        // Cell (i, j) is centered on (i, j)
+        let mut chart = ChartBuilder::on(&plot)
+            .margin(MARGIN)
+            .x_label_area_size(LABEL_AREA)
+            .y_label_area_size(LABEL_AREA)
+            .build_cartesian_2d(-0.5..xs.len() as f64 - 0.5, -0.5..ys.len() as f64 - 0.5)?;
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_labels(xs.len())
+            .y_labels(ys.len())
+            .x_label_formatter(&|&v| cell_label(&xs, v))
+            .y_label_formatter(&|&v| cell_label(&ys, v))
+            .x_desc(x.name())
+            .y_desc(y.name())
+            .draw()?;
+        let cells = results
+            .iter()
+            .filter(|(e, _)| facet.value(e) == facet_value)
+            .map(|(e, result)| {
+                let i = xs.iter().position(|&v| v == x.value(e)).unwrap() as f64;
+                let j = ys.iter().position(|&v| v == y.value(e)).unwrap() as f64;
+                Rectangle::new(
+                    [(i - 0.5, j - 0.5), (i + 0.5, j + 0.5)],
+                    prob_color(result.prob as f64).filled(),
+                )
+            });
+        chart.draw_series(cells)?;
+    }
+
+    let (_, colorbar) = colorbar.split_vertically(TITLE_HEIGHT);
+    let mut chart = ChartBuilder::on(&colorbar)
+        .margin(MARGIN)
+        .x_label_area_size(LABEL_AREA)
+        .right_y_label_area_size(LABEL_AREA)
+        .build_cartesian_2d(0.0..1.0, 0.0..1.0)?;
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .disable_x_axis()
+        .y_desc("prob")
+        .draw()?;
+    const STEPS: usize = 100;
+    chart.draw_series((0..STEPS).map(|k| {
+        let (lo, hi) = (k as f64 / STEPS as f64, (k + 1) as f64 / STEPS as f64);
+        Rectangle::new([(0.0, lo), (1.0, hi)], prob_color(lo).filled())
+    }))?;
+    area.present()
+}
+
+/// Writes `draw_heatmaps` to a PNG at `path`
+pub fn write_heatmaps_png(
+    path: impl AsRef<Path>,
+    results: &[(ExperimentConfig, RunResult)],
+    x: PlotField,
+    y: PlotField,
+    facet: PlotField,
+) -> Result<(), Box<dyn Error>> {
+    let size = heatmap_size(facet.values(results).len());
+    let area = BitMapBackend::new(path.as_ref(), size).into_drawing_area();
+    draw_heatmaps(&area, results, x, y, facet)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrialStats;
+
+    fn result(successes: usize) -> RunResult {
+        TrialStats {
+            trials: 1,
+            successes,
+            ..Default::default()
+        }
+        .result()
+    }
+
+    #[test]
+    fn test_draw_heatmaps() {
+        // A 3x3 grid per censorship level, everything succeeds without censorship and
+        // nothing with it. The middle of the uncensored grid wasn't run.
+        let mut results = Vec::new();
+        for (percent_censored, successes) in [(0.0, 1), (0.5, 0)] {
+            for n_clients in [10, 20, 30] {
+                for n_samples in [1, 2, 3] {
+                    if (percent_censored, n_clients, n_samples) == (0.0, 20, 2) {
+                        continue;
+                    }
+                    let e = ExperimentConfig {
+                        n_clients,
+                        n_samples,
+                        percent_censored,
+                        ..Default::default()
+                    };
+                    results.push((e, result(successes)));
+                }
+            }
+        }
+
+        let (w, h) = heatmap_size(2);
+        assert_eq!((w, h), (2 * PANEL_SIZE + COLORBAR_WIDTH, PANEL_SIZE));
+        let mut buf = vec![0; (w * h * 3) as usize];
+        {
+            let area = BitMapBackend::with_buffer(&mut buf, (w, h)).into_drawing_area();
+            draw_heatmaps(
+                &area,
+                &results,
+                PlotField::NClients,
+                PlotField::NSamples,
+                PlotField::PercentCensored,
+            )
+            .unwrap();
+        }
+        let pixel = |x: u32, y: u32| {
+            let k = ((y * w + x) * 3) as usize;
+            RGBColor(buf[k], buf[k + 1], buf[k + 2])
+        };
+
+        // The middle of each panel's plotting area, and how big a cell is
+        let plot_w = PANEL_SIZE - 2 * MARGIN - LABEL_AREA;
+        let plot_h = PANEL_SIZE - TITLE_HEIGHT - 2 * MARGIN - LABEL_AREA;
+        let center_x = MARGIN + LABEL_AREA + plot_w / 2;
+        let center_y = TITLE_HEIGHT + MARGIN + plot_h / 2;
+        let (cell_w, cell_h) = (plot_w / 3, plot_h / 3);
+        assert_eq!(pixel(center_x, center_y), WHITE, "cells not run are blank");
+        assert_eq!(pixel(center_x + cell_w, center_y), prob_color(1.0));
+        assert_eq!(pixel(center_x, center_y - cell_h), prob_color(1.0));
+        assert_eq!(pixel(PANEL_SIZE + center_x, center_y), prob_color(0.0));
+        assert_eq!(
+            pixel(PANEL_SIZE + center_x - cell_w, center_y + cell_h),
+            prob_color(0.0)
+        );
+    }
+
+    #[test]
+    fn test_prob_color() {
+        assert_eq!(prob_color(0.0), RGBColor(68, 1, 84));
+        assert_eq!(prob_color(0.5), RGBColor(33, 145, 140));
+        assert_eq!(prob_color(1.0), RGBColor(253, 231, 37));
+        assert_eq!(prob_color(2.0), prob_color(1.0));
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use rand::{rngs::SmallRng, SeedableRng};
+
+use crate::{entropy_seed, Dims, ExperimentConfig, Grid2dErasure, N_EXPERIMENTS};
+
+/// The `availability_profile` of the grid a failed trial ended up with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureProfile {
+    /// Which of the experiment's trials failed
+    pub trial: usize,
+    /// How many cells each column had
+    pub cols: Vec<usize>,
+    /// How many cells each row had
+    pub rows: Vec<usize>,
+}
+
+impl ExperimentConfig {
+    /// Runs the same trials as `run` and returns the profile of every one that failed, to
+    /// see which rows and columns were starved. Only 2d grids have profiles, so this errors
+    /// for other `dims`.
+    pub fn failure_profiles(&self) -> Result<Vec<FailureProfile>, String> {
+        self.failure_profiles_seeded(entropy_seed())
+    }
+
+    /// Same as `failure_profiles`, but all randomness is derived from `seed`, so the failed
+    /// trials are exactly the ones `run_seeded(seed)` counts
+    pub fn failure_profiles_seeded(&self, seed: u64) -> Result<Vec<FailureProfile>, String> {
+        if self.dims != Dims::Two {
+            return Err(format!(
+                "only 2d grids have availability profiles, not {:?}",
+                self.dims
+            ));
+        }
+        let (mask, target) = self.new_mask::<Grid2dErasure>(&mut SmallRng::seed_from_u64(seed));
+        let profiles = (0..N_EXPERIMENTS)
+            .filter_map(|trial| {
+                let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(trial as u64 + 1));
+                let res = self.trial_grid::<Grid2dErasure>(&mut rng, &mask);
+                if self.succeeded(&res, &target) {
+                    return None;
+                }
+                let (cols, rows) = res.availability_profile();
+                Some(FailureProfile { trial, cols, rows })
+            })
+            .collect();
+        Ok(profiles)
+    }
+
+    /// The columns of `write_profiles_csv`: `header` with the trial, whether the count is
+    /// for a `col` or a `row`, its index and the count in place of the results
+    pub fn profile_header() -> Vec<&'static str> {
+        let header = Self::header();
+        let mut columns = header[..header.len() - Self::RESULT_COLUMNS].to_vec();
+        columns.extend(["trial", "axis", "index", "count"]);
+        columns
+    }
+}
+
+/// Writes each config's profiles from `failure_profiles` in long format, one row per
+/// config, failed trial and column or row
+pub fn write_profiles_csv(
+    path: impl AsRef<Path>,
+    profiles: &[(ExperimentConfig, Vec<FailureProfile>)],
+) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(ExperimentConfig::profile_header())?;
+    for (e, profiles) in profiles {
+        let config = e.config_row();
+        for profile in profiles {
+            for (axis, counts) in [("col", &profile.cols), ("row", &profile.rows)] {
+                for (index, count) in counts.iter().enumerate() {
+                    let mut row = config.clone();
+                    row.push(profile.trial.to_string());
+                    row.push(axis.to_string());
+                    row.push(index.to_string());
+                    row.push(count.to_string());
+                    writer.write_record(row)?;
+                }
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ExperimentConfig {
+        ExperimentConfig {
+            n: 4,
+            n_clients: 10,
+            percent_censored: 0.5,
+            n_samples: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_failure_profiles() {
+        let e = config();
+        let profiles = e.failure_profiles_seeded(6).unwrap();
+        let stats = e.run_stats_seeded(6);
+        assert_eq!(profiles.len(), stats.trials - stats.successes);
+        for profile in &profiles {
+            assert_eq!((profile.cols.len(), profile.rows.len()), (8, 8));
+            assert_eq!(
+                profile.cols.iter().sum::<usize>(),
+                profile.rows.iter().sum::<usize>()
+            );
+        }
+
+        let one_d = ExperimentConfig {
+            dims: Dims::One,
+            ..e
+        };
+        assert!(one_d.failure_profiles_seeded(6).is_err());
+    }
+
+    #[test]
+    fn test_write_profiles_csv() {
+        let e = config();
+        let profile = FailureProfile {
+            trial: 3,
+            cols: vec![1, 2],
+            rows: vec![3, 0, 0],
+        };
+        let path = std::env::temp_dir().join(format!("profiles-{}.csv", std::process::id()));
+        write_profiles_csv(&path, &[(e, vec![profile])]).unwrap();
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let header = reader.headers().unwrap().clone();
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(header.len(), ExperimentConfig::profile_header().len());
+        assert_eq!(rows.len(), 5);
+        let tail = |row: &csv::StringRecord| -> Vec<String> {
+            row.iter().skip(row.len() - 4).map(String::from).collect()
+        };
+        assert_eq!(tail(&rows[0]), ["3", "col", "0", "1"]);
+        assert_eq!(tail(&rows[2]), ["3", "row", "0", "3"]);
+    }
+}
@@ -0,0 +1,128 @@
+use std::str::FromStr;
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{Error, ExperimentConfig, RunResult};
+
+/// An `ExperimentConfig` for Python. Every field is a keyword argument of the constructor,
+/// with the enums given as the strings their `FromStr` impls parse, and `strategy` sets
+/// both `honest_strategy` and `censor_strategy`. `topology` isn't supported.
+#[pyclass(name = "ExperimentConfig")]
+#[derive(Clone)]
+pub struct PyExperimentConfig {
+    config: ExperimentConfig,
+}
+
+fn parse<T: FromStr<Err = String>>(value: &PyAny) -> PyResult<T> {
+    value
+        .extract::<&str>()?
+        .parse()
+        .map_err(PyValueError::new_err)
+}
+
+#[pymethods]
+impl PyExperimentConfig {
+    #[new]
+    #[pyo3(signature = (**kwargs))]
+    fn new(kwargs: Option<&PyDict>) -> PyResult<Self> {
+        let mut config = ExperimentConfig::default();
+        for (key, value) in kwargs.into_iter().flatten() {
+            match key.extract::<&str>()? {
+                "n" => config.n = value.extract()?,
+                "cols" => config.cols = value.extract()?,
+                "extension" => config.extension = value.extract()?,
+                "dims" => config.dims = parse(value)?,
+                "n_clients" => config.n_clients = value.extract()?,
+                "percent_censored" => config.percent_censored = value.extract()?,
+                "n_censored_abs" => config.n_censored_abs = value.extract()?,
+                "rounding_mode" => config.rounding_mode = parse(value)?,
+                "n_samples" => config.n_samples = value.extract()?,
+                "strategy" => config = config.with_sample_strategy(parse(value)?),
+                "honest_strategy" => config.honest_strategy = parse(value)?,
+                "censor_strategy" => config.censor_strategy = parse(value)?,
+                "share_fraction" => config.share_fraction = value.extract()?,
+                "offline_fraction" => config.offline_fraction = value.extract()?,
+                "mask_strategy" => config.mask_strategy = parse(value)?,
+                "per_client_mask" => config.per_client_mask = value.extract()?,
+                "honest_fraction" => config.honest_fraction = value.extract()?,
+                "full_recovery" => config.full_recovery = value.extract()?,
+                "row_threshold" => config.row_threshold = value.extract()?,
+                "col_threshold" => config.col_threshold = value.extract()?,
+                "error_rate" => config.error_rate = value.extract()?,
+                "client_model" => config.client_model = parse(value)?,
+                "single_pass" => config.single_pass = value.extract()?,
+                key => {
+                    return Err(PyValueError::new_err(format!(
+                        "unknown ExperimentConfig field {:?}",
+                        key
+                    )))
+                }
+            }
+        }
+        Ok(Self { config })
+    }
+
+    /// Runs the experiment, see `ExperimentConfig::try_run_result`, without holding the GIL.
+    /// Raises `ValueError` if the config is invalid.
+    fn run(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let config = &self.config;
+        let result = py
+            .allow_threads(|| config.try_run_result())
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        result_dict(py, &result)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.config)
+    }
+}
+
+/// The fields of `result` as a dict
+fn result_dict(py: Python<'_>, result: &RunResult) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("prob", result.prob)?;
+    dict.set_item("successes", result.successes)?;
+    dict.set_item("trials", result.trials)?;
+    dict.set_item("prob_std_err", result.prob_std_err())?;
+    dict.set_item("prob_interval", result.prob_interval())?;
+    dict.set_item("all_recoverable", result.all_recoverable)?;
+    dict.set_item("control_prob", result.control_prob)?;
+    dict.set_item("accept_frac_mean", result.accept_frac_mean)?;
+    dict.set_item("accept_frac_std", result.accept_frac_std)?;
+    Ok(dict.into())
+}
+
+/// Runs every config on a pool of `n_threads` threads without holding the GIL, and
+/// returns their results in the same order. Raises `ValueError` before running anything
+/// if any config is invalid.
+#[pyfunction]
+fn run_sweep(
+    py: Python<'_>,
+    configs: Vec<PyExperimentConfig>,
+    n_threads: usize,
+) -> PyResult<Vec<PyObject>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(n_threads)
+        .build()
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    for c in &configs {
+        c.config
+            .validate()
+            .map_err(|errors| PyValueError::new_err(Error::InvalidConfig(errors).to_string()))?;
+    }
+    let results: Vec<RunResult> = py.allow_threads(|| {
+        pool.install(|| configs.par_iter().map(|c| c.config.run_result()).collect())
+    });
+    results
+        .iter()
+        .map(|result| result_dict(py, result))
+        .collect()
+}
+
+#[pymodule]
+fn avail_lc_sim(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyExperimentConfig>()?;
+    m.add_function(wrap_pyfunction!(run_sweep, m)?)?;
+    Ok(())
+}
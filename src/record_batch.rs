@@ -0,0 +1,415 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use arrow::{
+    array::{ArrayRef, BooleanBuilder, Float64Builder, StringBuilder, UInt32Builder},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    error::ArrowError,
+    ipc::writer::FileWriter,
+    record_batch::RecordBatch,
+};
+
+use crate::{Evaluator, ExperimentConfig, MaskStrategy, RunResult};
+
+/// The type of each column of `ResultBatchBuilder::schema`
+#[derive(Clone, Copy)]
+enum Kind {
+    U32,
+    F64,
+    Utf8,
+    Bool,
+}
+
+/// The columns of every batch, in order. They have the same names and hold the same values
+/// as the CSV columns of `ExperimentConfig::header`, except that nothing is formatted:
+/// counts are `u32`, fractions `f64`, flags `bool`, and names such as `strategy` `Utf8`.
+/// Columns that are blank in the CSV are null.
+const COLUMNS: [(&str, Kind, bool); 41] = [
+    ("dims", Kind::Utf8, false),
+    ("n", Kind::U32, false),
+    ("cols", Kind::U32, false),
+    ("extension", Kind::U32, false),
+    ("n_clients", Kind::U32, false),
+    ("percent_censored", Kind::F64, false),
+    ("n_censored_abs", Kind::U32, true),
+    ("rounding_mode", Kind::Utf8, false),
+    ("n_samples", Kind::U32, false),
+    ("strategy", Kind::Utf8, false),
+    ("box_width", Kind::U32, false),
+    ("box_height", Kind::U32, false),
+    ("censor_strategy", Kind::Utf8, false),
+    ("censor_box_width", Kind::U32, false),
+    ("censor_box_height", Kind::U32, false),
+    ("share_fraction", Kind::F64, false),
+    ("offline_fraction", Kind::F64, false),
+    ("mask", Kind::Utf8, false),
+    ("withheld_rows", Kind::U32, false),
+    ("per_client_mask", Kind::Bool, false),
+    ("honest_fraction", Kind::F64, false),
+    ("full_recovery", Kind::Bool, false),
+    ("row_threshold", Kind::U32, true),
+    ("col_threshold", Kind::U32, true),
+    ("error_rate", Kind::F64, false),
+    ("client_model", Kind::Utf8, false),
+    ("edge_prob", Kind::F64, true),
+    ("gossip_rounds", Kind::U32, true),
+    ("evaluator", Kind::Utf8, true),
+    ("evaluator_clients", Kind::U32, true),
+    ("single_pass", Kind::Bool, false),
+    ("prob", Kind::F64, false),
+    ("all_recoverable", Kind::F64, false),
+    ("accept_frac_mean", Kind::F64, true),
+    ("successes", Kind::U32, false),
+    ("trials", Kind::U32, false),
+    ("prob_ci_low", Kind::F64, false),
+    ("prob_ci_high", Kind::F64, false),
+    ("control_prob", Kind::F64, false),
+    ("prob_std_err", Kind::F64, false),
+    ("accept_frac_std", Kind::F64, true),
+];
+
+/// One cell of a row, before it's appended to its column
+enum Value {
+    U32(Option<u32>),
+    F64(Option<f64>),
+    Utf8(Option<String>),
+    Bool(bool),
+}
+
+fn u32(x: usize) -> Value {
+    Value::U32(Some(x.try_into().expect("counts fit in a u32 column")))
+}
+
+fn f64(x: f64) -> Value {
+    Value::F64(Some(x))
+}
+
+fn utf8(x: impl ToString) -> Value {
+    Value::Utf8(Some(x.to_string()))
+}
+
+/// The row of `COLUMNS` for `e` and `result`
+fn row(e: &ExperimentConfig, result: &RunResult) -> Vec<Value> {
+    let (box_width, box_height) = e.sample_size(&e.honest_strategy);
+    let (censor_box_width, censor_box_height) = e.sample_size(&e.censor_strategy);
+    let withheld_rows = match e.mask_strategy {
+        MaskStrategy::WithholdRows { count } => count,
+        MaskStrategy::Block => 0,
+    };
+    let threshold = |t: Option<usize>| Value::U32(t.map(|t| t as u32));
+    let topology = e.topology.as_ref();
+    let (prob_ci_low, prob_ci_high) = result.prob_interval();
+    vec![
+        utf8(&e.dims),
+        u32(e.n),
+        u32(e.shape().cols),
+        u32(e.extension),
+        u32(e.n_clients),
+        f64(e.percent_censored),
+        Value::U32(e.n_censored_abs.map(|n| n as u32)),
+        utf8(&e.rounding_mode),
+        u32(e.n_samples),
+        utf8(&e.honest_strategy),
+        u32(box_width),
+        u32(box_height),
+        utf8(&e.censor_strategy),
+        u32(censor_box_width),
+        u32(censor_box_height),
+        f64(e.share_fraction),
+        f64(e.offline_fraction),
+        utf8(&e.mask_strategy),
+        u32(withheld_rows),
+        Value::Bool(e.per_client_mask),
+        f64(e.honest_fraction),
+        Value::Bool(e.full_recovery),
+        threshold(e.row_threshold),
+        threshold(e.col_threshold),
+        f64(e.error_rate),
+        utf8(&e.client_model),
+        Value::F64(topology.map(|t| t.edge_prob)),
+        Value::U32(topology.map(|t| t.rounds as u32)),
+        Value::Utf8(topology.map(|t| t.evaluator.to_string())),
+        Value::U32(topology.and_then(|t| match t.evaluator {
+            Evaluator::Clients { count } => Some(count as u32),
+            Evaluator::FullNode => None,
+        })),
+        Value::Bool(e.single_pass),
+        f64(result.prob as f64),
+        f64(result.all_recoverable as f64),
+        Value::F64(result.accept_frac_mean.map(|x| x as f64)),
+        u32(result.successes),
+        u32(result.trials),
+        f64(prob_ci_low as f64),
+        f64(prob_ci_high as f64),
+        f64(result.control_prob as f64),
+        f64(result.prob_std_err() as f64),
+        Value::F64(result.accept_frac_std.map(|x| x as f64)),
+    ]
+}
+
+enum ColumnBuilder {
+    U32(UInt32Builder),
+    F64(Float64Builder),
+    Utf8(StringBuilder),
+    Bool(BooleanBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(kind: Kind) -> Self {
+        match kind {
+            Kind::U32 => ColumnBuilder::U32(UInt32Builder::new()),
+            Kind::F64 => ColumnBuilder::F64(Float64Builder::new()),
+            Kind::Utf8 => ColumnBuilder::Utf8(StringBuilder::new()),
+            Kind::Bool => ColumnBuilder::Bool(BooleanBuilder::new()),
+        }
+    }
+
+    fn append(&mut self, value: Value) {
+        match (self, value) {
+            (ColumnBuilder::U32(b), Value::U32(x)) => b.append_option(x),
+            (ColumnBuilder::F64(b), Value::F64(x)) => b.append_option(x),
+            (ColumnBuilder::Utf8(b), Value::Utf8(x)) => b.append_option(x),
+            (ColumnBuilder::Bool(b), Value::Bool(x)) => b.append_value(x),
+            _ => unreachable!("a row's values are in the order of COLUMNS"),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::U32(b) => Arc::new(b.finish()),
+            ColumnBuilder::F64(b) => Arc::new(b.finish()),
+            ColumnBuilder::Utf8(b) => Arc::new(b.finish()),
+            ColumnBuilder::Bool(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+/// Collects `(config, result)` pairs into Arrow `RecordBatch`es of `batch_size` rows, e.g.
+/// to hand to Polars without going through a CSV. See `COLUMNS` for the schema.
+pub struct ResultBatchBuilder {
+    batch_size: usize,
+    rows: usize,
+    columns: Vec<ColumnBuilder>,
+}
+
+impl ResultBatchBuilder {
+    /// Panics if `batch_size` is 0
+    pub fn new(batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batches need at least one row");
+        Self {
+            batch_size,
+            rows: 0,
+            columns: COLUMNS
+                .iter()
+                .map(|&(_, kind, _)| ColumnBuilder::new(kind))
+                .collect(),
+        }
+    }
+
+    /// The schema of every batch, which only changes when `ExperimentConfig::header` does
+    pub fn schema() -> SchemaRef {
+        let fields: Vec<Field> = COLUMNS
+            .iter()
+            .map(|&(name, kind, nullable)| {
+                let data_type = match kind {
+                    Kind::U32 => DataType::UInt32,
+                    Kind::F64 => DataType::Float64,
+                    Kind::Utf8 => DataType::Utf8,
+                    Kind::Bool => DataType::Boolean,
+                };
+                Field::new(name, data_type, nullable)
+            })
+            .collect();
+        Arc::new(Schema::new(fields))
+    }
+
+    /// Adds a row, returning a batch once there are `batch_size` rows that haven't been
+    /// returned yet
+    pub fn push(&mut self, e: &ExperimentConfig, result: &RunResult) -> Option<RecordBatch> {
+        for (column, value) in self.columns.iter_mut().zip(row(e, result)) {
+            column.append(value);
+        }
+        self.rows += 1;
+        (self.rows == self.batch_size).then(|| self.finish().unwrap())
+    }
+
+    /// A batch of the rows that haven't been returned yet, if there are any
+    pub fn finish(&mut self) -> Option<RecordBatch> {
+        if self.rows == 0 {
+            return None;
+        }
+        self.rows = 0;
+        let arrays = self.columns.iter_mut().map(ColumnBuilder::finish).collect();
+        Some(
+            RecordBatch::try_new(Self::schema(), arrays)
+                .expect("every column has a value for every row"),
+        )
+    }
+}
+
+/// Writes results to an Arrow IPC file as they come in, a batch at a time
+pub struct ResultIpcWriter {
+    builder: ResultBatchBuilder,
+    writer: FileWriter<File>,
+}
+
+impl ResultIpcWriter {
+    pub fn create(path: impl AsRef<Path>, batch_size: usize) -> Result<Self, ArrowError> {
+        let file = File::create(path)?;
+        Ok(Self {
+            builder: ResultBatchBuilder::new(batch_size),
+            writer: FileWriter::try_new(file, &ResultBatchBuilder::schema())?,
+        })
+    }
+
+    pub fn write(&mut self, e: &ExperimentConfig, result: &RunResult) -> Result<(), ArrowError> {
+        match self.builder.push(e, result) {
+            Some(batch) => self.writer.write(&batch),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the last partial batch and the file's footer
+    pub fn finish(mut self) -> Result<(), ArrowError> {
+        if let Some(batch) = self.builder.finish() {
+            self.writer.write(&batch)?;
+        }
+        self.writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{Array, AsArray},
+        datatypes::{Float64Type, UInt32Type},
+        ipc::reader::FileReader,
+    };
+
+    use super::*;
+    use crate::{SampleStrategy, Topology, TrialStats};
+
+    #[test]
+    fn test_schema_matches_header() {
+        let schema = ResultBatchBuilder::schema();
+        let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, ExperimentConfig::header());
+
+        // Every value is the one the CSV formats
+        let e = ExperimentConfig {
+            row_threshold: Some(40),
+            topology: Some(Topology {
+                edge_prob: 0.5,
+                rounds: 2,
+                evaluator: Evaluator::Clients { count: 3 },
+            }),
+            ..Default::default()
+        };
+        let result = TrialStats {
+            trials: 10,
+            successes: 4,
+            ..Default::default()
+        }
+        .result();
+        for ((value, csv), name) in row(&e, &result)
+            .into_iter()
+            .zip(e.to_row(&result))
+            .zip(ExperimentConfig::header())
+        {
+            let value = match value {
+                Value::U32(x) => x.map(|x| x.to_string()),
+                Value::F64(x) => x.map(|x| x.to_string()),
+                Value::Utf8(x) => x,
+                Value::Bool(x) => Some(x.to_string()),
+            };
+            match (value, csv.parse::<f64>()) {
+                // Results are `f32`s in the CSV
+                (Some(value), Ok(csv)) => {
+                    let value: f64 = value.parse().unwrap();
+                    assert!((value - csv).abs() < 1e-6, "{}", name);
+                }
+                (value, _) => assert_eq!(value.unwrap_or_default(), csv, "{}", name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_ipc() {
+        let configs = [
+            ExperimentConfig::default(),
+            ExperimentConfig {
+                n: 8,
+                ..Default::default()
+            }
+            .with_sample_strategy(SampleStrategy::Box {
+                width: 2,
+                height: 2,
+            }),
+            ExperimentConfig {
+                n_clients: 7,
+                col_threshold: Some(3),
+                ..Default::default()
+            },
+        ];
+        let results: Vec<RunResult> = (0..3)
+            .map(|successes| {
+                TrialStats {
+                    trials: 10,
+                    successes,
+                    ..Default::default()
+                }
+                .result()
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!("results-{}.arrow", std::process::id()));
+        let mut writer = ResultIpcWriter::create(&path, 2).unwrap();
+        for (e, result) in configs.iter().zip(&results) {
+            writer.write(e, result).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        assert_eq!(reader.schema(), ResultBatchBuilder::schema());
+        let batches: Vec<RecordBatch> = reader.map(|batch| batch.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+        let sizes: Vec<usize> = batches.iter().map(|b| b.num_rows()).collect();
+        assert_eq!(sizes, [2, 1]);
+
+        let column = |name: &str| {
+            let columns: Vec<ArrayRef> = batches
+                .iter()
+                .map(|b| b.column_by_name(name).unwrap().clone())
+                .collect();
+            columns
+        };
+        let n: Vec<u32> = column("n")
+            .iter()
+            .flat_map(|a| a.as_primitive::<UInt32Type>().values().to_vec())
+            .collect();
+        assert_eq!(n, [32, 8, 32]);
+        let n_clients: Vec<u32> = column("n_clients")
+            .iter()
+            .flat_map(|a| a.as_primitive::<UInt32Type>().values().to_vec())
+            .collect();
+        assert_eq!(n_clients, [100, 100, 7]);
+        let strategies: Vec<String> = column("strategy")
+            .iter()
+            .flat_map(|a| {
+                a.as_string::<i32>()
+                    .iter()
+                    .map(|s| s.unwrap().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(strategies, ["RandomPoints", "Box", "RandomPoints"]);
+        let probs: Vec<f64> = column("prob")
+            .iter()
+            .flat_map(|a| a.as_primitive::<Float64Type>().values().to_vec())
+            .collect();
+        let expected: Vec<f64> = results.iter().map(|r| r.prob as f64).collect();
+        assert_eq!(probs, expected);
+        let col_threshold = column("col_threshold");
+        assert_eq!(col_threshold[0].null_count(), 2);
+        assert_eq!(col_threshold[1].as_primitive::<UInt32Type>().value(0), 3);
+        assert_eq!(column("edge_prob")[0].null_count(), 2);
+    }
+}
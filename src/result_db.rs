@@ -0,0 +1,204 @@
+use std::path::Path;
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+
+use crate::{ExperimentConfig, RunResult};
+
+/// Results accumulated in a SQLite database over a long study. Every distinct config is
+/// stored once in `configs`, with a column per config column of `ExperimentConfig::header`
+/// holding the text the CSV would, and every run of it is a row of `results`.
+pub struct ResultDb {
+    conn: Connection,
+}
+
+/// The config columns of `ExperimentConfig::header`, quoted for SQL
+fn config_columns() -> Vec<String> {
+    let header = ExperimentConfig::header();
+    header[..header.len() - ExperimentConfig::RESULT_COLUMNS]
+        .iter()
+        .map(|name| format!("\"{}\"", name))
+        .collect()
+}
+
+impl ResultDb {
+    /// Opens the database at `path`, creating it and its tables if they don't exist.
+    /// `":memory:"` opens a new in-memory database.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        let columns = config_columns();
+        let definitions: Vec<String> = columns
+            .iter()
+            .map(|column| format!("{} TEXT NOT NULL", column))
+            .collect();
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS configs (
+                id INTEGER PRIMARY KEY,
+                {},
+                UNIQUE ({})
+            );
+            CREATE TABLE IF NOT EXISTS results (
+                id INTEGER PRIMARY KEY,
+                config_id INTEGER NOT NULL REFERENCES configs (id),
+                successes INTEGER NOT NULL,
+                trials INTEGER NOT NULL,
+                seed INTEGER,
+                recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE INDEX IF NOT EXISTS results_config_id ON results (config_id);",
+            definitions.join(",\n"),
+            columns.join(", ")
+        ))?;
+        Ok(Self { conn })
+    }
+
+    /// Adds a run of `config`, storing the config first if it's new
+    pub fn record(
+        &mut self,
+        config: &ExperimentConfig,
+        result: &RunResult,
+    ) -> rusqlite::Result<()> {
+        self.insert(config, result, None)
+    }
+
+    /// Same as `record`, but also stores the seed the run was derived from
+    pub fn record_seeded(
+        &mut self,
+        config: &ExperimentConfig,
+        result: &RunResult,
+        seed: u64,
+    ) -> rusqlite::Result<()> {
+        self.insert(config, result, Some(seed))
+    }
+
+    fn insert(
+        &mut self,
+        config: &ExperimentConfig,
+        result: &RunResult,
+        seed: Option<u64>,
+    ) -> rusqlite::Result<()> {
+        let columns = config_columns();
+        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            &format!(
+                "INSERT INTO configs ({}) VALUES ({}) ON CONFLICT DO NOTHING",
+                columns.join(", "),
+                placeholders.join(", ")
+            ),
+            params_from_iter(config.config_row()),
+        )?;
+        let config_id = config_id(&tx, config)?.expect("the config was just inserted");
+        tx.execute(
+            "INSERT INTO results (config_id, successes, trials, seed) VALUES (?1, ?2, ?3, ?4)",
+            // SQLite has no unsigned integers, so seeds are stored as the i64 with their bits
+            params![
+                config_id,
+                result.successes as i64,
+                result.trials as i64,
+                seed.map(|seed| seed as i64)
+            ],
+        )?;
+        tx.commit()
+    }
+
+    /// The configs with no results yet, in the same order, e.g. to resume a sweep
+    pub fn missing(&self, configs: &[ExperimentConfig]) -> rusqlite::Result<Vec<ExperimentConfig>> {
+        let mut missing = Vec::new();
+        for config in configs {
+            let has_results = match config_id(&self.conn, config)? {
+                Some(id) => self.conn.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM results WHERE config_id = ?1)",
+                    [id],
+                    |row| row.get(0),
+                )?,
+                None => false,
+            };
+            if !has_results {
+                missing.push(config.clone());
+            }
+        }
+        Ok(missing)
+    }
+}
+
+/// The id `config` is stored under, if it is
+fn config_id(conn: &Connection, config: &ExperimentConfig) -> rusqlite::Result<Option<i64>> {
+    let matches: Vec<String> = config_columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| format!("{} = ?{}", column, i + 1))
+        .collect();
+    conn.query_row(
+        &format!("SELECT id FROM configs WHERE {}", matches.join(" AND ")),
+        params_from_iter(config.config_row()),
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrialStats;
+
+    fn result(successes: usize) -> RunResult {
+        TrialStats {
+            trials: 10,
+            successes,
+            ..Default::default()
+        }
+        .result()
+    }
+
+    fn count(db: &ResultDb, table: &str) -> i64 {
+        db.conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut db = ResultDb::open(":memory:").unwrap();
+        let a = ExperimentConfig::default();
+        let b = ExperimentConfig {
+            n_clients: 7,
+            ..Default::default()
+        };
+        db.record(&a, &result(3)).unwrap();
+        db.record_seeded(&a, &result(4), u64::MAX).unwrap();
+        assert_eq!((count(&db, "configs"), count(&db, "results")), (1, 2));
+        db.record(&b, &result(5)).unwrap();
+        assert_eq!((count(&db, "configs"), count(&db, "results")), (2, 3));
+
+        let runs: Vec<(i64, i64, Option<i64>)> = db
+            .conn
+            .prepare("SELECT successes, trials, seed FROM results ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(runs, [(3, 10, None), (4, 10, Some(-1)), (5, 10, None)]);
+    }
+
+    #[test]
+    fn test_missing() {
+        let mut db = ResultDb::open(":memory:").unwrap();
+        let configs: Vec<ExperimentConfig> = (1..=4)
+            .map(|n_clients| ExperimentConfig {
+                n_clients,
+                ..Default::default()
+            })
+            .collect();
+        assert_eq!(db.missing(&configs).unwrap(), configs);
+        db.record(&configs[1], &result(1)).unwrap();
+        db.record(&configs[3], &result(1)).unwrap();
+        assert_eq!(
+            db.missing(&configs).unwrap(),
+            [configs[0].clone(), configs[2].clone()]
+        );
+        assert!(db.missing(&configs[3..]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+
+use crate::{entropy_seed, error::Error, ExperimentConfig, N_EXPERIMENTS};
+
+/// Trials are run in batches of `N_EXPERIMENTS` until a probability is clearly on one side
+/// of the target, or this many batches have run
+const MAX_BATCHES: usize = 16;
+
+/// How many standard errors from the target a probability has to be to stop early
+const Z: f32 = 3.0;
+
+/// Where the probability of reconstruction crosses a target as clients (or samples) are
+/// added
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CriticalPoint {
+    /// The most clients (samples) measured to fall short of the target
+    pub below: usize,
+    pub below_prob: f32,
+    /// The fewest clients (samples) measured to reach the target, always `below + 1`
+    pub above: usize,
+    pub above_prob: f32,
+}
+
+/// Where the probability of reconstruction crosses a target as each client samples more
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CriticalSamples {
+    /// The bracket in `n_samples`
+    pub point: CriticalPoint,
+    /// How many cells each honest sample covers, more than 1 for `SampleStrategy::Box` and
+    /// the other strategies that sample more than a point
+    pub cells_per_sample: usize,
+}
+
+impl CriticalSamples {
+    /// The bracket as the number of cells each honest client samples, rather than samples
+    pub fn cell_budget(&self) -> (usize, usize) {
+        (
+            self.point.below * self.cells_per_sample,
+            self.point.above * self.cells_per_sample,
+        )
+    }
+}
+
+/// Bisects `n_clients` between `lo` and `hi` for where clones of `template` first reach
+/// `target` probability, assuming the probability grows with the number of clients.
+/// Probes close to the target run more trials, so the answer isn't limited by noise.
+/// Errors unless `lo` falls short of the target and `hi` reaches it, or with
+/// `Error::InvalidConfig` if the clones with `lo` or `hi` clients can't be run.
+pub fn find_critical_clients(
+    template: &ExperimentConfig,
+    target: f32,
+    lo: usize,
+    hi: usize,
+) -> Result<CriticalPoint, Error> {
+    find_critical_clients_seeded(template, target, lo, hi, entropy_seed())
+}
+
+/// Same as `find_critical_clients`, but all randomness is derived from `seed`
+pub fn find_critical_clients_seeded(
+    template: &ExperimentConfig,
+    target: f32,
+    lo: usize,
+    hi: usize,
+    seed: u64,
+) -> Result<CriticalPoint, Error> {
+    let with_clients = |n_clients| ExperimentConfig {
+        n_clients,
+        ..template.clone()
+    };
+    bisect(with_clients, "clients", target, lo, hi, seed)
+}
+
+/// Bisects `n_samples` between 0 and `max_samples` for the fewest samples per client at
+/// which clones of `template` reach `target` probability, like `find_critical_clients`.
+/// With `SampleStrategy::Box`, `SlidingBox` or `FullLines` each sample is a whole box or set
+/// of lines, so the result also gives the bracket as a number of cells. Errors unless
+/// `max_samples` reaches the target, or if the clones can't be run.
+pub fn find_critical_samples(
+    template: &ExperimentConfig,
+    target: f32,
+    max_samples: usize,
+) -> Result<CriticalSamples, Error> {
+    find_critical_samples_seeded(template, target, max_samples, entropy_seed())
+}
+
+/// Same as `find_critical_samples`, but all randomness is derived from `seed`
+pub fn find_critical_samples_seeded(
+    template: &ExperimentConfig,
+    target: f32,
+    max_samples: usize,
+    seed: u64,
+) -> Result<CriticalSamples, Error> {
+    let with_samples = |n_samples| ExperimentConfig {
+        n_samples,
+        ..template.clone()
+    };
+    let point = bisect(with_samples, "samples", target, 0, max_samples, seed)?;
+    let (width, height) = template.sample_size(&template.honest_strategy);
+    let cells_per_sample = width * height;
+    Ok(CriticalSamples {
+        point,
+        cells_per_sample,
+    })
+}
+
+/// Bisects between `lo` and `hi` for the smallest value at which `config(value)` reaches
+/// `target`, where `what` names the values in errors
+fn bisect(
+    config: impl Fn(usize) -> ExperimentConfig,
+    what: &str,
+    target: f32,
+    lo: usize,
+    hi: usize,
+    seed: u64,
+) -> Result<CriticalPoint, Error> {
+    if !(0.0..=1.0).contains(&target) {
+        return Err(Error::InvalidFraction(target as f64));
+    }
+    if lo >= hi {
+        return Err(Error::InvalidBracket(format!(
+            "can't search between {} and {} {}",
+            lo, hi, what
+        )));
+    }
+    // Everything in between can be run if both ends can. `measure` doesn't run configs
+    // without samples, so those only have to be valid with some.
+    for value in [lo, hi] {
+        let e = config(value);
+        ExperimentConfig {
+            n_samples: e.n_samples.max(1),
+            ..e
+        }
+        .validate()
+        .map_err(Error::InvalidConfig)?;
+    }
+    // Each probe gets its own seed, so probing the same value always measures the same
+    let prob = |value: usize| measure(&config(value), target, seed.wrapping_add(value as u64));
+    let (mut lo, mut lo_prob) = (lo, prob(lo));
+    if lo_prob >= target {
+        return Err(Error::InvalidBracket(format!(
+            "{} {} already reach {} with probability {}",
+            lo, what, target, lo_prob
+        )));
+    }
+    let (mut hi, mut hi_prob) = (hi, prob(hi));
+    if hi_prob < target {
+        return Err(Error::InvalidBracket(format!(
+            "{} {} only reach probability {}, short of {}",
+            hi, what, hi_prob, target
+        )));
+    }
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        let mid_prob = prob(mid);
+        if mid_prob >= target {
+            (hi, hi_prob) = (mid, mid_prob);
+        } else {
+            (lo, lo_prob) = (mid, mid_prob);
+        }
+    }
+    Ok(CriticalPoint {
+        below: lo,
+        below_prob: lo_prob,
+        above: hi,
+        above_prob: hi_prob,
+    })
+}
+
+/// The probability for `e`, running batches of trials until it's `Z` standard errors from
+/// `target` or `MAX_BATCHES` have run
+fn measure(e: &ExperimentConfig, target: f32, seed: u64) -> f32 {
+    // Nobody samples anything without samples, and `run` refuses to try
+    if e.n_samples == 0 {
+        return 0.0;
+    }
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut total = 0.0;
+    let mut prob = 0.0;
+    for batch in 1..=MAX_BATCHES {
+        total += e.run_seeded(rng.next_u64());
+        prob = total / batch as f32;
+        let std_err = (prob * (1.0 - prob) / (batch * N_EXPERIMENTS) as f32).sqrt();
+        if (prob - target).abs() > Z * std_err {
+            break;
+        }
+    }
+    prob
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dims, SampleStrategy};
+
+    #[test]
+    fn test_find_critical_clients() {
+        // Without coding or censorship, k clients each sampling one of 16 cells find the
+        // target with probability 1 - (15/16)^k, which crosses 0.5 between 10 and 11
+        let e = ExperimentConfig {
+            n: 4,
+            dims: Dims::Zero,
+            n_samples: 1,
+            ..Default::default()
+        };
+        let point = find_critical_clients_seeded(&e, 0.5, 1, 64, 3).unwrap();
+        assert_eq!(point.above, point.below + 1);
+        assert!(point.below_prob < 0.5 && point.above_prob >= 0.5);
+        assert!((10..=12).contains(&point.above), "{:?}", point);
+        assert_eq!(find_critical_clients_seeded(&e, 0.5, 1, 64, 3), Ok(point));
+
+        for (lo, hi) in [(20, 64), (1, 4), (8, 8)] {
+            assert!(matches!(
+                find_critical_clients_seeded(&e, 0.5, lo, hi, 3),
+                Err(Error::InvalidBracket(_))
+            ));
+        }
+        assert_eq!(
+            find_critical_clients_seeded(&e, 1.5, 1, 64, 3),
+            Err(Error::InvalidFraction(1.5))
+        );
+
+        // Configs that can't be run are errors rather than panics
+        let uneven = e.clone().with_sample_strategy(SampleStrategy::Box {
+            width: 3,
+            height: 2,
+        });
+        let too_many_censored = ExperimentConfig {
+            n_censored_abs: Some(5),
+            ..e.clone()
+        };
+        for e in [uneven, too_many_censored] {
+            assert!(matches!(
+                find_critical_clients_seeded(&e, 0.5, 1, 64, 3),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_find_critical_samples() {
+        let e = ExperimentConfig {
+            n: 4,
+            dims: Dims::Zero,
+            n_clients: 1,
+            ..Default::default()
+        };
+        // One client finds the target with probability 1 - (15/16)^k from k points
+        let points = find_critical_samples_seeded(&e, 0.5, 64, 3).unwrap();
+        assert_eq!(points.cells_per_sample, 1);
+        assert!((10..=12).contains(&points.point.above), "{:?}", points);
+        assert_eq!(find_critical_samples_seeded(&e, 0.5, 64, 3), Ok(points));
+
+        // but from k of the 4 2x2 boxes with probability 1 - (3/4)^k, so 3 boxes (12 cells)
+        let boxes = find_critical_samples_seeded(
+            &e.clone().with_sample_strategy(SampleStrategy::Box {
+                width: 2,
+                height: 2,
+            }),
+            0.5,
+            64,
+            3,
+        )
+        .unwrap();
+        assert_eq!((boxes.point.below, boxes.point.above), (2, 3));
+        assert_eq!(boxes.cell_budget(), (8, 12));
+
+        assert!(matches!(
+            find_critical_samples_seeded(&e, 0.5, 4, 3),
+            Err(Error::InvalidBracket(_))
+        ));
+    }
+}
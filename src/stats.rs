@@ -0,0 +1,386 @@
+use std::{collections::HashMap, io, path::Path};
+
+use crate::{ExperimentConfig, RunResult};
+
+/// The z-score of the 95% confidence intervals reported for `prob`
+const Z_95: f64 = 1.96;
+
+/// The running mean and variance of a metric measured once per trial, updated with
+/// Welford's algorithm so the values themselves don't have to be kept
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Moments {
+    pub count: usize,
+    pub mean: f64,
+    /// The sum of squared differences from the mean
+    pub m2: f64,
+}
+
+impl Moments {
+    pub fn push(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Pools `other`'s values into these, as if they had all been pushed here
+    pub fn merge(&mut self, other: &Moments) {
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * (self.count * other.count) as f64 / count as f64;
+        self.count = count;
+    }
+
+    /// The population standard deviation, 0 without any values
+    pub fn std_dev(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            n => (self.m2 / n as f64).sqrt(),
+        }
+    }
+}
+
+/// The raw counts behind a `RunResult`. Stats from separate runs of the same config can
+/// be merged exactly, as if all their trials had been run together.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TrialStats {
+    pub trials: usize,
+    /// Trials that succeeded, see `ExperimentConfig::full_recovery`
+    pub successes: usize,
+    /// Trials in which all of the original data could be reconstructed
+    pub all_recoverable: usize,
+    /// Trials in which a random original cell, rather than the censored target, could be
+    /// reconstructed
+    pub control_successes: usize,
+    /// The fraction of censored clients that would accept the block, over the trials in
+    /// which they were simulated one by one
+    pub accept_frac: Moments,
+}
+
+impl TrialStats {
+    /// Adds one trial. `accept_frac` is the fraction of censored clients that had all
+    /// their requests answered, if that's known.
+    pub(crate) fn record(
+        &mut self,
+        success: bool,
+        all_recoverable: bool,
+        control: bool,
+        accept_frac: Option<f64>,
+    ) {
+        self.trials += 1;
+        self.successes += success as usize;
+        self.all_recoverable += all_recoverable as usize;
+        self.control_successes += control as usize;
+        if let Some(frac) = accept_frac {
+            self.accept_frac.push(frac);
+        }
+    }
+
+    /// Pools `other`'s trials into these
+    pub fn merge(&mut self, other: &TrialStats) {
+        self.trials += other.trials;
+        self.successes += other.successes;
+        self.all_recoverable += other.all_recoverable;
+        self.control_successes += other.control_successes;
+        self.accept_frac.merge(&other.accept_frac);
+    }
+
+    pub fn result(&self) -> RunResult {
+        let frac = |count: usize| count as f32 / self.trials as f32;
+        RunResult {
+            prob: frac(self.successes),
+            all_recoverable: frac(self.all_recoverable),
+            accept_frac_mean: (self.accept_frac.count > 0).then_some(self.accept_frac.mean as f32),
+            accept_frac_std: (self.accept_frac.count > 0)
+                .then_some(self.accept_frac.std_dev() as f32),
+            successes: self.successes,
+            trials: self.trials,
+            control_prob: frac(self.control_successes),
+        }
+    }
+
+    /// Reads the counts back out of a `result`, e.g. one parsed from a results CSV
+    fn from_result(result: &RunResult) -> Self {
+        let trials = result.trials;
+        let accept_frac = match result.accept_frac_mean {
+            Some(mean) => {
+                let std_dev = result.accept_frac_std.unwrap_or(0.0) as f64;
+                Moments {
+                    count: trials,
+                    mean: mean as f64,
+                    m2: std_dev * std_dev * trials as f64,
+                }
+            }
+            None => Moments::default(),
+        };
+        let count = |frac: f32| (frac as f64 * trials as f64).round() as usize;
+        TrialStats {
+            trials,
+            successes: result.successes,
+            all_recoverable: count(result.all_recoverable),
+            control_successes: count(result.control_prob),
+            accept_frac,
+        }
+    }
+}
+
+impl RunResult {
+    /// The 95% Wilson score interval for `prob`, which stays inside `[0, 1]` even when
+    /// every trial succeeded or none did. `(0, 1)` without any trials.
+    pub fn prob_interval(&self) -> (f32, f32) {
+        if self.trials == 0 {
+            return (0.0, 1.0);
+        }
+        let n = self.trials as f64;
+        let p = self.successes as f64 / n;
+        let z2 = Z_95 * Z_95;
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let half = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+        (
+            (center - half).max(0.0) as f32,
+            (center + half).min(1.0) as f32,
+        )
+    }
+
+    /// The binomial standard error of `prob`, 0 without any trials
+    pub fn prob_std_err(&self) -> f32 {
+        if self.trials == 0 {
+            return 0.0;
+        }
+        (self.prob * (1.0 - self.prob) / self.trials as f32).sqrt()
+    }
+}
+
+fn invalid(message: String) -> csv::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+/// Reads a results CSV written with `ExperimentConfig::header` and `to_row` and writes
+/// it to `output` with the rows of identical configs merged into one, in the order each
+/// config first appears. Probabilities and intervals are worked out again from the pooled
+/// `successes` and `trials`.
+pub fn merge_results_csv(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+) -> Result<(), csv::Error> {
+    let header = ExperimentConfig::header();
+    let n_config = header.len() - ExperimentConfig::RESULT_COLUMNS;
+    let mut reader = csv::Reader::from_path(input)?;
+    let found = reader.headers()?;
+    if !found.iter().eq(header.iter().copied()) {
+        return Err(invalid(format!(
+            "expected the columns {:?}, found {:?}",
+            header, found
+        )));
+    }
+    let column = |name: &str| header.iter().position(|h| *h == name).unwrap();
+
+    let mut merged: Vec<(Vec<String>, TrialStats)> = Vec::new();
+    let mut rows_by_config: HashMap<Vec<String>, usize> = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let parse = |name: &str| {
+            let value = &record[column(name)];
+            value
+                .parse::<f64>()
+                .map_err(|_| invalid(format!("bad {} {:?}", name, value)))
+        };
+        let result = RunResult {
+            prob: parse("prob")? as f32,
+            all_recoverable: parse("all_recoverable")? as f32,
+            accept_frac_mean: match &record[column("accept_frac_mean")] {
+                "" => None,
+                _ => Some(parse("accept_frac_mean")? as f32),
+            },
+            accept_frac_std: match &record[column("accept_frac_std")] {
+                "" => None,
+                _ => Some(parse("accept_frac_std")? as f32),
+            },
+            successes: parse("successes")? as usize,
+            trials: parse("trials")? as usize,
+            control_prob: parse("control_prob")? as f32,
+        };
+        let config: Vec<String> = record.iter().take(n_config).map(String::from).collect();
+        let stats = TrialStats::from_result(&result);
+        match rows_by_config.get(&config) {
+            Some(&i) => merged[i].1.merge(&stats),
+            None => {
+                rows_by_config.insert(config.clone(), merged.len());
+                merged.push((config, stats));
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_path(output)?;
+    writer.write_record(header)?;
+    for (mut row, stats) in merged {
+        row.extend(ExperimentConfig::result_row(&stats.result()));
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{grid2d::Grid2dErasure, ClientModel, Dims, N_EXPERIMENTS};
+
+    #[test]
+    fn test_merge_halves() {
+        // 20% of 20 clients, so accept fractions are exact in binary
+        let e = ExperimentConfig {
+            n: 8,
+            dims: Dims::Two,
+            n_clients: 20,
+            percent_censored: 0.2,
+            n_samples: 10,
+            client_model: ClientModel::Individual,
+            ..Default::default()
+        };
+        let (mask, target) = e.new_mask::<Grid2dErasure>(&mut SmallRng::seed_from_u64(4));
+        let stats = |trials| e.trial_stats::<Grid2dErasure>(&mask, &target, 4, trials);
+        let mut halves = stats(0..N_EXPERIMENTS / 2);
+        halves.merge(&stats(N_EXPERIMENTS / 2..N_EXPERIMENTS));
+        let full = stats(0..N_EXPERIMENTS);
+        // Merging moments can round differently than pushing every value in turn
+        let (a, b) = (halves.accept_frac, full.accept_frac);
+        assert_eq!(a.count, b.count);
+        assert!((a.mean - b.mean).abs() < 1e-12 && (a.m2 - b.m2).abs() < 1e-9);
+        let counts = |s: TrialStats| {
+            (
+                s.trials,
+                s.successes,
+                s.all_recoverable,
+                s.control_successes,
+            )
+        };
+        assert_eq!(counts(halves), counts(full));
+        assert_eq!(halves.result().prob, full.result().prob);
+        assert_eq!(full.result(), e.run_result_seeded(4));
+    }
+
+    // The mean and population standard deviation, computed from every value at once
+    fn naive_moments(values: &[f64]) -> (f64, f64) {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let var = values.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n;
+        (mean, var.sqrt())
+    }
+
+    #[test]
+    fn test_moments() {
+        let mut rng = SmallRng::seed_from_u64(5);
+        let values: Vec<f64> = (0..1000).map(|_| rng.gen::<f64>() * 10.0).collect();
+        let mut all = Moments::default();
+        for &x in &values {
+            all.push(x);
+        }
+        let (mean, std_dev) = naive_moments(&values);
+        assert_eq!(all.count, 1000);
+        assert!((all.mean - mean).abs() < 1e-9);
+        assert!((all.std_dev() - std_dev).abs() < 1e-9);
+
+        // Merging any split gives the same moments
+        for split in [0, 1, 300, 999, 1000] {
+            let (mut left, mut right) = (Moments::default(), Moments::default());
+            values[..split].iter().for_each(|&x| left.push(x));
+            values[split..].iter().for_each(|&x| right.push(x));
+            left.merge(&right);
+            assert_eq!(left.count, 1000);
+            assert!((left.mean - mean).abs() < 1e-9);
+            assert!((left.std_dev() - std_dev).abs() < 1e-9);
+        }
+
+        let mut constant = Moments::default();
+        [0.25; 10].iter().for_each(|&x| constant.push(x));
+        assert_eq!((constant.mean, constant.std_dev()), (0.25, 0.0));
+        assert_eq!(Moments::default().std_dev(), 0.0);
+    }
+
+    #[test]
+    fn test_prob_std_err() {
+        let result = TrialStats {
+            trials: 100,
+            successes: 50,
+            ..Default::default()
+        }
+        .result();
+        assert!((result.prob_std_err() - 0.05).abs() < 1e-6);
+        assert_eq!(TrialStats::default().result().prob_std_err(), 0.0);
+    }
+
+    #[test]
+    fn test_prob_interval() {
+        let result = |successes, trials| {
+            TrialStats {
+                trials,
+                successes,
+                ..Default::default()
+            }
+            .result()
+        };
+        let (low, high) = result(50, 100).prob_interval();
+        assert!((low - 0.404).abs() < 1e-3 && (high - 0.596).abs() < 1e-3);
+        assert_eq!(result(0, 100).prob_interval().0, 0.0);
+        assert_eq!(result(100, 100).prob_interval().1, 1.0);
+        // More trials, tighter interval
+        let (low, high) = result(500, 1000).prob_interval();
+        assert!(high - low < 0.07);
+        assert_eq!(result(0, 0).prob_interval(), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_merge_results_csv() {
+        let e = ExperimentConfig {
+            n: 4,
+            n_clients: 10,
+            ..Default::default()
+        };
+        let other = ExperimentConfig { n: 8, ..e.clone() };
+        let stats = |successes, all_recoverable| TrialStats {
+            trials: 100,
+            successes,
+            all_recoverable,
+            control_successes: all_recoverable + 5,
+            ..Default::default()
+        };
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("results-{}.csv", std::process::id()));
+        let output = dir.join(format!("results-merged-{}.csv", std::process::id()));
+        let mut writer = csv::Writer::from_path(&input).unwrap();
+        writer.write_record(ExperimentConfig::header()).unwrap();
+        writer
+            .write_record(e.to_row(&stats(30, 10).result()))
+            .unwrap();
+        writer
+            .write_record(other.to_row(&stats(90, 80).result()))
+            .unwrap();
+        writer
+            .write_record(e.to_row(&stats(50, 20).result()))
+            .unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        merge_results_csv(&input, &output).unwrap();
+        let text = std::fs::read_to_string(&output).unwrap();
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        let mut pooled = stats(30, 10);
+        pooled.merge(&stats(50, 20));
+        let expected = [
+            ExperimentConfig::header().join(","),
+            e.to_row(&pooled.result()).join(","),
+            other.to_row(&stats(90, 80).result()).join(","),
+        ];
+        assert_eq!(text.lines().collect::<Vec<_>>(), expected);
+        assert_eq!(pooled.result().prob, 0.4);
+    }
+}
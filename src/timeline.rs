@@ -0,0 +1,354 @@
+use std::path::Path;
+
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entropy_seed, grid1_5d::Grid1_5dErasure, grid3d::Grid3dErasure, grid_raw::GridRawErasure, Dims,
+    ExperimentConfig, Grid, Grid1dErasure, Grid2dErasure, GridNdErasure, Reconstructable,
+    N_EXPERIMENTS,
+};
+
+/// Clients joining the network over time rather than all at once, as a Poisson process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Arrivals {
+    /// The expected number of clients that arrive each step. `f64::INFINITY` has every
+    /// client arrive in the first step.
+    pub rate: f64,
+    /// How many steps are simulated. Clients that would arrive later never do.
+    pub steps: usize,
+}
+
+impl Arrivals {
+    /// The step each of `n` clients arrives in, in order of arrival
+    fn arrival_steps(&self, rng: &mut SmallRng, n: usize) -> Vec<usize> {
+        assert!(self.rate >= 0.0, "arrival rate {} is negative", self.rate);
+        if self.rate == 0.0 {
+            return vec![usize::MAX; n];
+        }
+        // The gaps between arrivals are exponentially distributed
+        let mut time = 0.0;
+        (0..n)
+            .map(|_| {
+                time += -(1.0 - rng.gen::<f64>()).ln() / self.rate;
+                time.floor() as usize
+            })
+            .collect()
+    }
+}
+
+impl ExperimentConfig {
+    /// The probability the censored target can be reconstructed after each step of
+    /// `arrivals`, with every client sampling once as it arrives. Clients are always
+    /// simulated one by one, as with `ClientModel::Individual`, and `topology` isn't
+    /// supported.
+    pub fn run_timeline(&self, arrivals: &Arrivals) -> Vec<f32> {
+        self.run_timeline_with_seed(arrivals, None)
+    }
+
+    /// Same as `run_timeline`, but all randomness is derived from `seed`
+    pub fn run_timeline_seeded(&self, arrivals: &Arrivals, seed: u64) -> Vec<f32> {
+        self.run_timeline_with_seed(arrivals, Some(seed))
+    }
+
+    fn run_timeline_with_seed(&self, arrivals: &Arrivals, seed: Option<u64>) -> Vec<f32> {
+        match self.dims {
+            Dims::Zero => self.run_timeline_generic_seeded::<GridRawErasure>(arrivals, seed),
+            Dims::One => self.run_timeline_generic_seeded::<Grid1dErasure>(arrivals, seed),
+            Dims::OneAndAHalf => {
+                self.run_timeline_generic_seeded::<Grid1_5dErasure>(arrivals, seed)
+            }
+            Dims::Two => self.run_timeline_generic_seeded::<Grid2dErasure>(arrivals, seed),
+            Dims::Three => self.run_timeline_generic_seeded::<Grid3dErasure>(arrivals, seed),
+            Dims::Four => self.run_timeline_generic_seeded::<GridNdErasure<4>>(arrivals, seed),
+        }
+    }
+
+    pub fn run_timeline_generic<R: Reconstructable>(&self, arrivals: &Arrivals) -> Vec<f32> {
+        self.run_timeline_generic_seeded::<R>(arrivals, None)
+    }
+
+    /// Seeded like `run_generic`, so when every client arrives in the first step each step
+    /// matches `run_seeded` with `ClientModel::Individual`
+    fn run_timeline_generic_seeded<R: Reconstructable>(
+        &self,
+        arrivals: &Arrivals,
+        seed: Option<u64>,
+    ) -> Vec<f32> {
+        let seed = seed.unwrap_or_else(entropy_seed);
+        let (mask, target) = self.new_mask::<R>(&mut SmallRng::seed_from_u64(seed));
+
+        let mut recon_counts = vec![0; arrivals.steps];
+        for trial in 0..N_EXPERIMENTS {
+            let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(trial as u64 + 1));
+            let clients = self.arriving_clients::<R>(&mut rng, &mask);
+            let arrival_steps = arrivals.arrival_steps(&mut rng, clients.len());
+
+            let mut pool = self.new_grid::<R>();
+            let mut arrived = clients.iter().zip(arrival_steps).peekable();
+            for (step, recon_count) in recon_counts.iter_mut().enumerate() {
+                while let Some((client, _)) = arrived.next_if(|(_, at)| *at <= step) {
+                    pool.merge_from(client);
+                }
+                *recon_count += self.pool_succeeded(&mut rng, &pool, &target) as i32;
+            }
+        }
+        recon_counts
+            .into_iter()
+            .map(|count| count as f32 / N_EXPERIMENTS as f32)
+            .collect()
+    }
+
+    /// Simulates every client sampling once, returning their shared grids in the random
+    /// order they arrive in
+    fn arriving_clients<R: Reconstructable>(&self, rng: &mut SmallRng, mask: &Grid) -> Vec<R> {
+        assert!(
+            self.topology.is_none(),
+            "arrivals assume everything shared reaches everyone"
+        );
+        let mut clients = Vec::new();
+        let mut keep = |client: &R| {
+            let mut grid = self.new_grid::<R>();
+            grid.merge_from(client);
+            clients.push(grid);
+        };
+        self.censored_clients(rng, mask, &mut keep);
+        self.honest_clients(rng, &mut keep);
+        // Clients arrive in a random order, not censored ones first
+        clients.shuffle(rng);
+        clients
+    }
+
+    /// Whether the pool of grids shared so far counts as a success, like a trial in `run`
+    fn pool_succeeded<R: Reconstructable>(
+        &self,
+        rng: &mut SmallRng,
+        pool: &R,
+        target: &R::Index,
+    ) -> bool {
+        if self.error_rate > 0.0 {
+            // Which cells are wrong is drawn afresh every time the pool is checked
+            let mut checked = self.new_grid::<R>();
+            checked.merge_from(pool);
+            checked.thin(rng, 1.0 - self.error_rate);
+            self.succeeded(&checked, target)
+        } else {
+            self.succeeded(pool, target)
+        }
+    }
+
+    /// How many clients have to arrive, one at a time in a random order, before the
+    /// censored target can be reconstructed. `None` if it can't be even once every client
+    /// has arrived.
+    pub fn clients_to_reconstruct(&self) -> Option<usize> {
+        self.clients_to_reconstruct_seeded(entropy_seed())
+    }
+
+    /// Same as `clients_to_reconstruct`, but all randomness is derived from `seed`
+    pub fn clients_to_reconstruct_seeded(&self, seed: u64) -> Option<usize> {
+        match self.dims {
+            Dims::Zero => self.clients_to_reconstruct_generic::<GridRawErasure>(seed),
+            Dims::One => self.clients_to_reconstruct_generic::<Grid1dErasure>(seed),
+            Dims::OneAndAHalf => self.clients_to_reconstruct_generic::<Grid1_5dErasure>(seed),
+            Dims::Two => self.clients_to_reconstruct_generic::<Grid2dErasure>(seed),
+            Dims::Three => self.clients_to_reconstruct_generic::<Grid3dErasure>(seed),
+            Dims::Four => self.clients_to_reconstruct_generic::<GridNdErasure<4>>(seed),
+        }
+    }
+
+    fn clients_to_reconstruct_generic<R: Reconstructable>(&self, seed: u64) -> Option<usize> {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let (mask, target) = self.new_mask::<R>(&mut rng);
+        self.clients_needed::<R>(&mut rng, &mask, &target)
+    }
+
+    fn clients_needed<R: Reconstructable>(
+        &self,
+        rng: &mut SmallRng,
+        mask: &Grid,
+        target: &R::Index,
+    ) -> Option<usize> {
+        let clients = self.arriving_clients::<R>(rng, mask);
+        let mut pool = self.new_grid::<R>();
+        if self.pool_succeeded(rng, &pool, target) {
+            return Some(0);
+        }
+        for (i, client) in clients.iter().enumerate() {
+            pool.merge_from(client);
+            if self.pool_succeeded(rng, &pool, target) {
+                return Some(i + 1);
+            }
+        }
+        None
+    }
+
+    /// `clients_to_reconstruct` averaged over every trial in which the target could be
+    /// reconstructed at all, all against one mask. `None` if it never could be.
+    pub fn mean_clients_to_reconstruct(&self) -> Option<f32> {
+        self.mean_clients_to_reconstruct_seeded(entropy_seed())
+    }
+
+    /// Same as `mean_clients_to_reconstruct`, but all randomness is derived from `seed`
+    pub fn mean_clients_to_reconstruct_seeded(&self, seed: u64) -> Option<f32> {
+        match self.dims {
+            Dims::Zero => self.mean_clients_to_reconstruct_generic::<GridRawErasure>(seed),
+            Dims::One => self.mean_clients_to_reconstruct_generic::<Grid1dErasure>(seed),
+            Dims::OneAndAHalf => self.mean_clients_to_reconstruct_generic::<Grid1_5dErasure>(seed),
+            Dims::Two => self.mean_clients_to_reconstruct_generic::<Grid2dErasure>(seed),
+            Dims::Three => self.mean_clients_to_reconstruct_generic::<Grid3dErasure>(seed),
+            Dims::Four => self.mean_clients_to_reconstruct_generic::<GridNdErasure<4>>(seed),
+        }
+    }
+
+    fn mean_clients_to_reconstruct_generic<R: Reconstructable>(&self, seed: u64) -> Option<f32> {
+        let (mask, target) = self.new_mask::<R>(&mut SmallRng::seed_from_u64(seed));
+        let needed: Vec<usize> = (0..N_EXPERIMENTS)
+            .filter_map(|trial| {
+                let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(trial as u64 + 1));
+                self.clients_needed::<R>(&mut rng, &mask, &target)
+            })
+            .collect();
+        match needed.len() {
+            0 => None,
+            n => Some(needed.iter().sum::<usize>() as f32 / n as f32),
+        }
+    }
+
+    /// The columns of `write_timeline_csv`: `header` with `step` and `prob` in place of
+    /// the results
+    pub fn timeline_header() -> Vec<&'static str> {
+        let header = Self::header();
+        let mut columns = header[..header.len() - Self::RESULT_COLUMNS].to_vec();
+        columns.extend(["step", "prob"]);
+        columns
+    }
+}
+
+/// Writes each config's timeline from `run_timeline` in long format, one row per config
+/// and step
+pub fn write_timeline_csv(
+    path: impl AsRef<Path>,
+    timelines: &[(ExperimentConfig, Vec<f32>)],
+) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(ExperimentConfig::timeline_header())?;
+    for (e, probs) in timelines {
+        for (step, prob) in probs.iter().enumerate() {
+            let mut row = e.config_row();
+            row.push(step.to_string());
+            row.push(format!("{:.10}", prob));
+            writer.write_record(row)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClientModel;
+
+    fn config() -> ExperimentConfig {
+        ExperimentConfig {
+            n: 4,
+            n_clients: 20,
+            percent_censored: 0.2,
+            n_samples: 5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_all_at_once() {
+        let arrivals = Arrivals {
+            rate: f64::INFINITY,
+            steps: 3,
+        };
+        let individual = ExperimentConfig {
+            client_model: ClientModel::Individual,
+            ..config()
+        };
+        let single_shot = individual.run_seeded(5);
+        assert_eq!(
+            config().run_timeline_seeded(&arrivals, 5),
+            vec![single_shot; 3]
+        );
+    }
+
+    #[test]
+    fn test_arrivals() {
+        let arrivals = Arrivals {
+            rate: 2.0,
+            steps: 30,
+        };
+        let probs = config().run_timeline_seeded(&arrivals, 9);
+        assert_eq!(probs.len(), 30);
+        // The pool only grows, so the chance of reconstructing from it never drops
+        assert!(
+            probs.windows(2).all(|pair| pair[0] <= pair[1]),
+            "{:?}",
+            probs
+        );
+        assert!(probs[0] < probs[29]);
+
+        let steps = arrivals.arrival_steps(&mut SmallRng::seed_from_u64(1), 1000);
+        assert!(steps.windows(2).all(|pair| pair[0] <= pair[1]));
+        // 1000 arrivals at 2 a step take about 500 steps
+        assert!((450..550).contains(&steps[999]), "{}", steps[999]);
+        let nobody = Arrivals {
+            rate: 0.0,
+            steps: 2,
+        };
+        assert_eq!(config().run_timeline_seeded(&nobody, 9), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_clients_to_reconstruct() {
+        // Without censorship, the first client to sample everything recovers the target
+        let everyone_sees_it = ExperimentConfig {
+            n: 2,
+            n_clients: 10,
+            n_samples: 100,
+            ..Default::default()
+        };
+        assert_eq!(everyone_sees_it.clients_to_reconstruct(), Some(1));
+        assert_eq!(everyone_sees_it.mean_clients_to_reconstruct(), Some(1.0));
+
+        // Fully censored clients never help
+        let all_censored = ExperimentConfig {
+            percent_censored: 1.0,
+            ..everyone_sees_it.clone()
+        };
+        assert_eq!(all_censored.clients_to_reconstruct(), None);
+        assert_eq!(all_censored.mean_clients_to_reconstruct(), None);
+
+        let e = config();
+        let needed = e.clients_to_reconstruct_seeded(4);
+        assert_eq!(needed, e.clients_to_reconstruct_seeded(4));
+        assert!(needed.unwrap_or(0) <= e.n_clients);
+        let mean = e.mean_clients_to_reconstruct_seeded(4).unwrap();
+        assert!((1.0..=20.0).contains(&mean), "{}", mean);
+    }
+
+    #[test]
+    fn test_write_timeline_csv() {
+        let e = config();
+        let timelines = vec![(e.clone(), vec![0.25, 0.5]), (e, vec![1.0])];
+        let path = std::env::temp_dir().join(format!("timeline-{}.csv", std::process::id()));
+        write_timeline_csv(&path, &timelines).unwrap();
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let header = reader.headers().unwrap().clone();
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            header.len(),
+            ExperimentConfig::header().len() - ExperimentConfig::RESULT_COLUMNS + 2
+        );
+        assert_eq!(&header[header.len() - 2], "step");
+        assert_eq!(rows.len(), 3);
+        let steps: Vec<&str> = rows.iter().map(|row| &row[row.len() - 2]).collect();
+        assert_eq!(steps, ["0", "1", "0"]);
+        assert_eq!(&rows[1][rows[1].len() - 1], "0.5000000000");
+    }
+}
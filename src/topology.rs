@@ -0,0 +1,181 @@
+use std::fmt::Display;
+
+use rand::{distributions::Bernoulli, prelude::Distribution, rngs::SmallRng, seq::index};
+use serde::{Deserialize, Serialize};
+
+use crate::traits::Reconstructable;
+
+/// Who reconstruction is checked from once the clients have gossiped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Evaluator {
+    /// A full node that doesn't sample, but is connected to the clients like any of them
+    FullNode,
+    /// Everything `count` clients picked at random have between them
+    Clients { count: usize },
+}
+
+impl Display for Evaluator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Evaluator::FullNode => write!(f, "FullNode"),
+            Evaluator::Clients { .. } => write!(f, "Clients"),
+        }
+    }
+}
+
+/// A peer to peer network where clients only exchange grids with their neighbours, rather
+/// than everything shared reaching everyone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Topology {
+    /// The probability each pair of nodes is connected, as in an Erdős–Rényi graph.
+    /// A new graph is drawn every trial, and 1 connects everyone.
+    pub edge_prob: f64,
+    /// How many rounds of exchanging grids with neighbours there are
+    pub rounds: usize,
+    pub evaluator: Evaluator,
+}
+
+impl Topology {
+    /// A random graph over `n` nodes, as a list of each node's neighbours
+    fn graph(&self, rng: &mut SmallRng, n: usize) -> Vec<Vec<usize>> {
+        let edge = Bernoulli::new(self.edge_prob)
+            .unwrap_or_else(|_| panic!("edge_prob {} isn't in [0, 1]", self.edge_prob));
+        let mut neighbours = vec![Vec::new(); n];
+        for u in 0..n {
+            for v in u + 1..n {
+                if edge.sample(rng) {
+                    neighbours[u].push(v);
+                    neighbours[v].push(u);
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Gossips the clients' grids for `rounds` rounds and returns what the evaluator ends up
+    /// with. Each round, every node merges in what its neighbours had after the last one.
+    pub(crate) fn gossip<R: Reconstructable>(
+        &self,
+        rng: &mut SmallRng,
+        mut nodes: Vec<R>,
+        new_grid: impl Fn() -> R,
+    ) -> R {
+        let n_clients = nodes.len();
+        // The full node is one more node, which starts out with nothing
+        if self.evaluator == Evaluator::FullNode {
+            nodes.push(new_grid());
+        }
+        let graph = self.graph(rng, nodes.len());
+        let mut next: Vec<R> = (0..nodes.len()).map(|_| new_grid()).collect();
+        for _ in 0..self.rounds {
+            for (v, grid) in next.iter_mut().enumerate() {
+                grid.reset();
+                grid.merge_from(&nodes[v]);
+                for &u in &graph[v] {
+                    grid.merge_from(&nodes[u]);
+                }
+            }
+            std::mem::swap(&mut nodes, &mut next);
+        }
+        match self.evaluator {
+            Evaluator::FullNode => nodes.pop().unwrap(),
+            Evaluator::Clients { count } => {
+                let mut res = new_grid();
+                for v in index::sample(rng, n_clients, count.min(n_clients)) {
+                    res.merge_from(&nodes[v]);
+                }
+                res
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{base_grid::SampleStrategy, grid2d::Grid2dErasure, traits::Shape};
+
+    fn clients(rng: &mut SmallRng, n: usize) -> Vec<Grid2dErasure> {
+        (0..n)
+            .map(|_| {
+                let mut g = Grid2dErasure::new(Shape::square(4));
+                g.sample(rng, 3, &SampleStrategy::RandomPoints);
+                g
+            })
+            .collect()
+    }
+
+    fn union(grids: &[Grid2dErasure]) -> Grid2dErasure {
+        let mut res = Grid2dErasure::new(Shape::square(4));
+        for g in grids {
+            res.merge_from(g);
+        }
+        res
+    }
+
+    #[test]
+    fn test_complete_graph() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let nodes = clients(&mut rng, 6);
+        let everything = union(&nodes);
+        let new_grid = || Grid2dErasure::new(Shape::square(4));
+        let topology = Topology {
+            edge_prob: 1.0,
+            rounds: 1,
+            evaluator: Evaluator::FullNode,
+        };
+        let seen = topology.gossip(&mut rng, clients_clone(&nodes), new_grid);
+        assert_eq!(seen, everything);
+
+        // Nothing reaches the full node without any rounds
+        let no_rounds = Topology {
+            rounds: 0,
+            ..topology.clone()
+        };
+        let seen = no_rounds.gossip(&mut rng, clients_clone(&nodes), new_grid);
+        assert_eq!(seen, new_grid());
+
+        // Without rounds, the clients only have their own samples
+        let one_client = Topology {
+            rounds: 0,
+            evaluator: Evaluator::Clients { count: 1 },
+            ..topology.clone()
+        };
+        let seen = one_client.gossip(&mut rng, clients_clone(&nodes), new_grid);
+        assert!(nodes.contains(&seen));
+        // but any client has everything after one
+        let one_client = Topology {
+            rounds: 1,
+            ..one_client
+        };
+        let seen = one_client.gossip(&mut rng, clients_clone(&nodes), new_grid);
+        assert_eq!(seen, everything);
+    }
+
+    #[test]
+    fn test_no_edges() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        let nodes = clients(&mut rng, 5);
+        let new_grid = || Grid2dErasure::new(Shape::square(4));
+        let topology = Topology {
+            edge_prob: 0.0,
+            rounds: 3,
+            evaluator: Evaluator::FullNode,
+        };
+        let seen = topology.gossip(&mut rng, clients_clone(&nodes), new_grid);
+        assert_eq!(seen, new_grid());
+        // Asking every client still finds everything
+        let all_clients = Topology {
+            evaluator: Evaluator::Clients { count: 10 },
+            ..topology
+        };
+        let seen = all_clients.gossip(&mut rng, clients_clone(&nodes), new_grid);
+        assert_eq!(seen, union(&nodes));
+    }
+
+    fn clients_clone(nodes: &[Grid2dErasure]) -> Vec<Grid2dErasure> {
+        nodes.to_vec()
+    }
+}
@@ -1,19 +1,256 @@
+use std::{fmt::Display, str::FromStr};
+
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base_grid::{Grid, MaskStrategy, SampleStrategy},
+    error::Error,
+};
+
+/// Which erasure encoding scheme a grid uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Dims {
+    /// No erasure encoding at all, a baseline where only sampled cells are available
+    Zero,
+    /// Each column is encoded on its own
+    One,
+    /// A 2d grid where only the rows are encoded
+    OneAndAHalf,
+    /// Both rows and columns are encoded
+    Two,
+    /// A cube encoded along all three axes
+    Three,
+    /// A hypercube encoded along all four axes
+    Four,
+}
+
+impl Display for Dims {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dims::Zero => write!(f, "0"),
+            Dims::One => write!(f, "1"),
+            Dims::OneAndAHalf => write!(f, "1.5"),
+            Dims::Two => write!(f, "2"),
+            Dims::Three => write!(f, "3"),
+            Dims::Four => write!(f, "4"),
+        }
+    }
+}
 
-use crate::base_grid::{Grid, SampleStrategy};
+/// Parses the same names `Display` writes, such as `1.5`
+impl FromStr for Dims {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "0" => Ok(Dims::Zero),
+            "1" => Ok(Dims::One),
+            "1.5" => Ok(Dims::OneAndAHalf),
+            "2" => Ok(Dims::Two),
+            "3" => Ok(Dims::Three),
+            "4" => Ok(Dims::Four),
+            _ => Err(format!("unknown dims {:?}", s)),
+        }
+    }
+}
+
+/// The grid encoded along `dims` axes, the inverse of `Display` for whole numbers
+impl TryFrom<usize> for Dims {
+    type Error = Error;
+
+    fn try_from(dims: usize) -> Result<Self, Error> {
+        match dims {
+            0 => Ok(Dims::Zero),
+            1 => Ok(Dims::One),
+            2 => Ok(Dims::Two),
+            3 => Ok(Dims::Three),
+            4 => Ok(Dims::Four),
+            _ => Err(Error::UnsupportedDims(dims)),
+        }
+    }
+}
+
+/// The size of the original, non-erasure encoded data, and the factor each
+/// encoded dimension is extended by. Any sizes work, not just powers of two: an extended
+/// column always needs exactly `rows` of its cells to be reconstructed, and an extended
+/// row exactly `cols`, rather than some fraction of the extended length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Shape {
+    pub rows: usize,
+    pub cols: usize,
+    pub extension: usize,
+}
+
+impl Shape {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            extension: 2,
+        }
+    }
+
+    pub fn with_extension(self, extension: usize) -> Self {
+        Self { extension, ..self }
+    }
+
+    pub fn square(n: usize) -> Self {
+        Self::new(n, n)
+    }
+}
+
+/// The fields of a serialized erasure grid, which have to be checked against each other
+/// before the grid can be rebuilt from them
+#[derive(Deserialize)]
+pub(crate) struct ErasureParts {
+    pub shape: Shape,
+    pub grid: Grid,
+}
 
 pub trait Reconstructable: Send + Sync {
     type Index: Clone + Send + Sync;
     // The dimension of the reconstruction
-    fn dims() -> usize;
+    fn dims() -> Dims;
     // Returns an index to censor and a mask representing the points which can be passed
     // to `sample_exclusion` that tells it what coordinates to exclude due to censoring
-    fn new_mask<R: RngCore>(rng: &mut R, n: usize) -> (Grid, Self::Index);
+    fn new_mask<R: RngCore>(rng: &mut R, shape: Shape) -> (Grid, Self::Index) {
+        Self::new_mask_at(shape, Self::random_index(rng, shape))
+    }
+    // Same as `new_mask`, but censors the given `target` rather than a random one, so the
+    // same mask can be rebuilt every run. Panics if `target` isn't in the original data.
+    fn new_mask_at(shape: Shape, target: Self::Index) -> (Grid, Self::Index);
+    // Same as `new_mask`, but lets the caller pick how the adversary censors the target.
+    // Errors if the strategy isn't supported or wouldn't actually hide the target.
+    fn new_mask_with<R: RngCore>(
+        rng: &mut R,
+        shape: Shape,
+        strategy: &MaskStrategy,
+    ) -> Result<(Grid, Self::Index), Error> {
+        match strategy {
+            MaskStrategy::Block => Ok(Self::new_mask(rng, shape)),
+            _ => Err(Error::InvalidStrategy(format!(
+                "{} masks are not supported in {}d grids",
+                strategy,
+                Self::dims()
+            ))),
+        }
+    }
+
+    // Same as `new_mask`, but returns the cells the adversary censors rather than the
+    // cells it leaves available
+    fn new_censor_set<R: RngCore>(rng: &mut R, shape: Shape) -> (Grid, Self::Index) {
+        let (mask, target) = Self::new_mask(rng, shape);
+        (mask.not(), target)
+    }
+
+    // A uniformly random index in the original data, as `new_mask` picks its target.
+    // Drawn directly, so it's cheap enough to draw every trial without building a mask.
+    fn random_index<R: RngCore>(rng: &mut R, shape: Shape) -> Self::Index;
 
-    fn new(n: usize) -> Self;
-    fn grid_size(&self) -> usize;
+    fn new(shape: Shape) -> Self;
+    // Same as `new`, but a row needs `row_threshold` cells to be reconstructed and a column
+    // `col_threshold`, to simulate other coding rates. `None` keeps the default. Errors if
+    // the grid doesn't support custom thresholds or they can't be met.
+    fn new_with_thresholds(
+        shape: Shape,
+        row_threshold: Option<usize>,
+        col_threshold: Option<usize>,
+    ) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        match (row_threshold, col_threshold) {
+            (None, None) => Ok(Self::new(shape)),
+            _ => Err(Error::InvalidThresholds(format!(
+                "custom reconstruction thresholds are not supported in {}d grids",
+                Self::dims()
+            ))),
+        }
+    }
+    fn shape(&self) -> Shape;
     fn can_reconstruct(&self, i: Self::Index) -> bool;
+    // Returns the grid of every cell that can be recovered from the present cells
+    fn reconstruction_closure(&self) -> Grid;
+    // The cells present, without any reconstruction
+    fn grid(&self) -> &Grid;
+    // How many reconstruction passes recover something on the way to
+    // `reconstruction_closure`. Grids that recover everything they can in one pass only
+    // need that pass, if there's anything to recover.
+    fn reconstruction_rounds(&self) -> usize {
+        (self.reconstruction_closure() != *self.grid()) as usize
+    }
+    // Whether `target` is recovered by a single reconstruction pass, without feeding what
+    // that pass recovers back in. Grids that never need more than one pass only have
+    // `can_reconstruct`.
+    fn can_reconstruct_single_pass(&self, target: Self::Index) -> bool {
+        self.can_reconstruct(target)
+    }
+    // Whether every cell of the grid can be recovered, rather than just one
+    fn can_reconstruct_all_cells(&self) -> bool {
+        let closure = self.reconstruction_closure();
+        closure.count_ones() == closure.w() * closure.h()
+    }
+    // Whether all of the original data can be recovered, which is what full nodes need.
+    // Recovered data can always be encoded again, so by default this is the same as
+    // `can_reconstruct_all_cells`.
+    fn can_reconstruct_all(&self) -> bool {
+        self.can_reconstruct_all_cells()
+    }
     fn sample<R: RngCore>(&mut self, rng: &mut R, amount: usize, strategy: &SampleStrategy);
-    fn sample_exclusion<R: RngCore>(&mut self, rng: &mut R, amount: usize, strategy: &SampleStrategy, mask: &Grid);
-    fn merge(self, other: Self) -> Self;
+    // Samples like `sample`, then drops every cell `mask` withholds. Returns how many cells
+    // were dropped, which is 0 only if every request was answered.
+    fn sample_exclusion<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        amount: usize,
+        strategy: &SampleStrategy,
+        mask: &Grid,
+    ) -> usize;
+    // Drop each present cell independently so only `keep` of them survive, modelling a
+    // client that only manages to share part of what it sampled
+    fn thin<R: RngCore>(&mut self, rng: &mut R, keep: f64);
+    // Removes every cell, leaving the grid as `new` would make it but without allocating
+    fn reset(&mut self);
+    // Adds every cell present in `other` to `self` without allocating a new grid. Panics
+    // if `other` is a different shape, see `try_merge_from`.
+    fn merge_from(&mut self, other: &Self);
+    fn merge(mut self, other: Self) -> Self
+    where
+        Self: Sized,
+    {
+        self.merge_from(&other);
+        self
+    }
+    // Same as `merge_from`, but errors rather than panicking if `other` is a different shape
+    fn try_merge_from(&mut self, other: &Self) -> Result<(), Error> {
+        let size = |grid: &Grid| (grid.w(), grid.h());
+        if self.shape() != other.shape() {
+            return Err(Error::ShapeMismatch {
+                expected: size(self.grid()),
+                got: size(other.grid()),
+            });
+        }
+        self.merge_from(other);
+        Ok(())
+    }
+    // Same as `merge`, but errors rather than panicking if `other` is a different shape
+    fn try_merge(mut self, other: Self) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        self.try_merge_from(&other)?;
+        Ok(self)
+    }
+    // Same as `merge`, but each cell only `other` has is lost with probability `loss_rate`,
+    // modelling imperfect aggregation over a gossip network
+    fn merge_lossy<R: RngCore>(mut self, mut other: Self, rng: &mut R, loss_rate: f64) -> Self
+    where
+        Self: Sized,
+    {
+        // Thinning the cells both have doesn't matter, `self` still has them
+        other.thin(rng, 1.0 - loss_rate);
+        self.merge_from(&other);
+        self
+    }
 }
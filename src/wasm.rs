@@ -0,0 +1,71 @@
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::{Error, ExperimentConfig, RunResult};
+
+/// Reads an `ExperimentConfig` from JSON, where any field that's left out keeps its
+/// default, e.g. `{"n": 8, "n_clients": 20}`. Errors if the config is invalid, see
+/// `ExperimentConfig::validate`.
+fn parse_config(json: &str) -> Result<ExperimentConfig, JsError> {
+    let fields: serde_json::Map<String, Value> = serde_json::from_str(json)?;
+    let mut config = serde_json::to_value(ExperimentConfig::default())?;
+    config
+        .as_object_mut()
+        .expect("configs serialize to objects")
+        .extend(fields);
+    let config: ExperimentConfig = serde_json::from_value(config)?;
+    config.validate().map_err(Error::InvalidConfig)?;
+    Ok(config)
+}
+
+fn to_js(result: &RunResult) -> Result<JsValue, JsError> {
+    serde_wasm_bindgen::to_value(result).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Runs the config given as JSON, see `parse_config`, and returns its `RunResult` as an
+/// object. Seeds from the browser's `crypto.getRandomValues`.
+#[wasm_bindgen]
+pub fn run_config(json: &str) -> Result<JsValue, JsError> {
+    to_js(&parse_config(json)?.run_result())
+}
+
+/// Same as `run_config`, but all randomness is derived from `seed`
+#[wasm_bindgen]
+pub fn run_config_seeded(json: &str, seed: u64) -> Result<JsValue, JsError> {
+    to_js(&parse_config(json)?.run_result_seeded(seed))
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::N_EXPERIMENTS;
+
+    fn ok<T>(result: Result<T, JsError>) -> T {
+        result.map_err(JsValue::from).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_run_config() {
+        // A 16x16 extended grid
+        let json = r#"{"n": 8, "n_clients": 20, "percent_censored": 0.2}"#;
+        let result = ok(run_config_seeded(json, 3));
+        let result: Value = serde_wasm_bindgen::from_value(result).unwrap();
+        let prob = result["prob"].as_f64().unwrap();
+        assert!((0.0..=1.0).contains(&prob));
+        assert_eq!(result["trials"], N_EXPERIMENTS);
+
+        let config = ok(parse_config(json));
+        assert_eq!((config.n, config.n_clients), (8, 20));
+        assert_eq!(config.n_samples, ExperimentConfig::default().n_samples);
+        assert_eq!(
+            prob as f32,
+            config.run_result_seeded(3).prob,
+            "the same seed runs the same trials"
+        );
+        assert!(run_config(json).is_ok());
+        assert!(run_config(r#"{"n": "eight"}"#).is_err());
+        assert!(run_config(r#"{"percent_censored": 1.5}"#).is_err());
+    }
+}
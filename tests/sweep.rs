@@ -0,0 +1,56 @@
+//! The sweep the examples run, on a tiny parameter set, through the public API only
+
+use avail_lc_sim::{run_sweep_results, ClientModel, Dims, ExperimentConfig, SampleStrategy};
+
+#[test]
+fn test_sweep_to_csv() {
+    let mut exps = Vec::new();
+    for client_model in [ClientModel::Aggregate, ClientModel::Individual] {
+        for dims in [Dims::One, Dims::Two] {
+            for percent_censored in [0.0, 0.5] {
+                let e = ExperimentConfig {
+                    n: 4,
+                    dims,
+                    n_clients: 10,
+                    percent_censored,
+                    n_samples: 4,
+                    client_model,
+                    ..Default::default()
+                }
+                .with_sample_strategy(SampleStrategy::RandomPoints);
+                e.validate().unwrap();
+                exps.push(e);
+            }
+        }
+    }
+
+    let results = run_sweep_results(exps.clone());
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(ExperimentConfig::header()).unwrap();
+    for (e, result) in &results {
+        writer.write_record(e.to_row(result)).unwrap();
+    }
+    let csv = writer.into_inner().unwrap();
+
+    let mut reader = csv::Reader::from_reader(csv.as_slice());
+    let header: Vec<String> = reader
+        .headers()
+        .unwrap()
+        .iter()
+        .map(str::to_owned)
+        .collect();
+    assert_eq!(header, ExperimentConfig::header());
+    let column = |name| header.iter().position(|h| h == name).unwrap();
+    let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+    assert_eq!(rows.len(), exps.len());
+    for ((row, e), (swept, result)) in rows.iter().zip(&exps).zip(&results) {
+        assert_eq!(swept, e);
+        assert_eq!(&row[column("n")], "4");
+        assert_eq!(&row[column("dims")], e.dims.to_string());
+        let prob: f32 = row[column("prob")].parse().unwrap();
+        assert!((prob - result.prob).abs() < 1e-6);
+        // Only censored clients simulated one by one know whether they'd accept
+        let accepts = e.client_model == ClientModel::Individual && e.percent_censored > 0.0;
+        assert_eq!(result.accept_frac_mean.is_some(), accepts);
+    }
+}